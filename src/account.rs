@@ -1,23 +1,34 @@
+use std::collections::HashMap;
 use serde::{ Deserialize, Serialize };
 use crate::client::Client;
 use anyhow::Result;
+use crate::fields::null_as_default;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Account {
     #[serde(rename = "type")]
     pub account_type: String,
     pub username: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub name: String,
-    pub github_url: String,
+    pub github_url: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl Account {
-    pub fn new(account_type: String, username: String, name: String, github_url: String) -> Self {
+    pub fn new(
+        account_type: String,
+        username: String,
+        name: String,
+        github_url: Option<String>
+    ) -> Self {
         Self {
             account_type,
             username,
             name,
             github_url,
+            extra: HashMap::new(),
         }
     }
 }
@@ -1,7 +1,9 @@
 use crate::client::Client;
-use crate::model::{ Model, ModelVersion, CreateModelOptions };
+use crate::model::{ Model, ModelVersion, CreateModelOptions, UpdateModelOptions };
 use crate::paginate::Page;
+use crate::prediction::Prediction;
 use anyhow::Result;
+use futures::stream::{ self, BoxStream, StreamExt, TryStreamExt };
 use reqwest::Method;
 use serde_json::json;
 
@@ -10,6 +12,39 @@ impl Client {
         self.fetch(Method::GET, "/models", None).await
     }
 
+    /// Lists the models belonging to `model_owner`.
+    ///
+    /// The API has no owner filter, so this reads the pages of `list_models`
+    /// and keeps the owner's models. Finding them all means walking the whole
+    /// public catalog, one request per page, so at most `max_pages` pages are
+    /// read. Pages are fetched as the stream is read.
+    pub fn list_models_by_owner<'a>(
+        &'a self,
+        model_owner: &'a str,
+        max_pages: usize
+    ) -> BoxStream<'a, Result<Model>> {
+        let start = (None, max_pages);
+        stream
+            ::try_unfold(start, move |(next, pages_left): (Option<String>, usize)| async move {
+                if pages_left == 0 {
+                    return anyhow::Ok(None);
+                }
+                let page: Page<Model> = match &next {
+                    Some(url) => self.paginate(url).await?,
+                    None if pages_left == max_pages => self.list_models().await?,
+                    None => return Ok(None),
+                };
+                let models: Vec<Result<Model>> = page.results
+                    .into_iter()
+                    .filter(|model| model.owner == model_owner)
+                    .map(Ok)
+                    .collect();
+                Ok(Some((stream::iter(models), (page.next, pages_left - 1))))
+            })
+            .try_flatten()
+            .boxed()
+    }
+
     pub async fn search_models(&self, query: &str) -> Result<Page<Model>> {
         self.fetch(Method::GET, "/models", Some(json!(query))).await
     }
@@ -24,6 +59,8 @@ impl Client {
         model_name: &str,
         options: CreateModelOptions
     ) -> Result<Model> {
+        self.validate_hardware(&options.hardware).await?;
+
         let body =
             json!({
             "owner": model_owner,
@@ -40,6 +77,40 @@ impl Client {
         self.fetch(Method::POST, "/models", Some(body)).await
     }
 
+    pub async fn update_model(
+        &self,
+        model_owner: &str,
+        model_name: &str,
+        options: UpdateModelOptions
+    ) -> Result<Model> {
+        self.fetch(
+            Method::PATCH,
+            &format!("/models/{}/{}", model_owner, model_name),
+            Some(serde_json::to_value(options)?)
+        ).await
+    }
+
+    pub async fn list_model_examples(
+        &self,
+        model_owner: &str,
+        model_name: &str
+    ) -> Result<Page<Prediction>> {
+        self.fetch(
+            Method::GET,
+            &format!("/models/{}/{}/examples", model_owner, model_name),
+            None
+        ).await
+    }
+
+    /// Returns the model's README as raw Markdown.
+    pub async fn get_model_readme(&self, model_owner: &str, model_name: &str) -> Result<String> {
+        self.fetch_text(
+            Method::GET,
+            &format!("/models/{}/{}/readme", model_owner, model_name),
+            None
+        ).await
+    }
+
     pub async fn delete_model(&self, model_owner: &str, model_name: &str) -> Result<()> {
         self.fetch(Method::DELETE, &format!("/models/{}/{}", model_owner, model_name), None).await
     }
@@ -81,34 +152,4 @@ impl Client {
             None
         ).await
     }
-
-    // pub async fn create_prediction_with_model(
-    //     &self,
-    //     model_owner: &str,
-    //     model_name: &str,
-    //     input: serde_json::Value,
-    //     webhook: Option<&Webhook>,
-    //     stream: bool
-    // ) -> Result<Prediction> {
-    //     let mut data = json!({
-    //         "input": input,
-    //     });
-
-    //     if let Some(webhook) = webhook {
-    //         data["webhook"] = json!(webhook.url);
-    //         if !webhook.events.is_empty() {
-    //             data["webhook_events_filter"] = json!(webhook.events);
-    //         }
-    //     }
-
-    //     if stream {
-    //         data["stream"] = json!(true);
-    //     }
-
-    //     self.fetch(
-    //         Method::POST,
-    //         &format!("/models/{}/{}/predictions", model_owner, model_name),
-    //         Some(data)
-    //     ).await
-    // }
 }
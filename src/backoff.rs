@@ -1,7 +1,7 @@
 use std::time::Duration;
 use rand::Rng;
 
-pub trait Backoff {
+pub trait Backoff: Send + Sync {
     fn next_delay(&self, retries: u32) -> Duration;
 }
 
@@ -0,0 +1,324 @@
+//! Running one model over many inputs.
+//!
+//! `BatchRunner` creates predictions for a stream of inputs with bounded
+//! concurrency, retries items that fail and, with a checkpoint file, appends
+//! every finished item as a JSON line so a crashed job can pick up where it
+//! stopped.
+
+use std::collections::HashMap;
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{ Result, anyhow };
+use futures::stream::{ self, BoxStream, Stream, StreamExt };
+use serde::{ Deserialize, Serialize };
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+use crate::backoff::{ Backoff, ExponentialBackoff };
+use crate::client::Client;
+use crate::error::RequestError;
+use crate::jsonl;
+use crate::prediction::{ Prediction, PredictionInput };
+use crate::request::{ PredictionRequest, Target };
+use crate::status::Status;
+
+const DEFAULT_CONCURRENCY: usize = 8;
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3600); // 1 hour
+
+/// The order in which `BatchRunner::run` yields results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchOrder {
+    /// Results come back in the order the inputs were given.
+    Input,
+    /// Results come back as soon as each item finishes.
+    Completion,
+}
+
+pub struct RetryPolicy {
+    /// Attempts per item, including the first one.
+    pub max_attempts: u32,
+    pub backoff: Box<dyn Backoff>,
+    /// Also retry predictions that ran and ended `failed`. Canceled
+    /// predictions never are.
+    ///
+    /// A prediction that couldn't be created is retried unless the API
+    /// rejected the request with a 4xx. A failed wait (a timeout, a poll
+    /// error) waits on the same prediction again rather than creating a new
+    /// one, and a prediction still running after the last attempt is
+    /// canceled.
+    pub retry_failed: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Box::new(ExponentialBackoff {
+                base: Duration::from_secs(1),
+                multiplier: 2.0,
+                jitter: Duration::ZERO,
+            }),
+            retry_failed: true,
+        }
+    }
+}
+
+/// The outcome of one input. This is also what each checkpoint line holds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResult {
+    /// Position of the input in the batch.
+    pub index: usize,
+    pub input: PredictionInput,
+    pub attempts: u32,
+    /// The last prediction created for this input, if any was.
+    pub prediction: Option<Prediction>,
+    /// Why the item failed, after its last attempt.
+    pub error: Option<String>,
+}
+
+impl BatchResult {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+pub struct BatchRunner<'a> {
+    client: &'a Client,
+    target: Target,
+    concurrency: usize,
+    order: BatchOrder,
+    retry: RetryPolicy,
+    checkpoint: Option<PathBuf>,
+    poll_interval: Duration,
+    timeout: Duration,
+}
+
+impl<'a> BatchRunner<'a> {
+    pub fn new(client: &'a Client, target: Target) -> Self {
+        Self {
+            client,
+            target,
+            concurrency: DEFAULT_CONCURRENCY,
+            order: BatchOrder::Input,
+            retry: RetryPolicy::default(),
+            checkpoint: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Maximum number of predictions in flight at once.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_order(mut self, order: BatchOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Appends each finished item to `path` as a JSON line. Items that
+    /// succeeded in an earlier run are returned from the file instead of being
+    /// run again; failed ones are retried.
+    pub fn with_checkpoint(mut self, path: impl Into<PathBuf>) -> Self {
+        self.checkpoint = Some(path.into());
+        self
+    }
+
+    pub fn with_wait_options(mut self, poll_interval: Duration, timeout: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self.timeout = timeout;
+        self
+    }
+
+    /// Runs every input and yields one result per input. An `Err` item means
+    /// the checkpoint couldn't be written or doesn't match the inputs; failed
+    /// predictions are reported through `BatchResult::error`.
+    pub async fn run<S>(&'a self, inputs: S) -> Result<BoxStream<'a, Result<BatchResult>>>
+        where S: Stream<Item = PredictionInput> + Send + 'a
+    {
+        let checkpoint = match &self.checkpoint {
+            Some(path) => Some(Arc::new(Checkpoint::open(path).await?)),
+            None => None,
+        };
+
+        let items = inputs.enumerate().map(move |(index, input)| {
+            let checkpoint = checkpoint.clone();
+            async move { self.run_item(index, input, checkpoint.as_deref()).await }
+        });
+
+        Ok(match self.order {
+            BatchOrder::Input => items.buffered(self.concurrency).boxed(),
+            BatchOrder::Completion => items.buffer_unordered(self.concurrency).boxed(),
+        })
+    }
+
+    pub async fn run_iter<I>(&'a self, inputs: I) -> Result<BoxStream<'a, Result<BatchResult>>>
+        where I: IntoIterator<Item = PredictionInput>, I::IntoIter: Send + 'a
+    {
+        self.run(stream::iter(inputs)).await
+    }
+
+    async fn run_item(
+        &self,
+        index: usize,
+        input: PredictionInput,
+        checkpoint: Option<&Checkpoint>
+    ) -> Result<BatchResult> {
+        if let Some(done) = checkpoint.and_then(|checkpoint| checkpoint.get(index)) {
+            if done.input != input {
+                return Err(
+                    anyhow!("checkpoint entry {} was recorded for a different input", index)
+                );
+            }
+            return Ok(done.clone());
+        }
+
+        let result = self.run_with_retries(index, input).await;
+        if let Some(checkpoint) = checkpoint {
+            checkpoint.record(&result).await?;
+        }
+        Ok(result)
+    }
+
+    async fn run_with_retries(&self, index: usize, input: PredictionInput) -> BatchResult {
+        let mut attempts = 0;
+        // Created by an earlier attempt whose wait failed, so still running.
+        let mut running: Option<Prediction> = None;
+
+        loop {
+            attempts += 1;
+            let outcome = match running.take() {
+                Some(prediction) => self.wait(prediction).await,
+                None => self.attempt(&input).await,
+            };
+            let (prediction, error, retryable) = match outcome {
+                Ok(prediction) if prediction.status == Status::Succeeded => {
+                    return BatchResult {
+                        index,
+                        input,
+                        attempts,
+                        prediction: Some(prediction),
+                        error: None,
+                    };
+                }
+                Ok(prediction) => {
+                    let error = format!(
+                        "prediction {} ended with status {}",
+                        prediction.id,
+                        prediction.status
+                    );
+                    let retryable = self.retry.retry_failed && prediction.status == Status::Failed;
+                    (Some(prediction), error, retryable)
+                }
+                Err(AttemptError::Create(e)) => {
+                    let rejected = e
+                        .downcast_ref::<RequestError>()
+                        .is_some_and(|e| e.status.is_client_error() && e.status != 429);
+                    (None, e.to_string(), !rejected)
+                }
+                Err(AttemptError::Wait(prediction, e)) => {
+                    running = Some(*prediction.clone());
+                    (Some(*prediction), e.to_string(), true)
+                }
+            };
+
+            if !retryable || attempts >= self.retry.max_attempts {
+                if let Some(prediction) = running {
+                    log::warn!("Canceling prediction {} for batch item {}", prediction.id, index);
+                    if let Err(e) = self.client.cancel_prediction(&prediction.id).await {
+                        log::error!("Failed to cancel prediction {}: {}", prediction.id, e);
+                    }
+                }
+                return BatchResult {
+                    index,
+                    input,
+                    attempts,
+                    prediction,
+                    error: Some(error),
+                };
+            }
+
+            log::warn!("Batch item {} failed on attempt {}, retrying: {}", index, attempts, error);
+            sleep(self.retry.backoff.next_delay(attempts - 1)).await;
+        }
+    }
+
+    async fn attempt(&self, input: &PredictionInput) -> Result<Prediction, AttemptError> {
+        let mut request = PredictionRequest::new(self.target.clone(), input.clone());
+        let waiter_webhook = self.client.webhook_waiter.as_ref().map(|waiter| waiter.webhook());
+        if let Some(webhook) = &waiter_webhook {
+            request = request.with_webhook(webhook);
+        }
+
+        let mut prediction = self.client
+            .create_prediction_from_request(request).await
+            .map_err(AttemptError::Create)?;
+        if let Some(webhook) = waiter_webhook {
+            prediction.webhook.get_or_insert(webhook.url);
+        }
+        self.wait(prediction).await
+    }
+
+    async fn wait(&self, prediction: Prediction) -> Result<Prediction, AttemptError> {
+        self.client
+            .wait_with_options(&prediction, self.poll_interval, self.timeout).await
+            .map_err(|e| AttemptError::Wait(Box::new(prediction), e))
+    }
+}
+
+/// Why an attempt didn't produce a finished prediction.
+enum AttemptError {
+    Create(anyhow::Error),
+    /// Waiting failed; the prediction may still be running.
+    Wait(Box<Prediction>, anyhow::Error),
+}
+
+/// Results recorded by an earlier run, plus the file new ones are appended to.
+pub(crate) struct Checkpoint {
+    completed: HashMap<usize, BatchResult>,
+    file: Mutex<tokio::fs::File>,
+}
+
+impl Checkpoint {
+    pub(crate) async fn open(path: &Path) -> Result<Self> {
+        let completed = parse_checkpoint(&jsonl::read(path).await?)?;
+        // Rewriting drops a line truncated by a crash, which new lines would
+        // otherwise be appended to.
+        let mut results: Vec<&BatchResult> = completed.values().collect();
+        results.sort_by_key(|result| result.index);
+        jsonl::rewrite(path, results).await?;
+        let file = jsonl::open_append(path).await?;
+
+        Ok(Self { completed, file: Mutex::new(file) })
+    }
+
+    /// The recorded result for `index`, if it succeeded. Failed items are run
+    /// again.
+    pub(crate) fn get(&self, index: usize) -> Option<&BatchResult> {
+        self.completed.get(&index).filter(|result| result.is_success())
+    }
+
+    pub(crate) async fn record(&self, result: &BatchResult) -> Result<()> {
+        jsonl::append(&mut *self.file.lock().await, result).await
+    }
+}
+
+/// Parses checkpoint lines by item index. A later line for an index replaces
+/// an earlier one.
+pub(crate) fn parse_checkpoint(contents: &str) -> Result<HashMap<usize, BatchResult>> {
+    let results: Vec<BatchResult> = jsonl::parse(contents, "checkpoint")?;
+    Ok(
+        results
+            .into_iter()
+            .map(|result| (result.index, result))
+            .collect()
+    )
+}
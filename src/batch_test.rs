@@ -0,0 +1,311 @@
+#[cfg(test)]
+mod tests {
+    use std::time::{ Duration, Instant };
+    use futures::StreamExt;
+    use reqwest::{ Method, StatusCode };
+    use serde_json::json;
+    use super::super::backoff::ConstantBackoff;
+    use super::super::batch::{
+        parse_checkpoint,
+        BatchResult,
+        BatchRunner,
+        Checkpoint,
+        RetryPolicy,
+    };
+    use super::super::prediction::PredictionInput;
+    use super::super::request::Target;
+    use super::super::status::Status;
+    use super::super::test_support::{
+        json_response,
+        prediction,
+        ScriptedTransport,
+        PREDICTION_ID,
+    };
+
+    fn result(index: usize, error: Option<&str>) -> BatchResult {
+        let prediction = prediction(PREDICTION_ID, Status::Succeeded);
+        let mut input = PredictionInput::new();
+        input.insert("prompt".to_string(), serde_json::json!(format!("item {}", index)));
+
+        BatchResult {
+            index,
+            input,
+            attempts: 1,
+            prediction: Some(prediction),
+            error: error.map(String::from),
+        }
+    }
+
+    fn line(result: &BatchResult) -> String {
+        serde_json::to_string(result).unwrap()
+    }
+
+    #[test]
+    fn test_parse_checkpoint() {
+        let contents = format!(
+            "{}\n\n{}\n",
+            line(&result(0, None)),
+            line(&result(2, Some("boom")))
+        );
+        let completed = parse_checkpoint(&contents).unwrap();
+
+        assert_eq!(completed.len(), 2);
+        assert!(completed[&0].is_success());
+        assert_eq!(completed[&2].error.as_deref(), Some("boom"));
+        assert_eq!(completed[&2].input["prompt"], "item 2");
+    }
+
+    #[test]
+    fn test_parse_checkpoint_drops_truncated_last_line() {
+        let full = line(&result(1, None));
+        let contents = format!("{}\n{}", full, &full[..full.len() / 2]);
+        let completed = parse_checkpoint(&contents).unwrap();
+
+        assert_eq!(completed.len(), 1);
+        assert!(completed.contains_key(&1));
+    }
+
+    #[test]
+    fn test_parse_checkpoint_rejects_corrupt_line() {
+        let contents = format!("{{not json\n{}\n", line(&result(0, None)));
+        assert!(parse_checkpoint(&contents).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_resumes_recorded_items() {
+        let path = std::env
+            ::temp_dir()
+            .join(format!("repli-batch-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let checkpoint = Checkpoint::open(&path).await.unwrap();
+        assert!(checkpoint.get(0).is_none());
+        checkpoint.record(&result(0, None)).await.unwrap();
+        checkpoint.record(&result(3, Some("failed"))).await.unwrap();
+        drop(checkpoint);
+
+        let resumed = Checkpoint::open(&path).await.unwrap();
+        assert!(resumed.get(0).unwrap().is_success());
+        assert!(resumed.get(3).is_none());
+        assert!(resumed.get(1).is_none());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "testing")]
+    fn inputs(count: usize) -> Vec<PredictionInput> {
+        (0..count).map(|index| result(index, None).input).collect()
+    }
+
+    /// Advances the newest unfinished prediction until `total` have been
+    /// created and all of them finished. Returns the most seen in flight.
+    #[cfg(feature = "testing")]
+    async fn drive(server: &super::super::testing::MockServer, total: usize) -> usize {
+        let mut max_in_flight = 0;
+        loop {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            let predictions = server.predictions();
+            let pending: Vec<_> = predictions
+                .iter()
+                .filter(|prediction| !prediction.status.is_terminated())
+                .collect();
+            max_in_flight = max_in_flight.max(pending.len());
+            match pending.first() {
+                Some(prediction) => {
+                    server.advance(&prediction.id).await.unwrap();
+                }
+                None if predictions.len() == total => {
+                    return max_in_flight;
+                }
+                None => {}
+            }
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_run_bounds_concurrency_and_orders_results() {
+        use super::super::batch::BatchOrder;
+        use super::super::testing::MockServer;
+
+        for (order, first) in [(BatchOrder::Input, 0), (BatchOrder::Completion, 1)] {
+            let server = MockServer::start_with_step_delay(None).await.unwrap();
+            let client = server.client();
+            let runner = BatchRunner::new(&client, Target::Version("abc".to_string()))
+                .with_concurrency(2)
+                .with_order(order)
+                .with_wait_options(Duration::from_millis(5), Duration::from_secs(10));
+
+            let results = async {
+                let results = runner.run_iter(inputs(4)).await.unwrap();
+                results.map(|result| result.unwrap()).collect::<Vec<_>>().await
+            };
+            let (results, max_in_flight) = tokio::join!(results, drive(&server, 4));
+
+            assert_eq!(max_in_flight, 2);
+            assert_eq!(results.len(), 4);
+            assert!(results.iter().all(|result| result.is_success()));
+            assert_eq!(results[0].index, first);
+            if order == BatchOrder::Input {
+                let indices: Vec<usize> = results.iter().map(|result| result.index).collect();
+                assert_eq!(indices, [0, 1, 2, 3]);
+            }
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_run_retries_failed_predictions() {
+        use std::sync::Arc;
+        use std::sync::atomic::{ AtomicU32, Ordering };
+        use super::super::testing::MockServer;
+
+        for (retry_failed, attempts) in [(true, 2), (false, 1)] {
+            let server = MockServer::start().await.unwrap();
+            let calls = Arc::new(AtomicU32::new(0));
+            let handler_calls = calls.clone();
+            server.on_predict("abc", move |_| {
+                match handler_calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err("boom".to_string()),
+                    _ => Ok(serde_json::json!("done")),
+                }
+            });
+
+            let client = server.client();
+            let runner = BatchRunner::new(&client, Target::Version("abc".to_string()))
+                .with_retry_policy(RetryPolicy {
+                    max_attempts: 3,
+                    backoff: Box::new(ConstantBackoff {
+                        base: Duration::from_millis(1),
+                        jitter: Duration::from_millis(1),
+                    }),
+                    retry_failed,
+                })
+                .with_wait_options(Duration::from_millis(5), Duration::from_secs(10));
+            let results: Vec<_> = runner.run_iter(inputs(1)).await.unwrap().collect().await;
+            let result = results[0].as_ref().unwrap();
+
+            assert_eq!(result.attempts, attempts);
+            assert_eq!(result.is_success(), retry_failed);
+            assert_eq!(server.predictions().len() as u32, attempts);
+        }
+    }
+
+    /// Runs one input against `api` with up to `max_attempts` attempts, each
+    /// waiting at most `timeout`.
+    async fn run_one(api: &ScriptedTransport, max_attempts: u32, timeout: Duration) -> BatchResult {
+        let client = api.client();
+        let runner = BatchRunner::new(&client, Target::Version("abc".to_string()))
+            .with_retry_policy(RetryPolicy {
+                max_attempts,
+                backoff: Box::new(ConstantBackoff {
+                    base: Duration::from_millis(1),
+                    jitter: Duration::from_millis(1),
+                }),
+                retry_failed: true,
+            })
+            .with_wait_options(Duration::from_millis(1), timeout);
+        let input = result(0, None).input;
+        let mut results = runner.run_iter([input]).await.unwrap();
+        results.next().await.unwrap().unwrap()
+    }
+
+    fn posts(api: &ScriptedTransport, path: &str) -> usize {
+        api.requests()
+            .iter()
+            .filter(|request| request.method == Method::POST && request.url.path() == path)
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_run_waits_again_after_wait_timeout() {
+        let started = Instant::now();
+        let api = ScriptedTransport::new(move |request| {
+            let status = match request.method {
+                Method::POST => Status::Starting,
+                _ if started.elapsed() < Duration::from_millis(150) => Status::Processing,
+                _ => Status::Succeeded,
+            };
+            json_response(StatusCode::OK, &prediction("p1", status))
+        });
+
+        let result = run_one(&api, 20, Duration::from_millis(50)).await;
+        assert!(result.is_success());
+        assert!(result.attempts > 1);
+        assert_eq!(posts(&api, "/v1/predictions"), 1);
+        assert_eq!(posts(&api, "/v1/predictions/p1/cancel"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_run_cancels_prediction_still_running() {
+        let api = ScriptedTransport::new(|request| {
+            let status = match request.url.path() {
+                "/v1/predictions/p1/cancel" => Status::Canceled,
+                "/v1/predictions" => Status::Starting,
+                _ => Status::Processing,
+            };
+            json_response(StatusCode::OK, &prediction("p1", status))
+        });
+
+        let result = run_one(&api, 2, Duration::from_millis(20)).await;
+        assert_eq!(result.attempts, 2);
+        assert!(result.error.unwrap().contains("Timeout"));
+        assert_eq!(posts(&api, "/v1/predictions"), 1);
+        assert_eq!(posts(&api, "/v1/predictions/p1/cancel"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_does_not_retry_rejected_create() {
+        let api = ScriptedTransport::new(|_| {
+            json_response(StatusCode::UNPROCESSABLE_ENTITY, &json!({ "detail": "bad input" }))
+        });
+
+        let result = run_one(&api, 3, Duration::from_secs(5)).await;
+        assert_eq!(result.attempts, 1);
+        assert!(result.error.unwrap().contains("bad input"));
+        assert_eq!(api.requests().len(), 1);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_run_resumes_after_truncated_checkpoint() {
+        use super::super::testing::MockServer;
+
+        let path = std::env
+            ::temp_dir()
+            .join(format!("repli-batch-resume-{}.jsonl", std::process::id()));
+        let truncated = line(&result(2, None));
+        let contents = format!(
+            "{}\n{}\n{}",
+            line(&result(0, None)),
+            line(&result(1, Some("boom"))),
+            &truncated[..truncated.len() / 2]
+        );
+        std::fs::write(&path, contents).unwrap();
+
+        let server = MockServer::start().await.unwrap();
+        let client = server.client();
+        let runner = BatchRunner::new(&client, Target::Version("abc".to_string()))
+            .with_checkpoint(&path)
+            .with_wait_options(Duration::from_millis(5), Duration::from_secs(10));
+        let results: Vec<BatchResult> = runner
+            .run_iter(inputs(3)).await
+            .unwrap()
+            .map(|result| result.unwrap())
+            .collect().await;
+
+        assert!(results.iter().all(|result| result.is_success()));
+        let recorded = result(0, None).prediction.unwrap();
+        assert_eq!(results[0].prediction.as_ref().unwrap().id, recorded.id);
+        assert_eq!(server.predictions().len(), 2);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        for line in contents.lines() {
+            serde_json::from_str::<BatchResult>(line).unwrap();
+        }
+        let completed = parse_checkpoint(&contents).unwrap();
+        assert!((0..3).all(|index| completed[&index].is_success()));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
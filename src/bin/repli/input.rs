@@ -0,0 +1,53 @@
+use std::path::{ Path, PathBuf };
+use anyhow::{ Result, anyhow };
+use serde_json::Value;
+use repli::{ Client, PredictionInput };
+
+/// One `-i key=value` argument.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum InputValue {
+    /// Values that parse as JSON are sent as JSON, anything else as a string.
+    Value(Value),
+    /// `key=@path` uploads the file and sends its URL.
+    Upload(PathBuf),
+}
+
+pub(crate) fn parse_input(arg: &str) -> Result<(String, InputValue)> {
+    let (key, value) = arg
+        .split_once('=')
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| anyhow!("invalid input {:?}, expected key=value", arg))?;
+
+    let value = match value.strip_prefix('@') {
+        Some(path) => InputValue::Upload(PathBuf::from(path)),
+        None =>
+            InputValue::Value(
+                serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()))
+            ),
+    };
+    Ok((key.to_string(), value))
+}
+
+/// Parses `args`, uploading any `@file` values.
+pub(crate) async fn resolve_inputs(client: &Client, args: &[String]) -> Result<PredictionInput> {
+    let mut input = PredictionInput::new();
+    for arg in args {
+        let (key, value) = parse_input(arg)?;
+        let value = match value {
+            InputValue::Value(value) => value,
+            InputValue::Upload(path) => Value::String(upload(client, &path).await?),
+        };
+        input.insert(key, value);
+    }
+    Ok(input)
+}
+
+async fn upload(client: &Client, path: &Path) -> Result<String> {
+    let file = client
+        .create_file_from_path(path, None).await
+        .map_err(|e| anyhow!("failed to upload {}: {}", path.display(), e))?;
+    file.urls
+        .get("get")
+        .cloned()
+        .ok_or_else(|| anyhow!("uploaded file {} has no URL", file.id))
+}
@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use serde_json::json;
+    use super::super::input::{ parse_input, InputValue };
+
+    fn value(arg: &str) -> InputValue {
+        parse_input(arg).unwrap().1
+    }
+
+    #[test]
+    fn test_parse_input() {
+        assert_eq!(parse_input("prompt=a cat").unwrap().0, "prompt");
+        assert_eq!(value("prompt=a cat"), InputValue::Value(json!("a cat")));
+        assert_eq!(value("seed=42"), InputValue::Value(json!(42)));
+        assert_eq!(value("hd=true"), InputValue::Value(json!(true)));
+        assert_eq!(value("sizes=[1, 2]"), InputValue::Value(json!([1, 2])));
+        assert_eq!(value("zip=007"), InputValue::Value(json!("007")));
+        assert_eq!(value("expr=a=b"), InputValue::Value(json!("a=b")));
+        assert_eq!(value("empty="), InputValue::Value(json!("")));
+        let upload = InputValue::Upload(PathBuf::from("photos/cat.jpg"));
+        assert_eq!(value("image=@photos/cat.jpg"), upload);
+    }
+
+    #[test]
+    fn test_parse_input_errors() {
+        assert!(parse_input("prompt").is_err());
+        assert!(parse_input("=value").is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! `repli`, a command-line client for the Replicate API.
+//!
+//! Built with the `cli` feature. Reads the token from `--token` or
+//! `REPLICATE_API_TOKEN`, prints tables by default and JSON with `--json`.
+//!
+//! ```text
+//! repli run black-forest-labs/flux-schnell -i prompt="a lighthouse" -i seed=42
+//! repli run acme/upscaler -i image=@photo.jpg --output-dir out
+//! repli predictions list
+//! repli deployments get acme/my-app --json
+//! ```
+
+mod input;
+mod output;
+mod input_test;
+mod output_test;
+
+use std::io::Write;
+use std::path::PathBuf;
+use anyhow::{ Result, anyhow };
+use clap::{ Parser, Subcommand };
+use futures::StreamExt;
+use repli::{ Client, Identifier, PredictionRequest, Status, Target };
+use crate::input::resolve_inputs;
+use crate::output::{ print_json, print_list, print_one, print_output };
+
+#[derive(Debug, Parser)]
+#[command(name = "repli", version, about = "Run models and manage resources on Replicate")]
+struct Cli {
+    /// API token.
+    #[arg(long, env = "REPLICATE_API_TOKEN", hide_env_values = true, global = true)]
+    token: Option<String>,
+
+    /// API base URL.
+    #[arg(long, global = true)]
+    base_url: Option<String>,
+
+    /// Print JSON instead of tables.
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run a model, wait for it to finish and download its output files.
+    Run {
+        /// "owner/name", "owner/name:version" or "deployments/owner/name".
+        model: String,
+        /// An input as key=value. Values are parsed as JSON when they can be;
+        /// key=@path uploads the file.
+        #[arg(short, long = "input", value_name = "KEY=VALUE")]
+        inputs: Vec<String>,
+        /// Where to save output files. Existing files are kept, and a name
+        /// that is taken gets a numbered suffix.
+        #[arg(long, default_value = ".")]
+        output_dir: PathBuf,
+        /// Print output URLs without downloading them.
+        #[arg(long)]
+        no_download: bool,
+    },
+    /// Run a model and print its output as it streams.
+    Stream {
+        model: String,
+        #[arg(short, long = "input", value_name = "KEY=VALUE")]
+        inputs: Vec<String>,
+    },
+    /// List, inspect and cancel predictions.
+    #[command(subcommand)]
+    Predictions(PredictionsCommand),
+    /// Look up models and their versions.
+    #[command(subcommand)]
+    Models(ModelsCommand),
+    /// Manage deployments.
+    #[command(subcommand)]
+    Deployments(DeploymentsCommand),
+    /// Upload and manage files.
+    #[command(subcommand)]
+    Files(FilesCommand),
+    /// List, inspect and cancel trainings.
+    #[command(subcommand)]
+    Trainings(TrainingsCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum PredictionsCommand {
+    List,
+    Get {
+        id: String,
+    },
+    Cancel {
+        id: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ModelsCommand {
+    List,
+    Get {
+        /// "owner/name".
+        model: String,
+    },
+    Versions {
+        model: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DeploymentsCommand {
+    List,
+    Get {
+        /// "owner/name".
+        deployment: String,
+    },
+    Delete {
+        deployment: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum FilesCommand {
+    List,
+    Get {
+        id: String,
+    },
+    Upload {
+        path: PathBuf,
+    },
+    Delete {
+        id: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TrainingsCommand {
+    List,
+    Get {
+        id: String,
+    },
+    Cancel {
+        id: String,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = execute(cli).await {
+        let broken_pipe = e
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe);
+        if !broken_pipe {
+            eprintln!("error: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn execute(cli: Cli) -> Result<()> {
+    let mut client = Client::new(cli.token)?;
+    if let Some(base_url) = cli.base_url {
+        client = client.with_base_url(base_url);
+    }
+    let json = cli.json;
+
+    match cli.command {
+        Command::Run { model, inputs, output_dir, no_download } => {
+            let input = resolve_inputs(&client, &inputs).await?;
+            let request = PredictionRequest::new(Target::parse(&model)?, input);
+            let prediction = client.create_prediction_from_request(request).await?;
+            eprintln!("Created prediction {}", prediction.id);
+
+            let prediction = client.wait(&prediction).await?;
+            if prediction.status != Status::Succeeded {
+                let error = prediction.error.map(|e| e.to_string()).unwrap_or_default();
+                let status = prediction.status;
+                return Err(anyhow!("prediction {} {}: {}", prediction.id, status, error));
+            }
+
+            let output = prediction.output.clone().unwrap_or_default();
+            if json {
+                print_json(&prediction)?;
+            } else {
+                print_output(&output)?;
+            }
+            if !no_download {
+                for file in client.download_outputs(&output, &output_dir).await? {
+                    eprintln!("Saved {}", file.path.display());
+                }
+            }
+        }
+        Command::Stream { model, inputs } => {
+            let input = resolve_inputs(&client, &inputs).await?;
+            let (events, errors) = client.stream(&model, input, None).await?;
+            let mut events = std::pin::pin!(events);
+            let mut errors = std::pin::pin!(errors);
+            let mut stdout = std::io::stdout();
+            loop {
+                let event = tokio::select! {
+                    event = events.next() => event,
+                    Some(error) = errors.next() => {
+                        return Err(error);
+                    }
+                };
+                let Some(event) = event else {
+                    break;
+                };
+                if json {
+                    writeln!(stdout, "{}", serde_json::to_string(&event)?)?;
+                } else {
+                    write!(stdout, "{}", event)?;
+                }
+                stdout.flush()?;
+            }
+            if !json {
+                writeln!(stdout)?;
+            }
+        }
+        Command::Predictions(command) =>
+            match command {
+                PredictionsCommand::List => {
+                    print_list(&client.list_predictions().await?.results, json)?;
+                }
+                PredictionsCommand::Get { id } => {
+                    print_one(&client.get_prediction(&id).await?, json)?;
+                }
+                PredictionsCommand::Cancel { id } => {
+                    print_one(&client.cancel_prediction(&id).await?, json)?;
+                }
+            }
+        Command::Models(command) =>
+            match command {
+                ModelsCommand::List => {
+                    print_list(&client.list_models().await?.results, json)?;
+                }
+                ModelsCommand::Get { model } => {
+                    let (owner, name) = owner_and_name(&model)?;
+                    print_one(&client.get_model(&owner, &name).await?, json)?;
+                }
+                ModelsCommand::Versions { model } => {
+                    let (owner, name) = owner_and_name(&model)?;
+                    print_list(&client.list_model_versions(&owner, &name).await?.results, json)?;
+                }
+            }
+        Command::Deployments(command) =>
+            match command {
+                DeploymentsCommand::List => {
+                    print_list(&client.list_deployments().await?.results, json)?;
+                }
+                DeploymentsCommand::Get { deployment } => {
+                    let (owner, name) = owner_and_name(&deployment)?;
+                    print_one(&client.get_deployment(&owner, &name).await?, json)?;
+                }
+                DeploymentsCommand::Delete { deployment } => {
+                    let (owner, name) = owner_and_name(&deployment)?;
+                    client.delete_deployment(&owner, &name).await?;
+                    eprintln!("Deleted deployment {}", deployment);
+                }
+            }
+        Command::Files(command) =>
+            match command {
+                FilesCommand::List => {
+                    print_list(&client.list_files().await?.results, json)?;
+                }
+                FilesCommand::Get { id } => {
+                    print_one(&client.get_file(&id).await?, json)?;
+                }
+                FilesCommand::Upload { path } => {
+                    print_one(&client.create_file_from_path(&path, None).await?, json)?;
+                }
+                FilesCommand::Delete { id } => {
+                    client.delete_file(&id).await?;
+                    eprintln!("Deleted file {}", id);
+                }
+            }
+        Command::Trainings(command) =>
+            match command {
+                TrainingsCommand::List => {
+                    print_list(&client.list_trainings().await?.results, json)?;
+                }
+                TrainingsCommand::Get { id } => {
+                    print_one(&client.get_training(&id).await?, json)?;
+                }
+                TrainingsCommand::Cancel { id } => {
+                    print_one(&client.cancel_training(&id).await?, json)?;
+                }
+            }
+    }
+
+    Ok(())
+}
+
+fn owner_and_name(identifier: &str) -> Result<(String, String)> {
+    let id = Identifier::parse(identifier)?;
+    if id.version.is_some() {
+        return Err(anyhow!("expected \"owner/name\" without a version, got {:?}", identifier));
+    }
+    Ok((id.owner, id.name))
+}
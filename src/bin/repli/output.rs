@@ -0,0 +1,193 @@
+use std::io::Write;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use repli::{ Deployment, File, Model, ModelVersion, Prediction, Training };
+
+/// A resource that prints as one row of a table.
+pub(crate) trait Row {
+    const HEADERS: &'static [&'static str];
+
+    fn row(&self) -> Vec<String>;
+}
+
+impl Row for Prediction {
+    const HEADERS: &'static [&'static str] = &["ID", "MODEL", "STATUS", "CREATED"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.model.clone(),
+            self.status.to_string(),
+            self.created_at.format("%Y-%m-%d %H:%M:%S").to_string()
+        ]
+    }
+}
+
+impl Row for Training {
+    const HEADERS: &'static [&'static str] = &["ID", "MODEL", "STATUS", "CREATED"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.model.clone(),
+            self.status.to_string(),
+            self.created_at.format("%Y-%m-%d %H:%M:%S").to_string()
+        ]
+    }
+}
+
+impl Row for Model {
+    const HEADERS: &'static [&'static str] = &["MODEL", "VISIBILITY", "RUNS", "DESCRIPTION"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            format!("{}/{}", self.owner, self.name),
+            self.visibility.to_string(),
+            self.run_count.to_string(),
+            truncate(self.description.as_deref().unwrap_or_default(), 60)
+        ]
+    }
+}
+
+impl Row for ModelVersion {
+    const HEADERS: &'static [&'static str] = &["ID", "COG", "CREATED"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.cog_version.clone().unwrap_or_default(),
+            self.created_at.format("%Y-%m-%d %H:%M:%S").to_string()
+        ]
+    }
+}
+
+impl Row for Deployment {
+    const HEADERS: &'static [&'static str] = &[
+        "DEPLOYMENT",
+        "RELEASE",
+        "MODEL",
+        "HARDWARE",
+        "INSTANCES",
+    ];
+
+    fn row(&self) -> Vec<String> {
+        let name = format!("{}/{}", self.owner, self.name);
+        match &self.current_release {
+            Some(release) =>
+                vec![
+                    name,
+                    release.number.to_string(),
+                    format!("{}:{}", release.model, release.version),
+                    release.configuration.hardware.to_string(),
+                    format!(
+                        "{}-{}",
+                        release.configuration.min_instances,
+                        release.configuration.max_instances
+                    )
+                ],
+            None => vec![name, String::new(), String::new(), String::new(), String::new()],
+        }
+    }
+}
+
+impl Row for File {
+    const HEADERS: &'static [&'static str] = &["ID", "NAME", "TYPE", "SIZE", "CREATED"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.name.clone(),
+            self.content_type.clone(),
+            self.size.to_string(),
+            self.created_at.format("%Y-%m-%d %H:%M:%S").to_string()
+        ]
+    }
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    let text = text.lines().next().unwrap_or_default();
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let truncated: String = text.chars().take(max - 3).collect();
+    format!("{}...", truncated)
+}
+
+/// Lays `rows` out in columns padded to the widest cell.
+pub(crate) fn render_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .map(|h| h.len())
+        .collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        padded.join("  ").trim_end().to_string()
+    };
+
+    let mut table = line(headers.to_vec());
+    for row in rows {
+        table.push('\n');
+        table.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    table
+}
+
+// `println!` panics when stdout is closed, as it is under `repli ... | head`;
+// this returns the error instead.
+macro_rules! out {
+    ($($arg:tt)*) => {
+        writeln!(std::io::stdout(), $($arg)*)
+    };
+}
+
+pub(crate) fn print_list<T: Row + Serialize>(items: &[T], json: bool) -> Result<()> {
+    if json {
+        out!("{}", serde_json::to_string_pretty(items)?)?;
+    } else {
+        let rows: Vec<_> = items
+            .iter()
+            .map(Row::row)
+            .collect();
+        out!("{}", render_table(T::HEADERS, &rows))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn print_one<T: Row + Serialize>(item: &T, json: bool) -> Result<()> {
+    if json {
+        out!("{}", serde_json::to_string_pretty(item)?)?;
+    } else {
+        out!("{}", render_table(T::HEADERS, &[item.row()]))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn print_json(value: &impl Serialize) -> Result<()> {
+    out!("{}", serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
+
+/// Prints a prediction's output: strings as they are, anything else as JSON.
+pub(crate) fn print_output(output: &Value) -> Result<()> {
+    match output {
+        Value::String(text) => out!("{}", text)?,
+        Value::Array(items) if items.iter().all(Value::is_string) => {
+            for item in items {
+                out!("{}", item.as_str().unwrap_or_default())?;
+            }
+        }
+        other => out!("{}", serde_json::to_string_pretty(other)?)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,28 @@
+#[cfg(test)]
+mod tests {
+    use super::super::output::{ render_table, Row };
+    use repli::Prediction;
+
+    #[test]
+    fn test_render_table() {
+        let rows = vec![
+            vec!["abc".to_string(), "succeeded".to_string()],
+            vec!["a-longer-id".to_string(), "failed".to_string()]
+        ];
+        assert_eq!(
+            render_table(&["ID", "STATUS"], &rows),
+            "ID           STATUS\nabc          succeeded\na-longer-id  failed"
+        );
+        assert_eq!(render_table(&["ID"], &[]), "ID");
+    }
+
+    #[test]
+    fn test_prediction_row() {
+        let prediction: Prediction = serde_json
+            ::from_str(include_str!("../../fixtures/prediction.json"))
+            .unwrap();
+        let row = prediction.row();
+        assert_eq!(row.len(), Prediction::HEADERS.len());
+        assert_eq!(row[0], prediction.id);
+    }
+}
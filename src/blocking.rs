@@ -0,0 +1,584 @@
+//! A synchronous client for code that doesn't run an async runtime.
+//!
+//! `BlockingClient` wraps a `Client` and its own tokio runtime, and mirrors
+//! the client's methods without `async`. Streams come back as iterators, and
+//! `BlockingClient::iter` walks every page of a list.
+//!
+//! ```no_run
+//! # fn example() -> anyhow::Result<()> {
+//! let client = repli::blocking::BlockingClient::new(None)?;
+//! let output = client.run("acme/upper", [("text".to_string(), "hi".into())].into(), None)?;
+//! for prediction in client.iter(client.list_predictions()?) {
+//!     println!("{}", prediction?.id);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Like `reqwest::blocking`, a `BlockingClient` must not be created, used or
+//! dropped inside an async context; the runtime panics if it is.
+
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+use anyhow::Result;
+use bytes::Bytes;
+use futures::stream::{ self, BoxStream, StreamExt };
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::runtime::Runtime;
+use crate::account::Account;
+use crate::client::Client;
+use crate::collection::Collection;
+use crate::deployment::{
+    CreateDeploymentOptions,
+    Deployment,
+    DeploymentRelease,
+    UpdateDeploymentOptions,
+};
+use crate::files::{ CreateFileOptions, File };
+use crate::hardware::{ Hardware, HardwareInfo };
+use crate::journal::{ JournalEntry, RecoveredPrediction, RecoveryPolicy };
+use crate::model::{ CreateModelOptions, Model, ModelVersion, UpdateModelOptions };
+use crate::output::DownloadedFile;
+use crate::paginate::Page;
+use crate::prediction::{ CreatePredictionParams, Prediction, PredictionInput, PredictionOutput };
+use crate::reconcile::{ ReconcileOptions, ReconcilePlan };
+use crate::request::PredictionRequest;
+use crate::rollout::{ RolloutOptions, RolloutReport };
+use crate::stream::SSEEvent;
+use crate::training::{ CreateTrainingOptions, Training };
+use crate::webhook::{ Webhook, WebhookEvent, WebhookSigningSecret };
+
+pub struct BlockingClient {
+    client: Client,
+    runtime: Runtime,
+}
+
+impl BlockingClient {
+    /// Same as `Client::new`.
+    pub fn new(auth_token: Option<String>) -> Result<Self> {
+        Self::from_client(Client::new(auth_token)?)
+    }
+
+    /// Wraps a configured `Client`.
+    pub fn from_client(client: Client) -> Result<Self> {
+        let runtime = tokio::runtime::Builder
+            ::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        Ok(Self { client, runtime })
+    }
+
+    /// The wrapped async client.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    pub fn fetch<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Value>
+    ) -> Result<T> {
+        self.block_on(self.client.fetch(method, path, body))
+    }
+
+    pub fn fetch_text(&self, method: Method, path: &str, body: Option<Value>) -> Result<String> {
+        self.block_on(self.client.fetch_text(method, path, body))
+    }
+
+    pub fn get_current_account(&self) -> Result<Account> {
+        self.block_on(self.client.get_current_account())
+    }
+
+    // Pages
+
+    pub fn paginate<T: DeserializeOwned>(&self, path: &str) -> Result<Page<T>> {
+        self.block_on(self.client.paginate(path))
+    }
+
+    pub fn paginate_next<T: DeserializeOwned>(&self, page: &Page<T>) -> Result<Option<Page<T>>> {
+        self.block_on(self.client.paginate_next(page))
+    }
+
+    /// Iterates over the results of `first` and every page after it,
+    /// fetching each page when the previous one runs out.
+    pub fn iter<T: DeserializeOwned>(&self, first: Page<T>) -> PageIter<'_, T> {
+        PageIter {
+            client: self,
+            next: first.next,
+            results: first.results.into_iter(),
+        }
+    }
+
+    // Predictions
+
+    pub fn run(
+        &self,
+        identifier: &str,
+        input: PredictionInput,
+        webhook: Option<&Webhook>
+    ) -> Result<PredictionOutput> {
+        self.block_on(self.client.run(identifier, input, webhook))
+    }
+
+    pub fn run_deployment(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str,
+        input: PredictionInput,
+        webhook: Option<&Webhook>
+    ) -> Result<PredictionOutput> {
+        self.block_on(
+            self.client.run_deployment(deployment_owner, deployment_name, input, webhook)
+        )
+    }
+
+    pub fn create_prediction(
+        &self,
+        model: Option<&str>,
+        version: Option<&str>,
+        deployment: Option<&str>,
+        input: Option<PredictionInput>,
+        params: Option<CreatePredictionParams>
+    ) -> Result<Prediction> {
+        self.block_on(self.client.create_prediction(model, version, deployment, input, params))
+    }
+
+    pub fn create_prediction_from_request(&self, request: PredictionRequest) -> Result<Prediction> {
+        self.block_on(self.client.create_prediction_from_request(request))
+    }
+
+    pub fn create_prediction_with_model(
+        &self,
+        owner: &str,
+        name: &str,
+        input: PredictionInput,
+        webhook: Option<&Webhook>,
+        stream: bool
+    ) -> Result<Prediction> {
+        self.block_on(self.client.create_prediction_with_model(owner, name, input, webhook, stream))
+    }
+
+    pub fn create_prediction_with_deployment(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str,
+        input: PredictionInput,
+        webhook: Option<&Webhook>,
+        stream: bool
+    ) -> Result<Prediction> {
+        self.block_on(
+            self.client.create_prediction_with_deployment(
+                deployment_owner,
+                deployment_name,
+                input,
+                webhook,
+                stream
+            )
+        )
+    }
+
+    pub fn list_predictions(&self) -> Result<Page<Prediction>> {
+        self.block_on(self.client.list_predictions())
+    }
+
+    pub fn get_prediction(&self, id: &str) -> Result<Prediction> {
+        self.block_on(self.client.get_prediction(id))
+    }
+
+    pub fn cancel_prediction(&self, id: &str) -> Result<Prediction> {
+        self.block_on(self.client.cancel_prediction(id))
+    }
+
+    pub fn wait(&self, prediction: &Prediction) -> Result<Prediction> {
+        self.block_on(self.client.wait(prediction))
+    }
+
+    pub fn wait_with_options(
+        &self,
+        prediction: &Prediction,
+        poll_interval: Duration,
+        timeout: Duration
+    ) -> Result<Prediction> {
+        self.block_on(self.client.wait_with_options(prediction, poll_interval, timeout))
+    }
+
+    pub fn recover_predictions(
+        &self,
+        policy: impl Fn(&JournalEntry) -> RecoveryPolicy
+    ) -> Result<Vec<RecoveredPrediction>> {
+        self.block_on(self.client.recover_predictions(policy))
+    }
+
+    /// Downloads every URL in `output` into `dir`. See
+    /// `Client::download_outputs`.
+    pub fn download_outputs(&self, output: &Value, dir: &Path) -> Result<Vec<DownloadedFile>> {
+        self.block_on(self.client.download_outputs(output, dir))
+    }
+
+    // Streams
+
+    /// Runs a prediction and iterates over its SSE events. Errors reading the
+    /// stream are yielded in between.
+    pub fn stream(
+        &self,
+        identifier: &str,
+        input: PredictionInput,
+        webhook: Option<&Webhook>
+    ) -> Result<EventIter<'_>> {
+        let (events, errors) = self.block_on(self.client.stream(identifier, input, webhook))?;
+        Ok(self.event_iter(events, errors))
+    }
+
+    pub fn stream_deployment(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str,
+        input: PredictionInput,
+        webhook: Option<&Webhook>
+    ) -> Result<EventIter<'_>> {
+        let (events, errors) = self.block_on(
+            self.client.stream_deployment(deployment_owner, deployment_name, input, webhook)
+        )?;
+        Ok(self.event_iter(events, errors))
+    }
+
+    pub fn stream_prediction(
+        &self,
+        prediction: Prediction,
+        last_event: Option<SSEEvent>
+    ) -> Result<EventIter<'_>> {
+        let (events, errors) = self.block_on(
+            self.client.stream_prediction(prediction, last_event)
+        )?;
+        Ok(self.event_iter(events, errors))
+    }
+
+    fn event_iter(
+        &self,
+        events: impl futures::Stream<Item = SSEEvent> + Send + 'static,
+        errors: impl futures::Stream<Item = anyhow::Error> + Send + 'static
+    ) -> EventIter<'_> {
+        EventIter {
+            client: self,
+            stream: stream::select(events.map(Ok), errors.map(Err)).boxed(),
+        }
+    }
+
+    // Models
+
+    pub fn list_models(&self) -> Result<Page<Model>> {
+        self.block_on(self.client.list_models())
+    }
+
+    /// Models belonging to `model_owner` from at most `max_pages` pages of
+    /// `list_models`, fetching each page as the iterator is read.
+    pub fn list_models_by_owner<'a>(
+        &'a self,
+        model_owner: &'a str,
+        max_pages: usize
+    ) -> impl Iterator<Item = Result<Model>> + 'a {
+        let mut models = self.client.list_models_by_owner(model_owner, max_pages);
+        std::iter::from_fn(move || self.block_on(models.next()))
+    }
+
+    pub fn search_models(&self, query: &str) -> Result<Page<Model>> {
+        self.block_on(self.client.search_models(query))
+    }
+
+    pub fn get_model(&self, model_owner: &str, model_name: &str) -> Result<Model> {
+        self.block_on(self.client.get_model(model_owner, model_name))
+    }
+
+    pub fn create_model(
+        &self,
+        model_owner: &str,
+        model_name: &str,
+        options: CreateModelOptions
+    ) -> Result<Model> {
+        self.block_on(self.client.create_model(model_owner, model_name, options))
+    }
+
+    pub fn update_model(
+        &self,
+        model_owner: &str,
+        model_name: &str,
+        options: UpdateModelOptions
+    ) -> Result<Model> {
+        self.block_on(self.client.update_model(model_owner, model_name, options))
+    }
+
+    pub fn delete_model(&self, model_owner: &str, model_name: &str) -> Result<()> {
+        self.block_on(self.client.delete_model(model_owner, model_name))
+    }
+
+    pub fn list_model_examples(
+        &self,
+        model_owner: &str,
+        model_name: &str
+    ) -> Result<Page<Prediction>> {
+        self.block_on(self.client.list_model_examples(model_owner, model_name))
+    }
+
+    pub fn get_model_readme(&self, model_owner: &str, model_name: &str) -> Result<String> {
+        self.block_on(self.client.get_model_readme(model_owner, model_name))
+    }
+
+    pub fn list_model_versions(
+        &self,
+        model_owner: &str,
+        model_name: &str
+    ) -> Result<Page<ModelVersion>> {
+        self.block_on(self.client.list_model_versions(model_owner, model_name))
+    }
+
+    pub fn get_model_version(
+        &self,
+        model_owner: &str,
+        model_name: &str,
+        version_id: &str
+    ) -> Result<ModelVersion> {
+        self.block_on(self.client.get_model_version(model_owner, model_name, version_id))
+    }
+
+    pub fn delete_model_version(
+        &self,
+        model_owner: &str,
+        model_name: &str,
+        version_id: &str
+    ) -> Result<()> {
+        self.block_on(self.client.delete_model_version(model_owner, model_name, version_id))
+    }
+
+    pub fn list_collections(&self) -> Result<Page<Collection>> {
+        self.block_on(self.client.list_collections())
+    }
+
+    pub fn get_collection(&self, slug: &str) -> Result<Collection> {
+        self.block_on(self.client.get_collection(slug))
+    }
+
+    pub fn list_hardware(&self) -> Result<Vec<HardwareInfo>> {
+        self.block_on(self.client.list_hardware())
+    }
+
+    pub fn validate_hardware(&self, hardware: &Hardware) -> Result<()> {
+        self.block_on(self.client.validate_hardware(hardware))
+    }
+
+    // Deployments
+
+    pub fn list_deployments(&self) -> Result<Page<Deployment>> {
+        self.block_on(self.client.list_deployments())
+    }
+
+    pub fn get_deployment(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str
+    ) -> Result<Deployment> {
+        self.block_on(self.client.get_deployment(deployment_owner, deployment_name))
+    }
+
+    pub fn create_deployment(&self, options: CreateDeploymentOptions) -> Result<Deployment> {
+        self.block_on(self.client.create_deployment(options))
+    }
+
+    pub fn update_deployment(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str,
+        options: UpdateDeploymentOptions
+    ) -> Result<Deployment> {
+        self.block_on(self.client.update_deployment(deployment_owner, deployment_name, options))
+    }
+
+    pub fn delete_deployment(&self, deployment_owner: &str, deployment_name: &str) -> Result<()> {
+        self.block_on(self.client.delete_deployment(deployment_owner, deployment_name))
+    }
+
+    pub fn deployment_releases(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str
+    ) -> Vec<DeploymentRelease> {
+        self.client.deployment_releases(deployment_owner, deployment_name)
+    }
+
+    pub fn rollout_deployment(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str,
+        update: UpdateDeploymentOptions,
+        options: RolloutOptions
+    ) -> Result<RolloutReport> {
+        self.block_on(
+            self.client.rollout_deployment(deployment_owner, deployment_name, update, options)
+        )
+    }
+
+    pub fn rollback_deployment(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str
+    ) -> Result<Deployment> {
+        self.block_on(self.client.rollback_deployment(deployment_owner, deployment_name))
+    }
+
+    pub fn plan_deployments(
+        &self,
+        desired: &[CreateDeploymentOptions],
+        prune: bool
+    ) -> Result<ReconcilePlan> {
+        self.block_on(self.client.plan_deployments(desired, prune))
+    }
+
+    pub fn apply_deployment_plan(&self, plan: &ReconcilePlan) -> Result<()> {
+        self.block_on(self.client.apply_deployment_plan(plan))
+    }
+
+    pub fn reconcile_deployments(
+        &self,
+        desired: &[CreateDeploymentOptions],
+        options: ReconcileOptions
+    ) -> Result<ReconcilePlan> {
+        self.block_on(self.client.reconcile_deployments(desired, options))
+    }
+
+    // Files
+
+    pub fn create_file_from_path(
+        &self,
+        file_path: &Path,
+        options: Option<CreateFileOptions>
+    ) -> Result<File> {
+        self.block_on(self.client.create_file_from_path(file_path, options))
+    }
+
+    pub fn create_file_from_bytes(
+        &self,
+        data: &[u8],
+        options: Option<CreateFileOptions>
+    ) -> Result<File> {
+        self.block_on(self.client.create_file_from_bytes(data, options))
+    }
+
+    pub fn list_files(&self) -> Result<Page<File>> {
+        self.block_on(self.client.list_files())
+    }
+
+    pub fn get_file(&self, file_id: &str) -> Result<File> {
+        self.block_on(self.client.get_file(file_id))
+    }
+
+    pub fn download_file(&self, file_id: &str) -> Result<Bytes> {
+        self.block_on(self.client.download_file(file_id))
+    }
+
+    pub fn delete_file(&self, file_id: &str) -> Result<()> {
+        self.block_on(self.client.delete_file(file_id))
+    }
+
+    // Trainings
+
+    pub fn create_training(
+        &self,
+        model_owner: &str,
+        model_name: &str,
+        version_id: &str,
+        options: CreateTrainingOptions
+    ) -> Result<Training> {
+        self.block_on(self.client.create_training(model_owner, model_name, version_id, options))
+    }
+
+    pub fn list_trainings(&self) -> Result<Page<Training>> {
+        self.block_on(self.client.list_trainings())
+    }
+
+    pub fn get_training(&self, training_id: &str) -> Result<Training> {
+        self.block_on(self.client.get_training(training_id))
+    }
+
+    pub fn cancel_training(&self, training_id: &str) -> Result<Training> {
+        self.block_on(self.client.cancel_training(training_id))
+    }
+
+    pub fn wait_training(&self, training: &Training) -> Result<Training> {
+        self.block_on(self.client.wait_training(training))
+    }
+
+    pub fn wait_training_with_options(
+        &self,
+        training: &Training,
+        poll_interval: Duration,
+        timeout: Duration
+    ) -> Result<Training> {
+        self.block_on(self.client.wait_training_with_options(training, poll_interval, timeout))
+    }
+
+    // Webhooks
+
+    pub fn get_default_webhook_secret(&self) -> Result<WebhookSigningSecret> {
+        self.block_on(self.client.get_default_webhook_secret())
+    }
+
+    pub fn list_webhook_events(&self) -> Result<Page<WebhookEvent>> {
+        self.block_on(self.client.list_webhook_events())
+    }
+
+    pub fn get_webhook_event(&self, event_id: &str) -> Result<WebhookEvent> {
+        self.block_on(self.client.get_webhook_event(event_id))
+    }
+}
+
+/// Every result of a paginated list. Returned by `BlockingClient::iter`.
+pub struct PageIter<'a, T> {
+    client: &'a BlockingClient,
+    next: Option<String>,
+    results: std::vec::IntoIter<T>,
+}
+
+impl<T: DeserializeOwned> Iterator for PageIter<'_, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.results.next() {
+                return Some(Ok(result));
+            }
+
+            let next = self.next.take()?;
+            match self.client.paginate::<T>(&next) {
+                Ok(page) => {
+                    self.next = page.next;
+                    self.results = page.results.into_iter();
+                }
+                Err(e) => {
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// SSE events from a streaming prediction, ending after the `done` event.
+pub struct EventIter<'a> {
+    client: &'a BlockingClient,
+    stream: BoxStream<'static, Result<SSEEvent>>,
+}
+
+impl Iterator for EventIter<'_> {
+    type Item = Result<SSEEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.client.block_on(self.stream.next())
+    }
+}
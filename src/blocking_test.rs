@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::runtime::Runtime;
+    use super::super::blocking::BlockingClient;
+    use super::super::files::CreateFileOptions;
+    use super::super::paginate::Page;
+    use super::super::prediction::{ Prediction, PredictionInput };
+    use super::super::request::{ PredictionRequest, Target };
+    use super::super::testing::MockServer;
+
+    fn input(text: &str) -> PredictionInput {
+        PredictionInput::from([("text".to_string(), json!(text))])
+    }
+
+    // The mock server runs on its own runtime so the test thread stays
+    // synchronous, as it would be in a program using the blocking client.
+    fn start() -> (Runtime, MockServer, BlockingClient) {
+        let runtime = tokio::runtime::Builder
+            ::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        let server = runtime.block_on(MockServer::start()).unwrap();
+        let client = BlockingClient::from_client(server.client()).unwrap();
+        (runtime, server, client)
+    }
+
+    #[test]
+    fn test_run_and_predictions() {
+        let (_runtime, server, client) = start();
+        server.on_predict("acme/upper", |input| {
+            Ok(json!(input["text"].as_str().unwrap_or_default().to_uppercase()))
+        });
+
+        let output = client.run("acme/upper", input("hi"), None).unwrap();
+        assert_eq!(output, json!("HI"));
+
+        let predictions = client.list_predictions().unwrap().results;
+        assert_eq!(predictions.len(), 1);
+        let prediction = client.get_prediction(&predictions[0].id).unwrap();
+        assert_eq!(prediction.output, Some(json!("HI")));
+    }
+
+    #[test]
+    fn test_files() {
+        let (_runtime, server, client) = start();
+        let options = CreateFileOptions {
+            filename: Some("hello.txt".to_string()),
+            content_type: Some("text/plain".to_string()),
+            metadata: None,
+        };
+
+        let file = client.create_file_from_bytes(b"hello", Some(options)).unwrap();
+        assert_eq!(client.get_file(&file.id).unwrap().name, "hello.txt");
+        assert_eq!(server.files()[0].1, b"hello");
+        assert_eq!(client.download_file(&file.id).unwrap(), "hello");
+
+        client.delete_file(&file.id).unwrap();
+        assert!(client.list_files().unwrap().results.is_empty());
+    }
+
+    #[test]
+    fn test_download_outputs() {
+        let (_runtime, _server, client) = start();
+        let file = client.create_file_from_bytes(b"hello", None).unwrap();
+        let dir = std::env
+            ::temp_dir()
+            .join(format!("repli-blocking-output-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let output = json!([file.urls["get"]]);
+        let downloaded = client.download_outputs(&output, &dir).unwrap();
+
+        assert_eq!(downloaded.len(), 1);
+        assert_eq!(downloaded[0].path, dir.join(&file.id));
+        assert_eq!(std::fs::read(&downloaded[0].path).unwrap(), b"hello");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_iter_follows_next_pages() {
+        let (_runtime, server, client) = start();
+        for text in ["a", "b"] {
+            let request = PredictionRequest::new(Target::Version("abc".to_string()), input(text));
+            client.create_prediction_from_request(request).unwrap();
+        }
+
+        let first = Page {
+            results: vec![client.list_predictions().unwrap().results.remove(0)],
+            next: Some(format!("{}/predictions", server.url())),
+            previous: None,
+        };
+        let ids: Vec<String> = client
+            .iter::<Prediction>(first)
+            .map(|prediction| prediction.unwrap().id)
+            .collect();
+        assert_eq!(ids.len(), 3);
+        assert_eq!(ids[0], ids[1]);
+
+        let broken = Page::<Prediction> {
+            results: Vec::new(),
+            next: Some(format!("{}/missing", server.url())),
+            previous: None,
+        };
+        let results: Vec<_> = client.iter(broken).collect();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+    }
+
+    #[test]
+    fn test_stream() {
+        let (_runtime, server, client) = start();
+        server.on_predict("acme/llm", |_| Ok(json!(["Hello", ", ", "world"])));
+
+        let events: Vec<_> = client
+            .stream("acme/llm", input("hi"), None)
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect();
+        let output: String = events
+            .iter()
+            .map(|event| event.to_string())
+            .collect();
+
+        assert_eq!(output, "Hello, world");
+        assert_eq!(events.last().unwrap().event_type, "done");
+    }
+}
@@ -0,0 +1,448 @@
+//! Caching the results of deterministic predictions.
+//!
+//! A `PredictionCache` attached with `Client::with_cache` makes `run` look
+//! up predictions against a pinned version ("owner/name:version") before
+//! creating one, keyed by the version and the input with its keys sorted.
+//! Predictions that succeed are stored on disk, one directory per entry, and
+//! optionally with their output files downloaded next to them. Runs against
+//! a model's latest version or a deployment are never cached, since what they
+//! run can change between calls.
+
+use std::collections::{ BTreeMap, HashMap };
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+use std::time::{ Duration, SystemTime };
+use anyhow::{ Result, anyhow };
+use chrono::{ DateTime, Utc };
+use reqwest::Url;
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
+use sha2::{ Digest, Sha256 };
+use tokio::sync::Mutex;
+use crate::client::Client;
+use crate::prediction::{ Prediction, PredictionInput, PredictionOutput };
+use crate::request::{ PredictionRequest, Target };
+use crate::status::Status;
+
+const ENTRY_FILE: &str = "entry.json";
+const OUTPUTS_DIR: &str = "outputs";
+// An entry without a readable entry.json may still be being written by
+// another process, so it's only deleted once it's this old.
+const UNREADABLE_GRACE: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone)]
+pub struct PredictionCache {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+    max_size: Option<u64>,
+    download_outputs: bool,
+    bypass: bool,
+    index: Arc<Mutex<Option<CacheIndex>>>,
+}
+
+/// The age and size of every entry, read from disk the first time the cache
+/// needs trimming and kept up to date by this cache and its clones after.
+#[derive(Debug, Default)]
+struct CacheIndex {
+    entries: HashMap<PathBuf, IndexedEntry>,
+    total: u64,
+}
+
+#[derive(Debug)]
+struct IndexedEntry {
+    stored_at: DateTime<Utc>,
+    size: u64,
+}
+
+impl CacheIndex {
+    fn insert(&mut self, entry_dir: PathBuf, entry: IndexedEntry) {
+        self.total += entry.size;
+        if let Some(old) = self.entries.insert(entry_dir, entry) {
+            self.total -= old.size;
+        }
+    }
+
+    fn remove(&mut self, entry_dir: &Path) {
+        if let Some(old) = self.entries.remove(entry_dir) {
+            self.total -= old.size;
+        }
+    }
+}
+
+/// A stored prediction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedPrediction {
+    pub prediction: Prediction,
+    pub stored_at: DateTime<Utc>,
+    /// Downloaded output files by the URL they came from.
+    #[serde(default)]
+    pub files: BTreeMap<String, PathBuf>,
+}
+
+impl CachedPrediction {
+    /// The prediction's output, with every downloaded URL replaced by a
+    /// `file://` URL for the local copy.
+    pub fn output(&self) -> Option<PredictionOutput> {
+        let output = self.prediction.output.as_ref()?;
+        Some(replace_urls(output, &self.files))
+    }
+}
+
+impl PredictionCache {
+    /// A cache in `dir`, which is created when the first entry is stored.
+    /// Entries never expire and the cache has no size limit by default.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl: None,
+            max_size: None,
+            download_outputs: false,
+            bypass: false,
+            index: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Ignores entries stored more than `ttl` ago and deletes them when found.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Deletes the oldest entries once everything stored, outputs included,
+    /// takes more than `bytes`. An entry bigger than that isn't kept at all.
+    pub fn with_max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Also downloads every URL in a prediction's output when storing it, and
+    /// returns `file://` URLs for the copies instead. Replicate's delivery
+    /// URLs expire after an hour, so without this cached outputs pointing at
+    /// files go stale.
+    pub fn with_download_outputs(mut self, download_outputs: bool) -> Self {
+        self.download_outputs = download_outputs;
+        self
+    }
+
+    /// Skips lookups while still storing new results, which refreshes the
+    /// entries it touches.
+    pub fn with_bypass(mut self, bypass: bool) -> Self {
+        self.bypass = bypass;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The stored prediction of `version` for `input`, if there is one that
+    /// hasn't expired.
+    pub async fn get(
+        &self,
+        version: &str,
+        input: &PredictionInput
+    ) -> Result<Option<CachedPrediction>> {
+        let entry_dir = self.entry_dir(version, input)?;
+        let Some(mut cached) = read_entry(&entry_dir).await? else {
+            return Ok(None);
+        };
+        if self.is_expired(cached.stored_at) {
+            self.remove(&entry_dir).await?;
+            return Ok(None);
+        }
+
+        for path in cached.files.values_mut() {
+            *path = entry_dir.join(OUTPUTS_DIR).join(&path);
+        }
+        Ok(Some(cached))
+    }
+
+    /// Stores `prediction` as the result of `version` for `input`, replacing
+    /// any earlier entry, then trims the cache to its size limit. Fails,
+    /// keeping nothing, if the entry alone is over the limit.
+    pub async fn put(
+        &self,
+        client: &Client,
+        version: &str,
+        input: &PredictionInput,
+        prediction: &Prediction
+    ) -> Result<CachedPrediction> {
+        if prediction.status != Status::Succeeded {
+            return Err(anyhow!("only succeeded predictions are cached"));
+        }
+
+        let entry_dir = self.entry_dir(version, input)?;
+        self.remove(&entry_dir).await?;
+        tokio::fs::create_dir_all(&entry_dir).await?;
+
+        let mut files = BTreeMap::new();
+        if let (true, Some(output)) = (self.download_outputs, &prediction.output) {
+            let outputs = entry_dir.join(OUTPUTS_DIR);
+            for file in client.download_outputs(output, &outputs).await? {
+                let name = file.path.file_name().map(PathBuf::from).unwrap_or_default();
+                files.insert(file.url, name);
+            }
+        }
+
+        let mut cached = CachedPrediction {
+            prediction: prediction.clone(),
+            stored_at: Utc::now(),
+            files,
+        };
+        let entry = serde_json::to_vec_pretty(&cached)?;
+        tokio::fs::write(entry_dir.join(ENTRY_FILE), entry).await?;
+
+        if self.ttl.is_some() || self.max_size.is_some() {
+            let size = dir_size(&entry_dir).await?;
+            if let Some(max_size) = self.max_size.filter(|max_size| size > *max_size) {
+                self.remove(&entry_dir).await?;
+                return Err(
+                    anyhow!(
+                        "prediction {} takes {} bytes, more than the cache's limit of {}",
+                        prediction.id,
+                        size,
+                        max_size
+                    )
+                );
+            }
+            let mut index = self.index().await?;
+            let index = index.get_or_insert_with(CacheIndex::default);
+            index.insert(entry_dir.clone(), IndexedEntry { stored_at: cached.stored_at, size });
+            self.evict(index).await?;
+        }
+
+        for path in cached.files.values_mut() {
+            *path = entry_dir.join(OUTPUTS_DIR).join(&path);
+        }
+        Ok(cached)
+    }
+
+    /// Deletes every entry.
+    pub async fn clear(&self) -> Result<()> {
+        *self.index.lock().await = None;
+        match tokio::fs::remove_dir_all(&self.dir).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn entry_dir(&self, version: &str, input: &PredictionInput) -> Result<PathBuf> {
+        Ok(self.dir.join(cache_key(version, input)?))
+    }
+
+    fn is_expired(&self, stored_at: DateTime<Utc>) -> bool {
+        let Some(ttl) = self.ttl else {
+            return false;
+        };
+        let age = Utc::now().signed_duration_since(stored_at);
+        age.to_std().is_ok_and(|age| age > ttl)
+    }
+
+    async fn remove(&self, entry_dir: &Path) -> Result<()> {
+        if let Some(index) = self.index.lock().await.as_mut() {
+            index.remove(entry_dir);
+        }
+        remove_entry(entry_dir).await
+    }
+
+    /// The index, loaded from disk if this is the first time it's needed.
+    async fn index(&self) -> Result<tokio::sync::MutexGuard<'_, Option<CacheIndex>>> {
+        let mut index = self.index.lock().await;
+        if index.is_none() {
+            *index = Some(self.scan().await?);
+        }
+        Ok(index)
+    }
+
+    /// Reads the age and size of every entry on disk, deleting expired ones
+    /// and ones left unreadable for longer than `UNREADABLE_GRACE`.
+    async fn scan(&self) -> Result<CacheIndex> {
+        let mut index = CacheIndex::default();
+        let mut dirs = match tokio::fs::read_dir(&self.dir).await {
+            Ok(dirs) => dirs,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(index);
+            }
+            Err(e) => {
+                return Err(e.into());
+            }
+        };
+        while let Some(dir) = dirs.next_entry().await? {
+            let metadata = dir.metadata().await?;
+            if !metadata.is_dir() {
+                continue;
+            }
+            let path = dir.path();
+            match read_entry(&path).await {
+                Ok(Some(cached)) if !self.is_expired(cached.stored_at) => {
+                    let size = dir_size(&path).await?;
+                    index.insert(path, IndexedEntry { stored_at: cached.stored_at, size });
+                }
+                Ok(Some(_)) => remove_entry(&path).await?,
+                _ => {
+                    let age = metadata
+                        .modified()
+                        .ok()
+                        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+                    if age.is_some_and(|age| age > UNREADABLE_GRACE) {
+                        remove_entry(&path).await?;
+                    }
+                }
+            }
+        }
+        Ok(index)
+    }
+
+    /// Deletes expired entries, then the oldest ones until the cache fits in
+    /// `max_size`.
+    async fn evict(&self, index: &mut CacheIndex) -> Result<()> {
+        let mut entries: Vec<(DateTime<Utc>, PathBuf)> = index.entries
+            .iter()
+            .map(|(path, entry)| (entry.stored_at, path.clone()))
+            .collect();
+        entries.sort();
+
+        for (stored_at, path) in entries {
+            let over_size = self.max_size.is_some_and(|max_size| index.total > max_size);
+            if !over_size && !self.is_expired(stored_at) {
+                continue;
+            }
+            index.remove(&path);
+            remove_entry(&path).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Hex SHA-256 of the version and the input as JSON. `serde_json` keeps object
+/// keys sorted, so inputs that differ only in key order share a key.
+pub(crate) fn cache_key(version: &str, input: &PredictionInput) -> Result<String> {
+    let input = serde_json::to_string(&serde_json::to_value(input)?)?;
+    let mut hasher = Sha256::new();
+    hasher.update(version.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(input.as_bytes());
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+async fn read_entry(entry_dir: &Path) -> Result<Option<CachedPrediction>> {
+    match tokio::fs::read(entry_dir.join(ENTRY_FILE)).await {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn remove_entry(entry_dir: &Path) -> Result<()> {
+    match tokio::fs::remove_dir_all(entry_dir).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+async fn dir_size(dir: &Path) -> Result<u64> {
+    let mut size = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                size += metadata.len();
+            }
+        }
+    }
+    Ok(size)
+}
+
+fn replace_urls(output: &Value, files: &BTreeMap<String, PathBuf>) -> Value {
+    match output {
+        Value::String(text) => {
+            let local = files.get(text).and_then(|path| Url::from_file_path(path).ok());
+            match local {
+                Some(url) => Value::String(url.to_string()),
+                None => output.clone(),
+            }
+        }
+        Value::Array(items) => {
+            Value::Array(
+                items
+                    .iter()
+                    .map(|item| replace_urls(item, files))
+                    .collect()
+            )
+        }
+        Value::Object(fields) => {
+            Value::Object(
+                fields
+                    .iter()
+                    .map(|(key, value)| (key.clone(), replace_urls(value, files)))
+                    .collect()
+            )
+        }
+        _ => output.clone(),
+    }
+}
+
+impl Client {
+    /// Looks up and stores `run` results in `cache`. A run with a webhook,
+    /// passed in or from the client's webhook waiter, always creates a
+    /// prediction so the webhook is delivered. Its output is still stored.
+    pub fn with_cache(mut self, cache: PredictionCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// The output of `request` from the client's cache, if it has one that
+    /// holds it and isn't bypassed. Requests with a webhook are never served
+    /// from the cache.
+    pub(crate) async fn cached_output(
+        &self,
+        request: &PredictionRequest
+    ) -> Option<PredictionOutput> {
+        let (cache, version) = self.cache_for(request)?;
+        if cache.bypass || request.webhook.is_some() || request.webhook_completed.is_some() {
+            return None;
+        }
+        match cache.get(version, &request.input).await {
+            Ok(cached) => {
+                let cached = cached?;
+                log::debug!("Using cached prediction {}", cached.prediction.id);
+                cached.output()
+            }
+            Err(e) => {
+                log::warn!("Failed to read prediction cache: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Stores `prediction` as the result of `request` in the client's cache,
+    /// and returns its output as the cache will return it.
+    pub(crate) async fn cache_output(
+        &self,
+        request: &PredictionRequest,
+        prediction: &Prediction
+    ) -> Option<PredictionOutput> {
+        let (cache, version) = self.cache_for(request)?;
+        match cache.put(self, version, &request.input, prediction).await {
+            Ok(cached) => cached.output(),
+            Err(e) => {
+                log::warn!("Failed to cache prediction {}: {}", prediction.id, e);
+                None
+            }
+        }
+    }
+
+    fn cache_for<'a>(
+        &'a self,
+        request: &'a PredictionRequest
+    ) -> Option<(&'a PredictionCache, &'a str)> {
+        match (&self.cache, &request.target) {
+            (Some(cache), Target::Version(version)) => Some((cache, version)),
+            _ => None,
+        }
+    }
+}
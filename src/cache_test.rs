@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use serde_json::json;
+    use super::super::cache::{ cache_key, PredictionCache };
+    use super::super::client::Client;
+    use super::super::prediction::{ Prediction, PredictionInput };
+    use super::super::status::Status;
+    use super::super::test_support;
+
+    fn input(text: &str) -> PredictionInput {
+        PredictionInput::from([
+            ("text".to_string(), json!(text)),
+            ("options".to_string(), json!({ "b": 1, "a": [1, 2] })),
+        ])
+    }
+
+    fn prediction(id: &str, status: Status) -> Prediction {
+        let mut prediction = test_support::prediction(id, status);
+        prediction.output = Some(json!(format!("output of {}", id)));
+        prediction
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env
+            ::temp_dir()
+            .join(format!("repli-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let mut reordered = PredictionInput::new();
+        reordered.insert("options".to_string(), json!({ "a": [1, 2], "b": 1 }));
+        reordered.insert("text".to_string(), json!("hi"));
+
+        let key = cache_key("abc", &input("hi")).unwrap();
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, cache_key("abc", &reordered).unwrap());
+        assert_ne!(key, cache_key("abd", &input("hi")).unwrap());
+        assert_ne!(key, cache_key("abc", &input("ho")).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_get_and_put() {
+        let client = Client::new(Some("token".to_string())).unwrap();
+        let cache = PredictionCache::new(temp_dir("put"));
+
+        assert!(cache.get("abc", &input("hi")).await.unwrap().is_none());
+        let failed = prediction("a", Status::Failed);
+        assert!(cache.put(&client, "abc", &input("hi"), &failed).await.is_err());
+
+        let done = prediction("a", Status::Succeeded);
+        cache.put(&client, "abc", &input("hi"), &done).await.unwrap();
+        let cached = cache.get("abc", &input("hi")).await.unwrap().unwrap();
+        assert_eq!(cached.prediction.id, "a");
+        assert_eq!(cached.output(), Some(json!("output of a")));
+        assert!(cache.get("abc", &input("ho")).await.unwrap().is_none());
+
+        cache.clear().await.unwrap();
+        assert!(!cache.dir().exists());
+    }
+
+    #[tokio::test]
+    async fn test_ttl_and_size_limit() {
+        let client = Client::new(Some("token".to_string())).unwrap();
+        let dir = temp_dir("evict");
+
+        let cache = PredictionCache::new(&dir).with_ttl(Duration::from_millis(1));
+        cache.put(&client, "abc", &input("a"), &prediction("a", Status::Succeeded)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(cache.get("abc", &input("a")).await.unwrap().is_none());
+
+        let cache = PredictionCache::new(&dir);
+        cache.put(&client, "abc", &input("a"), &prediction("a", Status::Succeeded)).await.unwrap();
+        let entry = dir.join(cache_key("abc", &input("a")).unwrap()).join("entry.json");
+        let size = std::fs::metadata(entry).unwrap().len();
+
+        let cache = cache.with_max_size(size * 2 + size / 2);
+        for text in ["b", "c"] {
+            let prediction = prediction(text, Status::Succeeded);
+            cache.put(&client, "abc", &input(text), &prediction).await.unwrap();
+        }
+        assert!(cache.get("abc", &input("a")).await.unwrap().is_none());
+        assert!(cache.get("abc", &input("b")).await.unwrap().is_some());
+        assert!(cache.get("abc", &input("c")).await.unwrap().is_some());
+
+        let mut huge = prediction("d", Status::Succeeded);
+        huge.output = Some(json!("x".repeat((size * 3) as usize)));
+        assert!(cache.put(&client, "abc", &input("d"), &huge).await.is_err());
+        assert!(cache.get("abc", &input("d")).await.unwrap().is_none());
+        assert!(cache.get("abc", &input("b")).await.unwrap().is_some());
+        assert!(cache.get("abc", &input("c")).await.unwrap().is_some());
+
+        cache.clear().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_evict_keeps_recent_unreadable_entries() {
+        let client = Client::new(Some("token".to_string())).unwrap();
+        let dir = temp_dir("unreadable");
+        let partial = dir.join("partial");
+        std::fs::create_dir_all(&partial).unwrap();
+        std::fs::write(partial.join("entry.json"), "{").unwrap();
+
+        let cache = PredictionCache::new(&dir).with_max_size(1 << 20);
+        cache.put(&client, "abc", &input("a"), &prediction("a", Status::Succeeded)).await.unwrap();
+        assert!(partial.exists());
+
+        cache.clear().await.unwrap();
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_run_downloads_and_reuses_outputs() {
+        use reqwest::Url;
+        use super::super::request::{ PredictionRequest, Target };
+        use super::super::testing::MockServer;
+
+        let server = MockServer::start().await.unwrap();
+        let file = server.client().create_file_from_bytes(b"hello", None).await.unwrap();
+        let url = file.urls["get"].clone();
+        server.on_predict("abc", move |_| Ok(json!({ "image": url, "seed": 42 })));
+
+        let dir = temp_dir("run");
+        let cache = PredictionCache::new(&dir).with_download_outputs(true);
+        let client = server.client().with_cache(cache.clone());
+
+        let output = client.run("acme/images:abc", input("hi"), None).await.unwrap();
+        let local = Url::parse(output["image"].as_str().unwrap()).unwrap();
+        assert_eq!(local.scheme(), "file");
+        let downloaded = std::fs::read(local.to_file_path().unwrap()).unwrap();
+        assert_eq!(downloaded, b"hello");
+        assert_eq!(output["seed"], 42);
+
+        let again = client.run("acme/images:abc", input("hi"), None).await.unwrap();
+        assert_eq!(again, output);
+        assert_eq!(server.predictions().len(), 1);
+
+        let request = PredictionRequest::new(Target::Version("abc".to_string()), input("hi"));
+        assert!(client.cached_output(&request).await.is_some());
+        let bypassed = server.client().with_cache(cache.clone().with_bypass(true));
+        assert!(bypassed.cached_output(&request).await.is_none());
+        let model = Target::Model { owner: "acme".to_string(), name: "images".to_string() };
+        assert!(client.cached_output(&PredictionRequest::new(model, input("hi"))).await.is_none());
+
+        cache.clear().await.unwrap();
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_run_with_webhook_skips_cache() {
+        use super::super::testing::MockServer;
+        use super::super::webhook::{ Webhook, WebhookEventType };
+
+        let server = MockServer::start().await.unwrap();
+        server.on_predict("abc", |input| Ok(input["text"].clone()));
+        let dir = temp_dir("webhook");
+        let cache = PredictionCache::new(&dir);
+        let client = server.client().with_cache(cache.clone());
+        let webhook = Webhook {
+            url: "https://example.com/hook".to_string(),
+            events: vec![WebhookEventType::Completed],
+        };
+
+        client.run("acme/echo:abc", input("hi"), None).await.unwrap();
+        let output = client.run("acme/echo:abc", input("hi"), Some(&webhook)).await.unwrap();
+        assert_eq!(output, json!("hi"));
+
+        let predictions = server.predictions();
+        assert_eq!(predictions.len(), 2);
+        assert!(predictions.iter().any(|p| p.webhook.as_deref() == Some(webhook.url.as_str())));
+
+        cache.clear().await.unwrap();
+    }
+}
@@ -0,0 +1,429 @@
+//! Recording and replaying HTTP sessions.
+//!
+//! A `Cassette` attached with `Client::with_cassette` either records every
+//! request the client sends, with its response, to a JSON file, or answers
+//! requests from such a file without touching the network. Streaming bodies
+//! are recorded whole, so SSE sessions replay too. Auth headers are redacted
+//! before anything is written.
+
+use std::collections::BTreeMap;
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, Mutex };
+use anyhow::{ Result, anyhow };
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::{ self, StreamExt };
+use reqwest::header::{ HeaderMap, HeaderName, HeaderValue };
+use reqwest::{ StatusCode, Url };
+use serde::{ Deserialize, Serialize };
+use crate::transport::{ HttpRequest, HttpResponse, Transport };
+
+const REDACTED: &str = "[REDACTED]";
+const DEFAULT_REDACTED_HEADERS: [&str; 4] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Send requests over the network and write them to the cassette.
+    Record,
+    /// Answer requests from the cassette; nothing is sent.
+    Replay,
+}
+
+/// Which parts of a request must equal a recorded one for it to replay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchRules {
+    pub method: bool,
+    /// Off by default, so a cassette recorded against the API replays against
+    /// any base URL.
+    pub host: bool,
+    pub path: bool,
+    /// Query parameters, in any order.
+    pub query: bool,
+    /// JSON bodies are compared as values; anything else byte for byte.
+    pub body: bool,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self {
+            method: true,
+            host: false,
+            path: true,
+            query: true,
+            body: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    rules: MatchRules,
+    redacted_headers: Vec<String>,
+}
+
+impl Cassette {
+    pub fn new(path: impl Into<PathBuf>, mode: CassetteMode) -> Self {
+        Self {
+            path: path.into(),
+            mode,
+            rules: MatchRules::default(),
+            redacted_headers: DEFAULT_REDACTED_HEADERS.iter()
+                .map(|name| name.to_string())
+                .collect(),
+        }
+    }
+
+    /// Records to `path`, replacing whatever it held.
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self::new(path, CassetteMode::Record)
+    }
+
+    pub fn replay(path: impl Into<PathBuf>) -> Self {
+        Self::new(path, CassetteMode::Replay)
+    }
+
+    /// Replays `path` if it exists and records to it otherwise.
+    pub fn once(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mode = if path.exists() { CassetteMode::Replay } else { CassetteMode::Record };
+        Self::new(path, mode)
+    }
+
+    pub fn with_match_rules(mut self, rules: MatchRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Also replaces `name` with a placeholder in recorded requests and
+    /// responses.
+    pub fn with_redacted_header(mut self, name: &str) -> Self {
+        self.redacted_headers.push(name.to_ascii_lowercase());
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    body: RecordedBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    body: RecordedBody,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RecordedBody {
+    #[default]
+    Empty,
+    Text(String),
+    Base64(String),
+}
+
+impl RecordedBody {
+    fn new(bytes: &[u8]) -> Self {
+        if bytes.is_empty() {
+            return RecordedBody::Empty;
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(text) => RecordedBody::Text(text.to_string()),
+            Err(_) => RecordedBody::Base64(BASE64.encode(bytes)),
+        }
+    }
+
+    fn bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            RecordedBody::Empty => Ok(Vec::new()),
+            RecordedBody::Text(text) => Ok(text.as_bytes().to_vec()),
+            RecordedBody::Base64(data) => Ok(BASE64.decode(data)?),
+        }
+    }
+}
+
+/// Wraps another transport to record through it, or replaces it on replay.
+pub(crate) struct CassetteTransport {
+    cassette: Cassette,
+    inner: Arc<dyn Transport>,
+    state: Arc<Mutex<CassetteState>>,
+}
+
+#[derive(Default)]
+struct CassetteState {
+    file: CassetteFile,
+    used: Vec<bool>,
+    // Replays fail with this if the cassette couldn't be read.
+    load_error: Option<String>,
+}
+
+impl CassetteTransport {
+    pub(crate) fn new(cassette: Cassette, inner: Arc<dyn Transport>) -> Self {
+        let mut state = CassetteState::default();
+        if cassette.mode == CassetteMode::Replay {
+            match load(&cassette.path) {
+                Ok(file) => {
+                    state.used = vec![false; file.interactions.len()];
+                    state.file = file;
+                }
+                Err(e) => {
+                    state.load_error = Some(
+                        format!("failed to load cassette {}: {}", cassette.path.display(), e)
+                    );
+                }
+            }
+        }
+
+        Self {
+            cassette,
+            inner,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    fn replay(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(e) = &state.load_error {
+            return Err(anyhow!("{}", e));
+        }
+
+        let body = request.body.as_deref().unwrap_or_default();
+        let position = (0..state.file.interactions.len()).find(|&i| {
+            !state.used[i] &&
+                matches(&self.cassette.rules, &state.file.interactions[i].request, request, body)
+        });
+        let Some(position) = position else {
+            return Err(
+                anyhow!(
+                    "no unused interaction in cassette {} matches {} {}",
+                    self.cassette.path.display(),
+                    request.method,
+                    request.url
+                )
+            );
+        };
+        state.used[position] = true;
+
+        let recorded = &state.file.interactions[position].response;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &recorded.headers {
+            if
+                let (Ok(name), Ok(value)) = (
+                    HeaderName::from_bytes(name.as_bytes()),
+                    HeaderValue::from_str(value),
+                )
+            {
+                headers.append(name, value);
+            }
+        }
+
+        Ok(
+            HttpResponse::new(
+                StatusCode::from_u16(recorded.status)?,
+                headers,
+                Bytes::from(recorded.body.bytes()?)
+            )
+        )
+    }
+
+    async fn record(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let recorded_request = RecordedRequest {
+            method: request.method.to_string(),
+            url: request.url.to_string(),
+            headers: self.redact(&request.headers),
+            body: RecordedBody::new(request.body.as_deref().unwrap_or_default()),
+        };
+
+        let response = self.inner.send(request).await?;
+        let status = response.status;
+        let headers = response.headers.clone();
+        let recorder = Recorder {
+            cassette: self.cassette.clone(),
+            state: self.state.clone(),
+            request: Some(recorded_request),
+            status: status.as_u16(),
+            headers: self.redact(&headers),
+            body: Vec::new(),
+        };
+
+        // Pass chunks through as they arrive. The interaction is written when
+        // the recorder is dropped, which is at the end of the body or when the
+        // reader stops early, as SSE readers do after the `done` event.
+        let body = stream::unfold((response.body, recorder), |(mut body, mut recorder)| {
+            async move {
+                let chunk = body.next().await?;
+                if let Ok(chunk) = &chunk {
+                    recorder.body.extend_from_slice(chunk);
+                }
+                Some((chunk, (body, recorder)))
+            }
+        });
+
+        Ok(HttpResponse {
+            status,
+            headers,
+            body: body.boxed(),
+        })
+    }
+
+    fn redact(&self, headers: &HeaderMap) -> BTreeMap<String, String> {
+        redact(headers, &self.cassette.redacted_headers)
+    }
+}
+
+impl Transport for CassetteTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        Box::pin(async move {
+            match self.cassette.mode {
+                CassetteMode::Replay => self.replay(&request),
+                CassetteMode::Record => self.record(request).await,
+            }
+        })
+    }
+}
+
+struct Recorder {
+    cassette: Cassette,
+    state: Arc<Mutex<CassetteState>>,
+    request: Option<RecordedRequest>,
+    status: u16,
+    headers: BTreeMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            log::error!("Failed to write cassette {}: {}", self.cassette.path.display(), e);
+        }
+    }
+}
+
+impl Recorder {
+    fn finish(&mut self) -> Result<()> {
+        let Some(request) = self.request.take() else {
+            return Ok(());
+        };
+        let interaction = Interaction {
+            request,
+            response: RecordedResponse {
+                status: self.status,
+                headers: std::mem::take(&mut self.headers),
+                body: RecordedBody::new(&self.body),
+            },
+        };
+
+        let mut state = self.state.lock().unwrap();
+        state.file.interactions.push(interaction);
+        std::fs::write(&self.cassette.path, serde_json::to_vec_pretty(&state.file)?)?;
+        Ok(())
+    }
+}
+
+fn load(path: &Path) -> Result<CassetteFile> {
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+fn redact(headers: &HeaderMap, redacted: &[String]) -> BTreeMap<String, String> {
+    let mut recorded = BTreeMap::new();
+    for (name, value) in headers {
+        let value = if redacted.iter().any(|r| r == name.as_str()) {
+            REDACTED.to_string()
+        } else {
+            String::from_utf8_lossy(value.as_bytes()).into_owned()
+        };
+        recorded
+            .entry(name.as_str().to_string())
+            .and_modify(|existing: &mut String| {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            })
+            .or_insert(value);
+    }
+    recorded
+}
+
+fn matches(
+    rules: &MatchRules,
+    recorded: &RecordedRequest,
+    request: &HttpRequest,
+    body: &[u8]
+) -> bool {
+    let Ok(url) = Url::parse(&recorded.url) else {
+        return false;
+    };
+
+    if rules.method && recorded.method != request.method.as_str() {
+        return false;
+    }
+    let same_host = url.host_str() == request.url.host_str() && url.port() == request.url.port();
+    if rules.host && !same_host {
+        return false;
+    }
+    if rules.path && url.path() != request.url.path() {
+        return false;
+    }
+    if rules.query {
+        let mut recorded_query: Vec<_> = url.query_pairs().collect();
+        let mut query: Vec<_> = request.url.query_pairs().collect();
+        recorded_query.sort();
+        query.sort();
+        if recorded_query != query {
+            return false;
+        }
+    }
+    if rules.body {
+        let Ok(recorded_body) = recorded.body.bytes() else {
+            return false;
+        };
+        let json = (
+            serde_json::from_slice::<serde_json::Value>(&recorded_body),
+            serde_json::from_slice::<serde_json::Value>(body),
+        );
+        let equal = match json {
+            (Ok(recorded_json), Ok(json)) => recorded_json == json,
+            _ => recorded_body == body,
+        };
+        if !equal {
+            return false;
+        }
+    }
+
+    true
+}
@@ -0,0 +1,161 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::path::PathBuf;
+    use std::sync::{ Arc, Mutex };
+    use anyhow::{ Result, anyhow };
+    use bytes::Bytes;
+    use futures::stream::{ self, StreamExt };
+    use reqwest::header::{ HeaderMap, HeaderValue, AUTHORIZATION };
+    use reqwest::{ Method, StatusCode, Url };
+    use super::super::cassette::{ Cassette, CassetteTransport, MatchRules };
+    use super::super::client::Client;
+    use super::super::test_support::ScriptedTransport;
+    use super::super::transport::{ HttpRequest, HttpResponse, Transport };
+
+    /// Answers requests with canned bodies, each sent as the given chunks.
+    fn canned(responses: Vec<Vec<&'static str>>) -> Arc<ScriptedTransport> {
+        let responses: Mutex<VecDeque<_>> = Mutex::new(responses.into());
+        Arc::new(
+            ScriptedTransport::new(move |_| {
+                let chunks = responses.lock().unwrap().pop_front();
+                let chunks = chunks.ok_or_else(|| anyhow!("unexpected request"))?;
+                Ok(HttpResponse {
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                    body: stream
+                        ::iter(chunks.into_iter().map(|chunk| Ok(Bytes::from(chunk))))
+                        .boxed(),
+                })
+            })
+        )
+    }
+
+    fn cassette_path(name: &str) -> PathBuf {
+        let path = std::env
+            ::temp_dir()
+            .join(format!("repli-cassette-{}-{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn request(method: Method, url: &str, body: Option<&str>) -> HttpRequest {
+        let mut request = HttpRequest::new(method, Url::parse(url).unwrap());
+        request.headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer r8_secret"));
+        request.body = body.map(|body| Bytes::from(body.to_string()));
+        request
+    }
+
+    async fn body(transport: &CassetteTransport, request: HttpRequest) -> Result<String> {
+        transport.send(request).await?.text().await
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let path = cassette_path("roundtrip");
+        let recorder = CassetteTransport::new(
+            Cassette::record(&path),
+            canned(vec![vec!["{\"id\":", "\"p1\"}"], vec!["{\"id\":\"p2\"}"]])
+        );
+        let get = || request(Method::GET, "https://api.replicate.com/v1/predictions/p1", None);
+        let post = |body| request(Method::POST, "https://api.replicate.com/v1/predictions", body);
+
+        assert_eq!(body(&recorder, get()).await.unwrap(), "{\"id\":\"p1\"}");
+        let created = body(&recorder, post(Some("{\"version\":\"v\",\"input\":{}}"))).await;
+        assert_eq!(created.unwrap(), "{\"id\":\"p2\"}");
+
+        let recorded = std::fs::read_to_string(&path).unwrap();
+        assert!(!recorded.contains("r8_secret"));
+        assert!(recorded.contains("[REDACTED]"));
+
+        let replayer = CassetteTransport::new(Cassette::replay(&path), canned(vec![]));
+        // JSON bodies match regardless of key order.
+        let replayed = body(&replayer, post(Some("{\"input\":{},\"version\":\"v\"}"))).await;
+        assert_eq!(replayed.unwrap(), "{\"id\":\"p2\"}");
+        assert_eq!(body(&replayer, get()).await.unwrap(), "{\"id\":\"p1\"}");
+        // Each interaction replays once.
+        assert!(body(&replayer, get()).await.is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_records_stream_read_partially() {
+        let path = cassette_path("stream");
+        let recorder = CassetteTransport::new(
+            Cassette::record(&path),
+            canned(vec![vec!["event: output\ndata: hi\n\n", "event: done\ndata: {}\n\n"]])
+        );
+
+        let url = "https://streaming.api.replicate.com/v1/streams/abc";
+        let mut response = recorder.send(request(Method::GET, url, None)).await.unwrap();
+        response.body.next().await.unwrap().unwrap();
+        drop(response);
+
+        let replayer = CassetteTransport::new(Cassette::replay(&path), canned(vec![]));
+        let replayed = body(&replayer, request(Method::GET, url, None)).await.unwrap();
+        assert_eq!(replayed, "event: output\ndata: hi\n\n");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_match_rules() {
+        let path = cassette_path("rules");
+        let recorder = CassetteTransport::new(
+            Cassette::record(&path),
+            canned(vec![vec!["ok"]])
+        );
+        let url = "https://api.replicate.com/v1/predictions?a=1&b=2";
+        body(&recorder, request(Method::POST, url, Some("{\"n\":1}"))).await.unwrap();
+
+        let other = request(Method::POST, "http://localhost/v1/predictions?b=2&a=1", Some("{}"));
+        let strict = CassetteTransport::new(Cassette::replay(&path), canned(vec![]));
+        assert!(body(&strict, other.clone()).await.is_err());
+
+        let rules = MatchRules { body: false, ..Default::default() };
+        let loose = CassetteTransport::new(
+            Cassette::replay(&path).with_match_rules(rules),
+            canned(vec![])
+        );
+        assert_eq!(body(&loose, other).await.unwrap(), "ok");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_replays_without_network() {
+        let path = cassette_path("client");
+        let prediction = include_str!("fixtures/prediction.json");
+        let cassette = serde_json::json!({
+            "interactions": [{
+                "request": {
+                    "method": "GET",
+                    "url": "https://api.replicate.com/v1/predictions/gm3qorzdhgbfurvjtvhg6dckhu",
+                    "headers": { "authorization": "[REDACTED]" }
+                },
+                "response": { "status": 200, "body": { "text": prediction } }
+            }]
+        });
+        std::fs::write(&path, cassette.to_string()).unwrap();
+
+        let client = Client::new(Some("unused".to_string()))
+            .unwrap()
+            .with_base_url("http://127.0.0.1:1/v1".to_string())
+            .with_cassette(Cassette::replay(&path));
+        let prediction = client.get_prediction("gm3qorzdhgbfurvjtvhg6dckhu").await.unwrap();
+        assert_eq!(prediction.model, "replicate/hello-world");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_cassette() {
+        let replayer = CassetteTransport::new(
+            Cassette::replay(cassette_path("missing")),
+            canned(vec![])
+        );
+        let error = body(&replayer, request(Method::GET, "https://example.com/", None)).await;
+        assert!(error.unwrap_err().to_string().contains("failed to load cassette"));
+    }
+}
@@ -1,11 +1,27 @@
-use std::time::Duration;
-use reqwest::{ Client as ReqwestClient, Method, Url };
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
+use reqwest::header::{ HeaderValue, AUTHORIZATION, CONTENT_TYPE };
+use reqwest::{ Method, Url };
 
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 use anyhow::{ Result, anyhow };
 
 use crate::backoff::{ Backoff, ExponentialBackoff };
+use crate::cache::PredictionCache;
+use crate::cassette::{ Cassette, CassetteTransport };
+use crate::deployment::ReleaseHistory;
+use crate::error::RequestError;
+use crate::hardware::HardwareCatalog;
+use crate::interceptor::{ Interceptor, InterceptorChain };
+use crate::journal::PredictionJournal;
+use crate::prediction::Prediction;
+use crate::progress::ProgressReporter;
+use crate::telemetry::{ self, Route };
+use crate::training::Training;
+use crate::transport::{ HttpRequest, HttpResponse, ReqwestTransport, Transport };
+use crate::usage::UsageTracker;
+use crate::wait::WebhookWaiter;
 
 const ENV_AUTH_TOKEN: &str = "REPLICATE_API_TOKEN";
 const DEFAULT_BASE_URL: &str = "https://api.replicate.com/v1";
@@ -13,10 +29,18 @@ const DEFAULT_MAX_RETRIES: u32 = 5;
 
 pub struct Client {
     pub(crate) auth_token: String,
-    pub(crate) client: ReqwestClient,
+    pub(crate) transport: Arc<dyn Transport>,
+    pub(crate) interceptors: InterceptorChain,
     pub(crate) base_url: String,
     max_retries: u32,
     backoff: Box<dyn Backoff>,
+    pub(crate) webhook_waiter: Option<WebhookWaiter>,
+    pub(crate) release_history: ReleaseHistory,
+    pub(crate) hardware_catalog: HardwareCatalog,
+    pub(crate) usage_tracker: Option<UsageTracker>,
+    pub(crate) journal: Option<PredictionJournal>,
+    pub(crate) cache: Option<PredictionCache>,
+    pub(crate) progress_reporter: Option<ProgressReporter>,
 }
 
 impl Client {
@@ -27,7 +51,8 @@ impl Client {
 
         Ok(Self {
             auth_token,
-            client: ReqwestClient::new(),
+            transport: Arc::new(ReqwestTransport::default()),
+            interceptors: InterceptorChain::default(),
             base_url: DEFAULT_BASE_URL.to_string(),
             max_retries: DEFAULT_MAX_RETRIES,
             backoff: Box::new(ExponentialBackoff {
@@ -35,14 +60,34 @@ impl Client {
                 multiplier: 2.0,
                 jitter: Duration::from_millis(50),
             }),
+            webhook_waiter: None,
+            release_history: ReleaseHistory::default(),
+            hardware_catalog: HardwareCatalog::default(),
+            usage_tracker: None,
+            journal: None,
+            cache: None,
+            progress_reporter: None,
         })
     }
 
+    /// A client using the token in REPLICATE_API_TOKEN, which must be set and
+    /// not blank.
+    pub fn new_from_env() -> Result<Self> {
+        match std::env::var(ENV_AUTH_TOKEN) {
+            Ok(token) if !token.trim().is_empty() => Self::new(Some(token)),
+            _ => Err(anyhow!("{} is not set", ENV_AUTH_TOKEN)),
+        }
+    }
+
     pub fn with_base_url(mut self, base_url: String) -> Self {
         self.base_url = base_url;
         self
     }
 
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
     pub fn with_max_retries(mut self, max_retries: u32) -> Self {
         self.max_retries = max_retries;
         self
@@ -53,62 +98,185 @@ impl Client {
         self
     }
 
+    /// Waits for predictions through webhook deliveries to `waiter` rather
+    /// than polling. `run` attaches the waiter's webhook to new predictions.
+    pub fn with_webhook_waiter(mut self, waiter: WebhookWaiter) -> Self {
+        self.webhook_waiter = Some(waiter);
+        self
+    }
+
+    /// Adds every completed prediction and training this client sees to
+    /// `tracker`.
+    pub fn with_usage_tracker(mut self, tracker: UsageTracker) -> Self {
+        self.usage_tracker = Some(tracker);
+        self
+    }
+
+    /// Sends every request through `transport` instead of the default
+    /// `ReqwestTransport`. Call this before `with_cassette`, which wraps the
+    /// transport set at that point.
+    pub fn with_transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    /// Records every request and response to `cassette`, or answers requests
+    /// from it, depending on its mode.
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.transport = Arc::new(CassetteTransport::new(cassette, self.transport));
+        self
+    }
+
+    /// Runs `interceptor` around every request, after any added before it.
+    pub fn with_interceptor(mut self, interceptor: impl Interceptor + 'static) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
     pub async fn fetch<T: DeserializeOwned>(
         &self,
         method: Method,
         path: &str,
         body: Option<Value>
     ) -> Result<T> {
-        let url = Url::parse(&format!("{}{}", self.base_url, path))?;
-        let mut request = self.client
-            .request(method.clone(), url.clone())
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.auth_token));
+        let response = self.send(method, path, body).await?;
+        let bytes = response.bytes().await?;
+        log::debug!("Successful response received");
+
+        // DELETE and some POST endpoints answer with an empty body.
+        if bytes.is_empty() {
+            return Ok(serde_json::from_value(Value::Null)?);
+        }
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    pub async fn fetch_text(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Value>
+    ) -> Result<String> {
+        let response = self.send(method, path, body).await?;
+        response.text().await
+    }
+
+    pub(crate) fn url(&self, path: &str) -> Result<Url> {
+        // Pagination cursors come back from the API as absolute URLs.
+        if path.starts_with("http://") || path.starts_with("https://") {
+            Ok(Url::parse(path)?)
+        } else {
+            Ok(Url::parse(&format!("{}{}", self.base_url, path))?)
+        }
+    }
+
+    async fn send(&self, method: Method, path: &str, body: Option<Value>) -> Result<HttpResponse> {
+        let url = self.url(path)?;
+        let mut request = self.request(method, url);
+        request.headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
         // .header("User-Agent", DEFAULT_USER_AGENT);
 
         if let Some(body) = body {
-            request = request.json(&body);
-            log::debug!("Request body: {}", serde_json::to_string_pretty(&body).unwrap());
+            request.body = Some(serde_json::to_vec(&body)?.into());
         }
 
-        log::info!("Sending {} request to {}", method, url);
+        self.send_request(request).await
+    }
+
+    /// A request carrying this client's auth token.
+    pub(crate) fn request(&self, method: Method, url: Url) -> HttpRequest {
+        let mut request = HttpRequest::new(method, url);
+        let token = HeaderValue::from_str(&format!("Bearer {}", self.auth_token));
+        if let Ok(mut token) = token {
+            token.set_sensitive(true);
+            request.headers.insert(AUTHORIZATION, token);
+        }
+        request
+    }
+
+    /// Sends `request` through the interceptors and the transport, retrying
+    /// as configured.
+    pub(crate) async fn send_request(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let route = if request.url.as_str().starts_with(&self.base_url) {
+            Route::new(&request.url)
+        } else {
+            Route::download()
+        };
+        let span = telemetry::request_span(&request, &route);
+        telemetry::instrument(self.send_with_retries(request, &route), span).await
+    }
+
+    async fn send_with_retries(
+        &self,
+        mut request: HttpRequest,
+        route: &Route
+    ) -> Result<HttpResponse> {
+        log::info!("Sending {} request to {}", request.method, request.url);
+        self.interceptors.before_request(&mut request)?;
 
         let mut attempts = 0;
         loop {
             log::debug!("Attempt {} of {}", attempts + 1, self.max_retries + 1);
 
-            let response = request.try_clone().unwrap().send().await?;
-
-            log::debug!("Response status: {}", response.status());
+            let started = Instant::now();
+            let response = match self.transport.send(request.clone()).await {
+                Ok(response) => response,
+                Err(e) => {
+                    telemetry::record_error(&request, route, &e);
+                    self.interceptors.on_error(&request, &e);
+                    return Err(e);
+                }
+            };
+            let elapsed = started.elapsed();
+            telemetry::record_response(&request, route, attempts + 1, response.status, elapsed);
+            self.interceptors.after_response(&request, &response, elapsed);
 
-            if response.status().is_success() {
-                let json = response.json().await?;
-                log::debug!("Successful response received");
+            log::debug!("Response status: {}", response.status);
 
-                return Ok(json);
+            if response.status.is_success() {
+                return Ok(response);
             } else {
                 log::warn!("Request failed");
             }
 
-            if !self.should_retry(&response, &method) || attempts >= self.max_retries {
-                let error_text = response.text().await?;
-                log::error!("Request failed: {}", error_text);
-                return Err(anyhow!("Request failed: {}", error_text));
+            if !self.should_retry(&response, &request.method) || attempts >= self.max_retries {
+                let status = response.status;
+                let error = match response.text().await {
+                    Ok(body) => anyhow!(RequestError { status, body }),
+                    Err(e) => e,
+                };
+                log::error!("{}", error);
+                self.interceptors.on_error(&request, &error);
+                return Err(error);
             }
 
             let delay = self.backoff.next_delay(attempts);
             log::info!("Retrying after {} ms", delay.as_millis());
+            telemetry::record_retry(&request, route, delay);
             tokio::time::sleep(delay).await;
             attempts += 1;
         }
     }
 
-    fn should_retry(&self, response: &reqwest::Response, method: &Method) -> bool {
+    fn should_retry(&self, response: &HttpResponse, method: &Method) -> bool {
         if method == Method::GET {
-            response.status() == 429 ||
-                (response.status().as_u16() >= 500 && response.status().as_u16() < 600)
+            response.status == 429 || response.status.is_server_error()
         } else {
-            response.status() == 429
+            response.status == 429
+        }
+    }
+
+    /// Passes a prediction the client fetched to the usage tracker and
+    /// journal, whichever are attached.
+    pub(crate) async fn observe_prediction(&self, prediction: &Prediction) {
+        if let Some(tracker) = &self.usage_tracker {
+            tracker.record_prediction(prediction);
+        }
+        self.journal_finished(prediction).await;
+    }
+
+    pub(crate) fn observe_training(&self, training: &Training) {
+        if let Some(tracker) = &self.usage_tracker {
+            tracker.record_training(training);
         }
     }
 }
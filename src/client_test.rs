@@ -1,88 +1,303 @@
 #[cfg(test)]
 mod tests {
-    use crate::client::Client;
-    use crate::model::Model;
-    use crate::prediction::Prediction;
-    use crate::webhook::Webhook;
-    use mockito::{mock, server_url};
-    use serde_json::json;
+    use std::sync::Mutex;
+    use super::super::client::Client;
+
+    // The environment is shared by every test thread, so tests that change
+    // REPLICATE_API_TOKEN take turns.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Runs `f` with REPLICATE_API_TOKEN set to `token`, or unset.
+    fn with_env_token<T>(token: Option<&str>, f: impl FnOnce() -> T) -> T {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let saved = std::env::var("REPLICATE_API_TOKEN").ok();
+        match token {
+            Some(token) => std::env::set_var("REPLICATE_API_TOKEN", token),
+            None => std::env::remove_var("REPLICATE_API_TOKEN"),
+        }
+        let result = f();
+        match saved {
+            Some(saved) => std::env::set_var("REPLICATE_API_TOKEN", saved),
+            None => std::env::remove_var("REPLICATE_API_TOKEN"),
+        }
+        result
+    }
 
     #[tokio::test]
     async fn test_new_client_no_auth() {
-        let result = Client::new(None);
+        let result = with_env_token(None, || Client::new(None));
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err().to_string(), "No auth token provided");
+        assert_eq!(result.err().unwrap().to_string(), "No auth token provided");
     }
 
     #[tokio::test]
     async fn test_new_client_blank_auth_token_from_env() {
-        std::env::set_var("REPLICATE_API_TOKEN", "");
-        let result = Client::new_from_env();
+        let result = with_env_token(Some(""), Client::new_from_env);
         assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("REPLICATE_API_TOKEN"));
+        assert!(result.err().unwrap().to_string().contains("REPLICATE_API_TOKEN"));
     }
 
     #[tokio::test]
     async fn test_new_client_auth_token_from_env() {
-        std::env::set_var("REPLICATE_API_TOKEN", "test-token");
-        let result = Client::new_from_env();
+        let result = with_env_token(Some("test-token"), Client::new_from_env);
         assert!(result.is_ok());
     }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod mock_server_tests {
+    use std::sync::{ Arc, Mutex };
+    use axum::body::Bytes;
+    use axum::http::HeaderMap;
+    use axum::routing::post;
+    use axum::Router;
+    use futures::StreamExt;
+    use serde_json::json;
+    use super::super::collection::Collection;
+    use super::super::deployment::{ CreateDeploymentOptions, UpdateDeploymentOptions };
+    use super::super::files::CreateFileOptions;
+    use super::super::hardware::Hardware;
+    use super::super::prediction::PredictionInput;
+    use super::super::request::{ PredictionRequest, Target };
+    use super::super::status::Status;
+    use super::super::testing::MockServer;
+    use super::super::webhook::{ validate_webhook, Webhook, WebhookEventType };
+
+    fn input(text: &str) -> PredictionInput {
+        PredictionInput::from([("text".to_string(), json!(text))])
+    }
+
+    fn collection(slug: &str, name: &str) -> Collection {
+        serde_json::from_value(json!({ "slug": slug, "name": name, "description": "" })).unwrap()
+    }
 
     #[tokio::test]
-    async fn test_list_collections() {
-        let mock_server = mock("GET", "/collections")
-            .with_status(200)
-            .with_body(r#"
-                {
-                    "results": [
-                        {"slug": "collection-1", "name": "Collection 1", "description": ""},
-                        {"slug": "collection-2", "name": "Collection 2", "description": ""}
-                    ]
-                }
-            "#)
-            .create();
+    async fn test_requests_without_token_are_rejected() {
+        let server = MockServer::start().await.unwrap();
+        let response = reqwest::get(format!("{}/account", server.url())).await.unwrap();
+        assert_eq!(response.status(), 401);
+    }
 
-        let client = Client::new(Some("test-token".to_string())).unwrap();
-        client.set_base_url(&server_url());
+    #[tokio::test]
+    async fn test_list_collections() {
+        let server = MockServer::start().await.unwrap();
+        server.add_collection(collection("collection-1", "Collection 1"));
+        server.add_collection(collection("collection-2", "Collection 2"));
 
-        let collections = client.list_collections().await.unwrap();
+        let collections = server.client().list_collections().await.unwrap().results;
 
         assert_eq!(collections.len(), 2);
         assert_eq!(collections[0].slug, "collection-1");
         assert_eq!(collections[0].name, "Collection 1");
         assert_eq!(collections[1].slug, "collection-2");
         assert_eq!(collections[1].name, "Collection 2");
-
-        mock_server.assert();
     }
 
     #[tokio::test]
     async fn test_get_collection() {
-        let mock_server = mock("GET", "/collections/super-resolution")
-            .with_status(200)
-            .with_body(r#"
-                {
-                    "name": "Super resolution",
-                    "slug": "super-resolution",
-                    "description": "Upscaling models that create high-quality images from low-quality images.",
-                    "models": []
-                }
-            "#)
-            .create();
-
-        let client = Client::new(Some("test-token".to_string())).unwrap();
-        client.set_base_url(&server_url());
+        let server = MockServer::start().await.unwrap();
+        server.add_collection(collection("super-resolution", "Super resolution"));
+        let client = server.client();
 
         let collection = client.get_collection("super-resolution").await.unwrap();
-
         assert_eq!(collection.name, "Super resolution");
-        assert_eq!(collection.slug, "super-resolution");
-        assert_eq!(collection.description, "Upscaling models that create high-quality images from low-quality images.");
-        assert!(collection.models.is_empty());
+        assert!(client.get_collection("missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_prediction_moves_through_statuses() {
+        let server = MockServer::start_with_step_delay(None).await.unwrap();
+        let client = server.client();
+        let target = Target::Version("abc123".to_string());
+
+        let prediction = client
+            .create_prediction_from_request(PredictionRequest::new(target, input("hi")))
+            .await
+            .unwrap();
+        assert_eq!(prediction.status, Status::Starting);
+
+        server.advance(&prediction.id).await.unwrap();
+        let processing = client.get_prediction(&prediction.id).await.unwrap();
+        assert_eq!(processing.status, Status::Processing);
+        assert!(processing.started_at.is_some());
 
-        mock_server.assert();
+        server.advance(&prediction.id).await.unwrap();
+        let done = client.get_prediction(&prediction.id).await.unwrap();
+        assert_eq!(done.status, Status::Succeeded);
+        assert_eq!(done.version, "abc123");
+        assert_eq!(done.output, Some(json!({ "text": "hi" })));
+        assert!(done.metrics.unwrap().predict_time.is_some());
     }
 
-    // Add more tests here...
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn test_run_uses_handler() {
+        let server = MockServer::start().await.unwrap();
+        server.on_predict("acme/upper", |input| {
+            Ok(json!(input["text"].as_str().unwrap_or_default().to_uppercase()))
+        });
+        server.on_predict("acme/broken", |_| Err("out of memory".to_string()));
+        let client = server.client();
+
+        let output = client.run("acme/upper", input("hi"), None).await.unwrap();
+        assert_eq!(output, json!("HI"));
+
+        let error = client.run("acme/broken", input("hi"), None).await.unwrap_err();
+        assert!(error.to_string().contains("out of memory"));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_prediction() {
+        let server = MockServer::start_with_step_delay(None).await.unwrap();
+        let client = server.client();
+
+        let prediction = client
+            .create_prediction_with_model("acme", "slow", input("hi"), None, false)
+            .await
+            .unwrap();
+        let canceled = client.cancel_prediction(&prediction.id).await.unwrap();
+
+        assert_eq!(canceled.status, Status::Canceled);
+        assert_eq!(server.advance(&prediction.id).await.unwrap().status, Status::Canceled);
+    }
+
+    #[tokio::test]
+    async fn test_stream() {
+        let server = MockServer::start().await.unwrap();
+        server.on_predict("acme/llm", |_| Ok(json!(["Hello", ", ", "world"])));
+
+        let client = server.client();
+        let (events, _errors) = client.stream("acme/llm", input("hi"), None).await.unwrap();
+        let events: Vec<_> = events.collect().await;
+        let output: String = events
+            .iter()
+            .map(|event| event.to_string())
+            .collect();
+
+        assert_eq!(output, "Hello, world");
+        assert_eq!(events.last().unwrap().event_type, "done");
+    }
+
+    #[tokio::test]
+    async fn test_files() {
+        let server = MockServer::start().await.unwrap();
+        let client = server.client();
+        let options = CreateFileOptions {
+            filename: Some("hello.txt".to_string()),
+            content_type: Some("text/plain".to_string()),
+            metadata: None,
+        };
+
+        let file = client.create_file_from_bytes(b"hello", Some(options)).await.unwrap();
+        assert_eq!(file.name, "hello.txt");
+        assert_eq!(file.size, 5);
+        assert_eq!(server.files()[0].1, b"hello");
+
+        assert_eq!(client.get_file(&file.id).await.unwrap().content_type, "text/plain");
+        assert_eq!(client.list_files().await.unwrap().results.len(), 1);
+
+        client.delete_file(&file.id).await.unwrap();
+        assert!(client.get_file(&file.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deployments() {
+        let server = MockServer::start().await.unwrap();
+        server.on_predict("v2", |_| Ok(json!("from v2")));
+        let client = server.client();
+
+        let deployment = client
+            .create_deployment(CreateDeploymentOptions {
+                name: "my-app".to_string(),
+                model: "acme/model".to_string(),
+                version: "v1".to_string(),
+                hardware: Hardware::GpuT4,
+                min_instances: 0,
+                max_instances: 1,
+            })
+            .await
+            .unwrap();
+        assert_eq!(deployment.current_release.unwrap().number, 1);
+
+        let update = UpdateDeploymentOptions {
+            version: Some("v2".to_string()),
+            ..Default::default()
+        };
+        let updated = client.update_deployment(&deployment.owner, "my-app", update).await.unwrap();
+        let release = updated.current_release.unwrap();
+        assert_eq!(release.number, 2);
+        assert_eq!(release.configuration.hardware, Hardware::GpuT4);
+        assert_eq!(client.deployment_releases(&deployment.owner, "my-app").len(), 2);
+
+        let output = client
+            .run_deployment(&deployment.owner, "my-app", input("hi"), None)
+            .await
+            .unwrap();
+        assert_eq!(output, json!("from v2"));
+
+        client.delete_deployment(&deployment.owner, "my-app").await.unwrap();
+        assert!(server.deployments().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_deliveries_are_signed() {
+        let server = MockServer::start().await.unwrap();
+        let client = server.client();
+        let secret = client.get_default_webhook_secret().await.unwrap();
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let app = Router::new().route(
+            "/",
+            post({
+                let received = received.clone();
+                move |headers: HeaderMap, body: Bytes| async move {
+                    received.lock().unwrap().push(validate_webhook(&headers, &body, &secret));
+                }
+            })
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let webhook = Webhook {
+            url: url.clone(),
+            events: vec![WebhookEventType::Start, WebhookEventType::Completed],
+        };
+        let prediction = client
+            .create_prediction_with_model("acme", "echo", input("hi"), Some(&webhook), false)
+            .await
+            .unwrap();
+        client.wait(&prediction).await.unwrap();
+
+        let deliveries = server.webhook_deliveries();
+        let events: Vec<_> = deliveries
+            .iter()
+            .map(|delivery| delivery.event.clone())
+            .collect();
+        assert_eq!(events, vec![WebhookEventType::Start, WebhookEventType::Completed]);
+        assert!(deliveries.iter().all(|delivery| delivery.status == Some(200)));
+        assert!(received.lock().unwrap().iter().all(|result| result.is_ok()));
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unresponsive_webhook_does_not_block_requests() {
+        let server = MockServer::start_with_step_delay(None).await.unwrap();
+        let app = Router::new().route(
+            "/",
+            post(|| async { tokio::time::sleep(std::time::Duration::from_secs(60)).await })
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let webhook = Webhook { url, events: vec![WebhookEventType::Start] };
+        let started = std::time::Instant::now();
+        server
+            .client()
+            .create_prediction_with_model("acme", "echo", input("hi"), Some(&webhook), false).await
+            .unwrap();
+
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert_eq!(server.webhook_deliveries()[0].status, None);
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use serde::{ Deserialize, Serialize };
 use anyhow::Result;
 use crate::client::Client;
@@ -8,8 +9,10 @@ use crate::paginate::Page;
 pub struct Collection {
     pub name: String,
     pub slug: String,
-    pub description: String,
+    pub description: Option<String>,
     pub models: Option<Vec<Model>>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl Client {
@@ -1,16 +1,23 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use chrono::{ DateTime, Utc };
 use serde::{ Deserialize, Serialize };
 use anyhow::Result;
 use crate::client::Client;
 use crate::account::Account;
+use crate::hardware::Hardware;
 use crate::prediction::{ Prediction, PredictionInput };
 use crate::webhook::Webhook;
 use crate::paginate::Page;
+use crate::request::{ PredictionRequest, Target };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Deployment {
     pub owner: String,
     pub name: String,
-    pub current_release: DeploymentRelease,
+    pub current_release: Option<DeploymentRelease>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,14 +25,16 @@ pub struct DeploymentRelease {
     pub number: i32,
     pub model: String,
     pub version: String,
-    pub created_at: String,
+    pub created_at: DateTime<Utc>,
     pub created_by: Account,
     pub configuration: DeploymentConfiguration,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeploymentConfiguration {
-    pub hardware: String,
+    pub hardware: Hardware,
     pub min_instances: i32,
     pub max_instances: i32,
 }
@@ -35,20 +44,84 @@ pub struct CreateDeploymentOptions {
     pub name: String,
     pub model: String,
     pub version: String,
-    pub hardware: String,
+    pub hardware: Hardware,
     pub min_instances: i32,
     pub max_instances: i32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Fields to change on an existing deployment. Fields left as `None` are not
+/// sent, so the API keeps their current value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UpdateDeploymentOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
-    pub hardware: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hardware: Option<Hardware>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub min_instances: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_instances: Option<i32>,
 }
 
+impl UpdateDeploymentOptions {
+    /// Whether no field would be changed.
+    pub fn is_empty(&self) -> bool {
+        self.model.is_none() &&
+            self.version.is_none() &&
+            self.hardware.is_none() &&
+            self.min_instances.is_none() &&
+            self.max_instances.is_none()
+    }
+}
+
+impl From<&DeploymentRelease> for UpdateDeploymentOptions {
+    /// Options that restore the model, version and configuration of `release`.
+    fn from(release: &DeploymentRelease) -> Self {
+        Self {
+            model: Some(release.model.clone()),
+            version: Some(release.version.clone()),
+            hardware: Some(release.configuration.hardware.clone()),
+            min_instances: Some(release.configuration.min_instances),
+            max_instances: Some(release.configuration.max_instances),
+        }
+    }
+}
+
+/// The releases this client has seen for each deployment, oldest first.
+///
+/// The API only reports a deployment's current release, so history is built
+/// up from every deployment the client fetches, creates or updates.
+#[derive(Debug, Default)]
+pub(crate) struct ReleaseHistory {
+    releases: Mutex<HashMap<String, Vec<DeploymentRelease>>>,
+}
+
+impl ReleaseHistory {
+    pub(crate) fn record(&self, deployment: &Deployment) {
+        let Some(release) = &deployment.current_release else {
+            return;
+        };
+
+        let key = format!("{}/{}", deployment.owner, deployment.name);
+        let mut releases = self.releases.lock().unwrap();
+        let history = releases.entry(key).or_default();
+        if let Err(pos) = history.binary_search_by_key(&release.number, |r| r.number) {
+            history.insert(pos, release.clone());
+        }
+    }
+
+    pub(crate) fn get(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str
+    ) -> Vec<DeploymentRelease> {
+        let key = format!("{}/{}", deployment_owner, deployment_name);
+        self.releases.lock().unwrap().get(&key).cloned().unwrap_or_default()
+    }
+}
+
 impl Client {
     pub async fn create_prediction_with_deployment(
         &self,
@@ -58,23 +131,15 @@ impl Client {
         webhook: Option<&Webhook>,
         stream: bool
     ) -> Result<Prediction> {
-        let mut data = serde_json::json!({
-            "input": input,
-        });
-
+        let target = Target::Deployment {
+            owner: deployment_owner.to_string(),
+            name: deployment_name.to_string(),
+        };
+        let mut request = PredictionRequest::new(target, input).with_stream(stream);
         if let Some(webhook) = webhook {
-            data["webhook"] = serde_json::json!(webhook.url);
-            if !webhook.events.is_empty() {
-                data["webhook_events_filter"] = serde_json::json!(webhook.events);
-            }
-        }
-
-        if stream {
-            data["stream"] = serde_json::json!(true);
+            request = request.with_webhook(webhook);
         }
-
-        let path = format!("/deployments/{}/{}/predictions", deployment_owner, deployment_name);
-        self.fetch(reqwest::Method::POST, &path, Some(data)).await
+        self.create_prediction_from_request(request).await
     }
 
     pub async fn get_deployment(
@@ -83,19 +148,36 @@ impl Client {
         deployment_name: &str
     ) -> Result<Deployment> {
         let path = format!("/deployments/{}/{}", deployment_owner, deployment_name);
-        self.fetch(reqwest::Method::GET, &path, None).await
+        let deployment = self.fetch(reqwest::Method::GET, &path, None).await?;
+        self.release_history.record(&deployment);
+        Ok(deployment)
     }
 
     pub async fn list_deployments(&self) -> Result<Page<Deployment>> {
-        self.fetch(reqwest::Method::GET, "/deployments", None).await
+        let page: Page<Deployment> = self.fetch(reqwest::Method::GET, "/deployments", None).await?;
+        page.results.iter().for_each(|deployment| self.release_history.record(deployment));
+        Ok(page)
+    }
+
+    /// Returns the releases of a deployment that this client has observed,
+    /// oldest first.
+    pub fn deployment_releases(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str
+    ) -> Vec<DeploymentRelease> {
+        self.release_history.get(deployment_owner, deployment_name)
     }
 
     pub async fn create_deployment(&self, options: CreateDeploymentOptions) -> Result<Deployment> {
-        self.fetch(
+        self.validate_hardware(&options.hardware).await?;
+        let deployment = self.fetch(
             reqwest::Method::POST,
             "/deployments",
             Some(serde_json::to_value(options)?)
-        ).await
+        ).await?;
+        self.release_history.record(&deployment);
+        Ok(deployment)
     }
 
     pub async fn update_deployment(
@@ -104,8 +186,17 @@ impl Client {
         deployment_name: &str,
         options: UpdateDeploymentOptions
     ) -> Result<Deployment> {
+        if let Some(hardware) = &options.hardware {
+            self.validate_hardware(hardware).await?;
+        }
         let path = format!("/deployments/{}/{}", deployment_owner, deployment_name);
-        self.fetch(reqwest::Method::PATCH, &path, Some(serde_json::to_value(options)?)).await
+        let deployment = self.fetch(
+            reqwest::Method::PATCH,
+            &path,
+            Some(serde_json::to_value(options)?)
+        ).await?;
+        self.release_history.record(&deployment);
+        Ok(deployment)
     }
 
     pub async fn delete_deployment(
@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use super::super::deployment::{ Deployment, ReleaseHistory, UpdateDeploymentOptions };
+    use super::super::hardware::Hardware;
+    use serde_json::json;
+
+    fn deployment(number: i32, version: &str) -> Deployment {
+        let mut deployment: Deployment = serde_json
+            ::from_str(include_str!("fixtures/deployment.json"))
+            .unwrap();
+        let release = deployment.current_release.as_mut().unwrap();
+        release.number = number;
+        release.version = version.to_string();
+        deployment
+    }
+
+    #[test]
+    fn test_release_history_orders_and_dedupes() {
+        let history = ReleaseHistory::default();
+        history.record(&deployment(2, "v2"));
+        history.record(&deployment(1, "v1"));
+        history.record(&deployment(2, "v2"));
+
+        let releases = history.get("acme", "image-upscaler");
+        let versions: Vec<&str> = releases
+            .iter()
+            .map(|r| r.version.as_str())
+            .collect();
+        assert_eq!(versions, vec!["v1", "v2"]);
+        assert!(history.get("acme", "other").is_empty());
+    }
+
+    #[test]
+    fn test_update_options_from_release() {
+        let deployment = deployment(3, "v3");
+        let options = UpdateDeploymentOptions::from(deployment.current_release.as_ref().unwrap());
+        assert_eq!(options.hardware, Some(Hardware::GpuT4));
+        assert_eq!(
+            serde_json::to_value(options).unwrap(),
+            json!({
+                "model": "acme/esrgan",
+                "version": "v3",
+                "hardware": "gpu-t4",
+                "min_instances": 1,
+                "max_instances": 5,
+            })
+        );
+    }
+
+    #[test]
+    fn test_update_options_skip_unset_fields() {
+        let options = UpdateDeploymentOptions {
+            version: Some("v4".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_value(options).unwrap(), json!({ "version": "v4" }));
+    }
+}
@@ -57,6 +57,19 @@ impl APIError {
     }
 }
 
+/// A response with an error status, after any retries.
+#[derive(Error, Debug)]
+pub struct RequestError {
+    pub status: reqwest::StatusCode,
+    pub body: String,
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Request failed: {}", self.body)
+    }
+}
+
 #[derive(Error, Debug)]
 pub struct ModelError {
     pub prediction: crate::prediction::Prediction,
@@ -0,0 +1,14 @@
+//! Serde helpers for the resource types.
+//!
+//! The API sends `null` for fields it has no value for as often as it leaves
+//! them out, so a defaulted field has to accept both.
+
+use serde::{ Deserialize, Deserializer };
+
+/// Deserializes `null` as `T::default()`. Use with `#[serde(default)]` so a
+/// missing field is defaulted too.
+pub(crate) fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where D: Deserializer<'de>, T: Default + Deserialize<'de>
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
@@ -3,24 +3,37 @@ use std::path::Path;
 use std::fs::File as FsFile;
 use std::io::Read;
 use anyhow::{ Result, anyhow };
-use reqwest::multipart::{ Form, Part };
+use bytes::Bytes;
+use chrono::{ DateTime, Utc };
+use reqwest::header::{ HeaderValue, CONTENT_TYPE };
 use serde::{ Deserialize, Serialize };
-use mime_guess::from_path;
+use sha2::{ Digest, Sha256 };
+use mime_guess::{ from_path, mime::Mime };
 use crate::client::Client;
 use crate::paginate::Page;
+use crate::fields::null_as_default;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct File {
     pub id: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub name: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub content_type: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub size: i64,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub etag: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub checksums: HashMap<String, String>,
-    pub metadata: HashMap<String, String>,
-    pub created_at: String,
-    pub expires_at: Option<String>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub metadata: HashMap<String, serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub urls: HashMap<String, String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -72,27 +85,21 @@ impl Client {
         content_type: String,
         metadata: Option<HashMap<String, String>>
     ) -> Result<File> {
-        let mut form = Form::new().part(
-            "content",
-            Part::bytes(data.to_vec()).file_name(filename).mime_str(&content_type)?
-        );
-
-        if let Some(metadata) = metadata {
-            form = form.part("metadata", Part::text(serde_json::to_string(&metadata)?));
-        }
+        // Going through `Value` sorts the keys, so the body is deterministic.
+        let metadata = metadata
+            .map(|metadata| serde_json::to_value(metadata).map(|value| value.to_string()))
+            .transpose()?;
+        let (boundary, body) = multipart_body(data, &filename, &content_type, metadata.as_deref())?;
 
-        let response = self.client
-            .post(&format!("{}/files", self.base_url))
-            .multipart(form)
-            .header("Authorization", format!("Bearer {}", self.auth_token))
-            .send().await?;
-
-        if !response.status().is_success() {
-            return Err(anyhow!("Failed to create file: {}", response.text().await?));
-        }
+        let mut request = self.request(reqwest::Method::POST, self.url("/files")?);
+        request.headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_str(&format!("multipart/form-data; boundary={}", boundary))?
+        );
+        request.body = Some(body.into());
 
-        let file: File = response.json().await?;
-        Ok(file)
+        let response = self.send_request(request).await?;
+        Ok(serde_json::from_slice(&response.bytes().await?)?)
     }
 
     pub async fn list_files(&self) -> Result<Page<File>> {
@@ -103,7 +110,60 @@ impl Client {
         self.fetch(reqwest::Method::GET, &format!("/files/{}", file_id), None).await
     }
 
+    /// The contents of a file. `get_file` and the file's `get` URL return
+    /// its metadata.
+    pub async fn download_file(&self, file_id: &str) -> Result<Bytes> {
+        let url = self.url(&format!("/files/{}/download", file_id))?;
+        self.send_request(self.request(reqwest::Method::GET, url)).await?.bytes().await
+    }
+
     pub async fn delete_file(&self, file_id: &str) -> Result<()> {
         self.fetch(reqwest::Method::DELETE, &format!("/files/{}", file_id), None).await
     }
 }
+
+/// Encodes the `content` and optional `metadata` parts of a file upload. The
+/// boundary is derived from the content so identical uploads produce identical
+/// bodies, which keeps cassette matching stable. Quotes and line breaks in
+/// `filename` are percent-encoded, as browsers do, so it can't end the header.
+fn multipart_body(
+    data: &[u8],
+    filename: &str,
+    content_type: &str,
+    metadata: Option<&str>
+) -> Result<(String, Vec<u8>)> {
+    if content_type.contains(['\r', '\n']) || content_type.parse::<Mime>().is_err() {
+        return Err(anyhow!("invalid content type: {:?}", content_type));
+    }
+    let boundary = format!("repli-{:x}", Sha256::digest(data));
+    let filename = filename.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A");
+
+    let mut body = Vec::new();
+    body.extend_from_slice(
+        format!(
+            concat!(
+                "--{}\r\n",
+                "Content-Disposition: form-data; name=\"content\"; filename=\"{}\"\r\n",
+                "Content-Type: {}\r\n\r\n"
+            ),
+            boundary,
+            filename,
+            content_type
+        ).as_bytes()
+    );
+    body.extend_from_slice(data);
+    body.extend_from_slice(b"\r\n");
+
+    if let Some(metadata) = metadata {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"metadata\"\r\n\r\n{}\r\n",
+                boundary,
+                metadata
+            ).as_bytes()
+        );
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+    Ok((boundary, body))
+}
@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod tests {
+    use super::super::account::Account;
+    use super::super::collection::Collection;
+    use super::super::deployment::Deployment;
+    use super::super::files::File;
+    use super::super::hardware::Hardware;
+    use super::super::model::{ Model, Visibility };
+    use super::super::prediction::Prediction;
+    use super::super::status::Status;
+    use super::super::training::Training;
+    use super::super::webhook::WebhookEventType;
+    use chrono::{ TimeZone, Utc };
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[test]
+    fn test_model() {
+        let model: Model = serde_json::from_str(include_str!("fixtures/model.json")).unwrap();
+        assert_eq!(model.owner, "stability-ai");
+        assert_eq!(model.visibility, Visibility::Public);
+        assert_eq!(model.license_url, None);
+        assert_eq!(model.run_count, 76212318);
+
+        let example = model.default_example.unwrap();
+        assert_eq!(example.status, Status::Succeeded);
+        assert_eq!(example.extra.get("data_removed"), Some(&json!(false)));
+
+        let version = model.latest_version.unwrap();
+        assert_eq!(version.cog_version.as_deref(), Some("0.8.6"));
+    }
+
+    #[test]
+    fn test_model_with_nulls() {
+        let model: Model = serde_json
+            ::from_str(include_str!("fixtures/model_sparse.json"))
+            .unwrap();
+        assert_eq!(model.description, None);
+        assert_eq!(model.github_url, None);
+        assert_eq!(model.cover_image_url, None);
+        assert_eq!(model.run_count, 0);
+        assert!(model.default_example.is_none());
+        assert_eq!(model.latest_version.unwrap().cog_version, None);
+        assert_eq!(model.extra.get("is_official"), Some(&json!(false)));
+    }
+
+    #[test]
+    fn test_extra_fields_round_trip() {
+        let model: Model = serde_json
+            ::from_str(include_str!("fixtures/model_sparse.json"))
+            .unwrap();
+        let value = serde_json::to_value(&model).unwrap();
+        assert_eq!(value["is_official"], json!(false));
+    }
+
+    #[test]
+    fn test_collection() {
+        let collection: Collection = serde_json
+            ::from_str(include_str!("fixtures/collection.json"))
+            .unwrap();
+        assert_eq!(collection.slug, "super-resolution");
+        assert_eq!(collection.description, None);
+        assert!(collection.models.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_account() {
+        let account: Account = serde_json::from_str(include_str!("fixtures/account.json")).unwrap();
+        assert_eq!(account.username, "acme");
+        assert_eq!(account.github_url, None);
+        assert!(account.extra.contains_key("avatar_url"));
+
+        let account: Account = serde_json
+            ::from_value(json!({ "type": "user", "username": "alice", "name": null }))
+            .unwrap();
+        assert_eq!(account.name, "");
+    }
+
+    #[test]
+    fn test_file() {
+        let file: File = serde_json::from_str(include_str!("fixtures/file.json")).unwrap();
+        assert_eq!(file.size, 14);
+        assert_eq!(file.metadata.get("customer_reference_id"), Some(&json!(123)));
+        assert_eq!(file.expires_at, None);
+        assert_eq!(
+            file.created_at,
+            Utc.with_ymd_and_hms(2024, 8, 22, 12, 26, 51).unwrap() + Duration::from_millis(79)
+        );
+    }
+
+    #[test]
+    fn test_file_with_nulls() {
+        let file: File = serde_json::from_str(include_str!("fixtures/file_sparse.json")).unwrap();
+        assert_eq!(file.name, "");
+        assert_eq!(file.content_type, "");
+        assert_eq!(file.size, 0);
+        assert!(file.checksums.is_empty());
+        assert!(file.metadata.is_empty());
+        assert!(file.urls.is_empty());
+    }
+
+    #[test]
+    fn test_deployment() {
+        let deployment: Deployment = serde_json
+            ::from_str(include_str!("fixtures/deployment.json"))
+            .unwrap();
+        let release = deployment.current_release.unwrap();
+        assert_eq!(release.configuration.hardware, Hardware::GpuT4);
+        assert_eq!(release.created_by.github_url.as_deref(), Some("https://github.com/acme"));
+    }
+
+    #[test]
+    fn test_prediction() {
+        let prediction: Prediction = serde_json
+            ::from_str(include_str!("fixtures/prediction.json"))
+            .unwrap();
+        assert_eq!(prediction.status, Status::Succeeded);
+        assert_eq!(prediction.output, Some(json!("hello Alice")));
+        assert_eq!(prediction.metrics.as_ref().unwrap().predict_time, Some(0.012683));
+        assert_eq!(prediction.queue_duration(), Some(Duration::from_micros(13182)));
+        assert_eq!(prediction.run_duration(), Some(Duration::from_micros(12683)));
+    }
+
+    #[test]
+    fn test_prediction_with_nulls() {
+        let prediction: Prediction = serde_json
+            ::from_str(include_str!("fixtures/prediction_sparse.json"))
+            .unwrap();
+        assert_eq!(prediction.model, "");
+        assert!(prediction.version.starts_with("5c7d5dc6"));
+        assert!(prediction.input.is_empty());
+        assert!(prediction.urls.is_none());
+        assert_eq!(prediction.extra.get("data_removed"), Some(&json!(true)));
+
+        let mut value = json!(prediction);
+        value["version"] = json!(null);
+        let prediction: Prediction = serde_json::from_value(value).unwrap();
+        assert_eq!(prediction.version, "");
+    }
+
+    #[test]
+    fn test_training_with_nulls() {
+        let mut value: serde_json::Value = serde_json
+            ::from_str(include_str!("fixtures/training.json"))
+            .unwrap();
+        value["model"] = json!(null);
+        value["version"] = json!(null);
+
+        let training: Training = serde_json::from_value(value).unwrap();
+        assert_eq!((training.model.as_str(), training.version.as_str()), ("", ""));
+    }
+
+    #[test]
+    fn test_training() {
+        let training: Training = serde_json
+            ::from_str(include_str!("fixtures/training.json"))
+            .unwrap();
+        assert_eq!(training.status, Status::Processing);
+        assert!(training.run_duration().is_none());
+
+        let progress = training.progress().unwrap();
+        assert_eq!(progress.current, 450);
+        assert_eq!(progress.total, 1000);
+    }
+
+    #[test]
+    fn test_prediction_with_webhook() {
+        let mut value: serde_json::Value = serde_json
+            ::from_str(include_str!("fixtures/prediction.json"))
+            .unwrap();
+        value["webhook"] = json!("https://example.com/hook");
+        value["webhook_events_filter"] = json!(["start", "completed", "queued"]);
+
+        let prediction: Prediction = serde_json::from_value(value).unwrap();
+        assert_eq!(
+            prediction.webhook_events_filter.unwrap(),
+            vec![
+                WebhookEventType::Start,
+                WebhookEventType::Completed,
+                WebhookEventType::Other("queued".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use std::time::{ Duration, Instant };
+use serde::{ Deserialize, Serialize };
+use anyhow::Result;
+use thiserror::Error;
+use tokio::sync::Mutex;
+use crate::client::Client;
+
+const CATALOG_TTL: Duration = Duration::from_secs(600); // 10 minutes
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Hardware {
+    #[serde(rename = "cpu")]
+    Cpu,
+    #[serde(rename = "gpu-t4")]
+    GpuT4,
+    #[serde(rename = "gpu-l40s")]
+    GpuL40s,
+    #[serde(rename = "gpu-l40s-2x")]
+    GpuL40s2x,
+    #[serde(rename = "gpu-a40-small")]
+    GpuA40Small,
+    #[serde(rename = "gpu-a40-large")]
+    GpuA40Large,
+    #[serde(rename = "gpu-a100-large")]
+    GpuA100Large,
+    #[serde(rename = "gpu-a100-large-2x")]
+    GpuA100Large2x,
+    #[serde(rename = "gpu-h100")]
+    GpuH100,
+    /// A SKU this version of the client doesn't know about yet.
+    #[serde(untagged)]
+    Other(String),
+}
+
+impl std::fmt::Display for Hardware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Hardware::Cpu => write!(f, "cpu"),
+            Hardware::GpuT4 => write!(f, "gpu-t4"),
+            Hardware::GpuL40s => write!(f, "gpu-l40s"),
+            Hardware::GpuL40s2x => write!(f, "gpu-l40s-2x"),
+            Hardware::GpuA40Small => write!(f, "gpu-a40-small"),
+            Hardware::GpuA40Large => write!(f, "gpu-a40-large"),
+            Hardware::GpuA100Large => write!(f, "gpu-a100-large"),
+            Hardware::GpuA100Large2x => write!(f, "gpu-a100-large-2x"),
+            Hardware::GpuH100 => write!(f, "gpu-h100"),
+            Hardware::Other(sku) => write!(f, "{}", sku),
+        }
+    }
+}
+
+/// An entry in the hardware catalog returned by `Client::list_hardware`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareInfo {
+    pub name: String,
+    pub sku: Hardware,
+}
+
+#[derive(Debug, Error)]
+#[error("hardware \"{sku}\" is not available, expected one of: {}", available.join(", "))]
+pub struct UnsupportedHardwareError {
+    pub sku: String,
+    pub available: Vec<String>,
+}
+
+/// The catalog `validate_hardware` last fetched, so creating and updating
+/// models and deployments doesn't fetch it every time.
+#[derive(Debug, Default)]
+pub(crate) struct HardwareCatalog {
+    cached: Mutex<Option<(Instant, Vec<HardwareInfo>)>>,
+}
+
+impl Client {
+    pub async fn list_hardware(&self) -> Result<Vec<HardwareInfo>> {
+        self.fetch(reqwest::Method::GET, "/hardware", None).await
+    }
+
+    /// Checks `hardware` against the catalog returned by `list_hardware`. The
+    /// catalog is cached for ten minutes; a SKU missing from a cached catalog
+    /// is checked against a fresh one before it's rejected.
+    pub async fn validate_hardware(&self, hardware: &Hardware) -> Result<()> {
+        let (mut catalog, fresh) = self.hardware_catalog(false).await?;
+        if !fresh && !catalog.iter().any(|entry| &entry.sku == hardware) {
+            catalog = self.hardware_catalog(true).await?.0;
+        }
+
+        if catalog.iter().any(|entry| &entry.sku == hardware) {
+            Ok(())
+        } else {
+            Err(
+                (UnsupportedHardwareError {
+                    sku: hardware.to_string(),
+                    available: catalog
+                        .iter()
+                        .map(|entry| entry.sku.to_string())
+                        .collect(),
+                }).into()
+            )
+        }
+    }
+
+    /// The cached catalog, or a newly fetched one if it's stale or `refresh`
+    /// is set. The flag is whether it was just fetched.
+    async fn hardware_catalog(&self, refresh: bool) -> Result<(Vec<HardwareInfo>, bool)> {
+        let mut cached = self.hardware_catalog.cached.lock().await;
+        if let Some((fetched_at, catalog)) = cached.as_ref() {
+            if !refresh && fetched_at.elapsed() < CATALOG_TTL {
+                return Ok((catalog.clone(), false));
+            }
+        }
+
+        let catalog = self.list_hardware().await?;
+        *cached = Some((Instant::now(), catalog.clone()));
+        Ok((catalog, true))
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod tests {
+    use super::super::hardware::{ Hardware, HardwareInfo, UnsupportedHardwareError };
+    use serde_json::json;
+
+    #[test]
+    fn test_hardware_known_sku() {
+        let hardware: Hardware = serde_json::from_value(json!("gpu-a40-large")).unwrap();
+        assert_eq!(hardware, Hardware::GpuA40Large);
+        assert_eq!(serde_json::to_value(&hardware).unwrap(), json!("gpu-a40-large"));
+        assert_eq!(hardware.to_string(), "gpu-a40-large");
+    }
+
+    #[test]
+    fn test_hardware_unknown_sku() {
+        let hardware: Hardware = serde_json::from_value(json!("gpu-b200")).unwrap();
+        assert_eq!(hardware, Hardware::Other("gpu-b200".to_string()));
+        assert_eq!(serde_json::to_value(&hardware).unwrap(), json!("gpu-b200"));
+    }
+
+    #[test]
+    fn test_catalog() {
+        let catalog: Vec<HardwareInfo> = serde_json
+            ::from_value(
+                json!([
+                { "name": "CPU", "sku": "cpu" },
+                { "name": "Nvidia T4 GPU", "sku": "gpu-t4" },
+                { "name": "Nvidia A40 (Large) GPU", "sku": "gpu-a40-large" },
+            ])
+            )
+            .unwrap();
+
+        assert_eq!(catalog.len(), 3);
+        assert_eq!(catalog[1].name, "Nvidia T4 GPU");
+        assert_eq!(catalog[1].sku, Hardware::GpuT4);
+    }
+
+    #[test]
+    fn test_unsupported_hardware_error() {
+        let error = UnsupportedHardwareError {
+            sku: "gpu-b200".to_string(),
+            available: vec!["cpu".to_string(), "gpu-t4".to_string()],
+        };
+        assert_eq!(
+            error.to_string(),
+            "hardware \"gpu-b200\" is not available, expected one of: cpu, gpu-t4"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_hardware_caches_catalog() {
+        use bytes::Bytes;
+        use reqwest::StatusCode;
+        use super::super::test_support::{ response, ScriptedTransport };
+
+        let catalog = ScriptedTransport::new(|_| {
+            let body = Bytes::from(r#"[{ "name": "Nvidia T4 GPU", "sku": "gpu-t4" }]"#);
+            Ok(response(StatusCode::OK, body))
+        });
+        let client = catalog.client();
+
+        client.validate_hardware(&Hardware::GpuT4).await.unwrap();
+        client.validate_hardware(&Hardware::GpuT4).await.unwrap();
+        assert_eq!(catalog.requests().len(), 1);
+
+        let unknown = Hardware::Other("gpu-b200".to_string());
+        let error = client.validate_hardware(&unknown).await.unwrap_err();
+        assert!(error.downcast_ref::<UnsupportedHardwareError>().is_some());
+        assert_eq!(catalog.requests().len(), 2);
+    }
+}
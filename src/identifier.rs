@@ -2,7 +2,9 @@ use std::fmt;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
-#[error("invalid identifier, it must be in the format \"owner/name\" or \"owner/name:version\"")]
+#[error(
+    "invalid identifier, it must be in the format \"owner/name\", \"owner/name:version\" or \"deployments/owner/name\""
+)]
 pub struct InvalidIdentifierError;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -0,0 +1,140 @@
+//! Hooks around every API request.
+//!
+//! Interceptors added with `Client::with_interceptor` run in the order they
+//! were added, for every request the client sends: resource methods, file
+//! uploads and SSE streams alike.
+
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+use serde_json::Value;
+use crate::transport::{ HttpRequest, HttpResponse };
+
+const REDACTED: &str = "[REDACTED]";
+
+pub trait Interceptor: Send + Sync {
+    /// Runs once before a request is sent, retries included. Headers added
+    /// here are sent; returning an error aborts the request.
+    fn before_request(&self, _request: &mut HttpRequest) -> Result<()> {
+        Ok(())
+    }
+
+    /// Runs for every response, including error statuses that will be
+    /// retried. The body hasn't been read yet.
+    fn after_response(
+        &self,
+        _request: &HttpRequest,
+        _response: &HttpResponse,
+        _elapsed: Duration
+    ) {}
+
+    /// Runs when a request fails for good: the transport errored or the last
+    /// attempt got an error status.
+    fn on_error(&self, _request: &HttpRequest, _error: &anyhow::Error) {}
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct InterceptorChain {
+    interceptors: Vec<Arc<dyn Interceptor>>,
+}
+
+impl InterceptorChain {
+    pub(crate) fn push(&mut self, interceptor: Arc<dyn Interceptor>) {
+        self.interceptors.push(interceptor);
+    }
+
+    pub(crate) fn before_request(&self, request: &mut HttpRequest) -> Result<()> {
+        self.interceptors
+            .iter()
+            .try_for_each(|interceptor| interceptor.before_request(request))
+    }
+
+    pub(crate) fn after_response(
+        &self,
+        request: &HttpRequest,
+        response: &HttpResponse,
+        elapsed: Duration
+    ) {
+        for interceptor in &self.interceptors {
+            interceptor.after_response(request, response, elapsed);
+        }
+    }
+
+    pub(crate) fn on_error(&self, request: &HttpRequest, error: &anyhow::Error) {
+        for interceptor in &self.interceptors {
+            interceptor.on_error(request, error);
+        }
+    }
+}
+
+/// Logs each request and response at debug level. Request bodies are logged
+/// with prediction `input` and any other listed fields replaced.
+#[derive(Debug, Clone)]
+pub struct LogInterceptor {
+    redacted_fields: Vec<String>,
+}
+
+impl Default for LogInterceptor {
+    fn default() -> Self {
+        Self { redacted_fields: vec!["input".to_string()] }
+    }
+}
+
+impl LogInterceptor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also redacts top-level body field `field`.
+    pub fn with_redacted_field(mut self, field: &str) -> Self {
+        self.redacted_fields.push(field.to_string());
+        self
+    }
+
+    /// Logs bodies as sent, inputs included.
+    pub fn without_redaction(mut self) -> Self {
+        self.redacted_fields.clear();
+        self
+    }
+
+    pub(crate) fn redact(&self, body: &[u8]) -> String {
+        match serde_json::from_slice::<Value>(body) {
+            Ok(Value::Object(mut fields)) => {
+                for field in &self.redacted_fields {
+                    if let Some(value) = fields.get_mut(field) {
+                        *value = Value::String(REDACTED.to_string());
+                    }
+                }
+                Value::Object(fields).to_string()
+            }
+            Ok(value) if self.redacted_fields.is_empty() => value.to_string(),
+            _ => format!("<{} bytes>", body.len()),
+        }
+    }
+}
+
+impl Interceptor for LogInterceptor {
+    fn before_request(&self, request: &mut HttpRequest) -> Result<()> {
+        match &request.body {
+            Some(body) => {
+                log::debug!("{} {} body: {}", request.method, request.url, self.redact(body));
+            }
+            None => log::debug!("{} {}", request.method, request.url),
+        }
+        Ok(())
+    }
+
+    fn after_response(&self, request: &HttpRequest, response: &HttpResponse, elapsed: Duration) {
+        log::debug!(
+            "{} {} -> {} in {} ms",
+            request.method,
+            request.url,
+            response.status,
+            elapsed.as_millis()
+        );
+    }
+
+    fn on_error(&self, request: &HttpRequest, error: &anyhow::Error) {
+        log::debug!("{} {} failed: {}", request.method, request.url, error);
+    }
+}
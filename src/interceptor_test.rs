@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::{ Arc, Mutex };
+    use std::time::Duration;
+    use anyhow::{ Result, anyhow };
+    use bytes::Bytes;
+    use reqwest::header::HeaderValue;
+    use reqwest::StatusCode;
+    use serde_json::json;
+    use super::super::backoff::ConstantBackoff;
+    use super::super::client::Client;
+    use super::super::interceptor::{ Interceptor, LogInterceptor };
+    use super::super::prediction::PredictionInput;
+    use super::super::request::{ PredictionRequest, Target };
+    use super::super::test_support::{ response, ScriptedTransport };
+    use super::super::transport::{ HttpRequest, HttpResponse };
+
+    /// Answers requests with the given statuses in turn, and fails to
+    /// connect once they run out.
+    fn double(statuses: &[u16]) -> ScriptedTransport {
+        let statuses: Mutex<VecDeque<u16>> = Mutex::new(statuses.iter().copied().collect());
+        ScriptedTransport::new(move |_| {
+            let status = statuses.lock().unwrap().pop_front();
+            let status = status.ok_or_else(|| anyhow!("connection refused"))?;
+            let body = Bytes::from(include_str!("fixtures/prediction.json"));
+            Ok(response(StatusCode::from_u16(status)?, body))
+        })
+    }
+
+    /// Writes what it sees to a shared log, tagged with `name`.
+    struct Recording {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Interceptor for Recording {
+        fn before_request(&self, request: &mut HttpRequest) -> Result<()> {
+            self.log.lock().unwrap().push(format!("{} before {}", self.name, request.url.path()));
+            request.headers.insert("x-trace-id", HeaderValue::from_static("trace-1"));
+            Ok(())
+        }
+
+        fn after_response(&self, _request: &HttpRequest, response: &HttpResponse, _: Duration) {
+            let entry = format!("{} after {}", self.name, response.status.as_u16());
+            self.log.lock().unwrap().push(entry);
+        }
+
+        fn on_error(&self, _request: &HttpRequest, error: &anyhow::Error) {
+            self.log.lock().unwrap().push(format!("{} error {}", self.name, error));
+        }
+    }
+
+    fn client(double: &ScriptedTransport, log: &Arc<Mutex<Vec<String>>>) -> Client {
+        Client::new(Some("r8_token".to_string()))
+            .unwrap()
+            .with_transport(double.clone())
+            .with_max_retries(1)
+            .with_backoff(
+                Box::new(ConstantBackoff {
+                    base: Duration::ZERO,
+                    jitter: Duration::from_millis(1),
+                })
+            )
+            .with_interceptor(Recording { name: "a", log: log.clone() })
+            .with_interceptor(Recording { name: "b", log: log.clone() })
+    }
+
+    #[tokio::test]
+    async fn test_interceptors_see_every_attempt() {
+        let double = double(&[503, 200]);
+        let log = Arc::default();
+        client(&double, &log).get_prediction("abc").await.unwrap();
+
+        assert_eq!(*log.lock().unwrap(), [
+            "a before /v1/predictions/abc",
+            "b before /v1/predictions/abc",
+            "a after 503",
+            "b after 503",
+            "a after 200",
+            "b after 200",
+        ]);
+        let requests = double.requests();
+        assert!(requests.iter().all(|request| request.headers["x-trace-id"] == "trace-1"));
+    }
+
+    #[tokio::test]
+    async fn test_on_error() {
+        let log = Arc::default();
+        let error = client(&double(&[404]), &log).get_prediction("abc").await.unwrap_err();
+        assert!(error.to_string().starts_with("Request failed"));
+        assert!(log.lock().unwrap()[4].starts_with("a error Request failed"));
+
+        let log = Arc::default();
+        client(&double(&[]), &log).get_prediction("abc").await.unwrap_err();
+        assert_eq!(log.lock().unwrap()[2..], [
+            "a error connection refused",
+            "b error connection refused",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_before_request_can_abort() {
+        struct Deny;
+        impl Interceptor for Deny {
+            fn before_request(&self, _request: &mut HttpRequest) -> Result<()> {
+                Err(anyhow!("denied"))
+            }
+        }
+
+        let double = double(&[200]);
+        let client = Client::new(Some("r8_token".to_string()))
+            .unwrap()
+            .with_transport(double.clone())
+            .with_interceptor(Deny);
+        let request = PredictionRequest::new(
+            Target::Version("v".to_string()),
+            PredictionInput::new()
+        );
+        let error = client.create_prediction_from_request(request).await.unwrap_err();
+        assert_eq!(error.to_string(), "denied");
+        assert!(double.requests().is_empty());
+    }
+
+    #[test]
+    fn test_log_interceptor_redacts_input() {
+        let body = json!({ "version": "v", "input": { "api_key": "secret" } }).to_string();
+
+        let redacted = LogInterceptor::new().redact(body.as_bytes());
+        assert!(!redacted.contains("secret"));
+        assert!(redacted.contains("\"version\":\"v\""));
+
+        let redacted = LogInterceptor::new()
+            .with_redacted_field("version")
+            .redact(body.as_bytes());
+        assert!(!redacted.contains("\"v\""));
+
+        let unredacted = LogInterceptor::new().without_redaction().redact(body.as_bytes());
+        assert!(unredacted.contains("secret"));
+        assert_eq!(LogInterceptor::new().redact(b"\x00\x01"), "<2 bytes>");
+    }
+}
@@ -0,0 +1,256 @@
+//! Crash recovery for in-flight predictions.
+//!
+//! A `PredictionJournal` attached with `Client::with_journal` appends a JSON
+//! line to its file for every prediction the client creates, with the tags
+//! set by `PredictionRequest::with_tag`, and another once the client sees that
+//! prediction finish. After a restart, `Client::recover_predictions` picks up
+//! the ones that hadn't finished and waits on them, cancels them or just
+//! reports where they are.
+
+use std::collections::BTreeMap;
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, Mutex };
+use anyhow::{ Result, anyhow };
+use chrono::{ DateTime, Utc };
+use futures::future::join_all;
+use serde::{ Deserialize, Serialize };
+use crate::client::Client;
+use crate::jsonl;
+use crate::prediction::Prediction;
+use crate::status::Status;
+
+/// A prediction the journal saw created.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: String,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One line of the journal file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum JournalRecord {
+    Created(JournalEntry),
+    Finished {
+        id: String,
+        status: Status,
+    },
+    /// Dropped with `PredictionJournal::forget` without being seen finishing.
+    Forgotten {
+        id: String,
+    },
+}
+
+/// What `Client::recover_predictions` does with an outstanding prediction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryPolicy {
+    /// Wait for it to finish.
+    Resume,
+    /// Cancel it.
+    Cancel,
+    /// Fetch it and leave it running. It stays in the journal.
+    Inspect,
+}
+
+#[derive(Debug)]
+pub struct RecoveredPrediction {
+    pub entry: JournalEntry,
+    pub policy: RecoveryPolicy,
+    /// The prediction as the policy left it.
+    pub result: Result<Prediction>,
+}
+
+/// An append-only record of the predictions a client created and hasn't yet
+/// seen finish. It's backed by a JSON Lines file that outlives the process.
+#[derive(Debug, Clone)]
+pub struct PredictionJournal {
+    inner: Arc<JournalInner>,
+}
+
+#[derive(Debug)]
+struct JournalInner {
+    path: PathBuf,
+    /// Held for every write, so lines land in the order `outstanding`
+    /// changes.
+    file: tokio::sync::Mutex<tokio::fs::File>,
+    outstanding: Mutex<Vec<JournalEntry>>,
+}
+
+impl PredictionJournal {
+    /// Opens the journal at `path`, creating it if needed. Predictions that
+    /// already finished are dropped from the file, so it only ever holds
+    /// what's in flight plus what finished since.
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let outstanding = parse_journal(&jsonl::read(&path).await?)?;
+        compact(&path, &outstanding).await?;
+        let file = jsonl::open_append(&path).await?;
+
+        Ok(Self {
+            inner: Arc::new(JournalInner {
+                path,
+                file: tokio::sync::Mutex::new(file),
+                outstanding: Mutex::new(outstanding),
+            }),
+        })
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.inner.path.clone()
+    }
+
+    /// Predictions created but not yet seen finishing, oldest first.
+    pub fn outstanding(&self) -> Vec<JournalEntry> {
+        self.inner.outstanding.lock().unwrap().clone()
+    }
+
+    pub(crate) async fn record_created(
+        &self,
+        prediction: &Prediction,
+        tags: BTreeMap<String, String>
+    ) -> Result<()> {
+        let entry = JournalEntry {
+            id: prediction.id.clone(),
+            model: prediction.model.clone(),
+            tags,
+            created_at: prediction.created_at,
+        };
+        let mut file = self.inner.file.lock().await;
+        jsonl::append(&mut file, &JournalRecord::Created(entry.clone())).await?;
+        self.inner.outstanding.lock().unwrap().push(entry);
+        drop(file);
+
+        // A prediction can come back from creation already finished.
+        self.record_finished(prediction).await
+    }
+
+    /// Marks `prediction` finished if it's terminal and outstanding.
+    pub(crate) async fn record_finished(&self, prediction: &Prediction) -> Result<()> {
+        if !prediction.status.is_terminated() {
+            return Ok(());
+        }
+
+        let record = JournalRecord::Finished {
+            id: prediction.id.clone(),
+            status: prediction.status,
+        };
+        self.remove(&prediction.id, &record).await?;
+        Ok(())
+    }
+
+    /// Drops `id` from the journal without waiting to see it finish, e.g. a
+    /// prediction that can no longer be fetched. Returns whether it was
+    /// outstanding.
+    pub async fn forget(&self, id: &str) -> Result<bool> {
+        self.remove(id, &(JournalRecord::Forgotten { id: id.to_string() })).await
+    }
+
+    /// Appends `record` and drops `id` from `outstanding`, if it's there.
+    async fn remove(&self, id: &str, record: &JournalRecord) -> Result<bool> {
+        let mut file = self.inner.file.lock().await;
+        let outstanding = self.inner.outstanding.lock().unwrap().iter().any(|e| e.id == id);
+        if !outstanding {
+            return Ok(false);
+        }
+
+        jsonl::append(&mut file, record).await?;
+        self.inner.outstanding.lock().unwrap().retain(|e| e.id != id);
+        Ok(true)
+    }
+}
+
+/// Rewrites the journal with just the `Created` lines of `outstanding`.
+async fn compact(path: &Path, outstanding: &[JournalEntry]) -> Result<()> {
+    let records = outstanding.iter().cloned().map(JournalRecord::Created);
+    jsonl::rewrite(path, records).await
+}
+
+/// Replays journal lines into the predictions still outstanding. As with
+/// batch checkpoints, a malformed last line is dropped and any other is an
+/// error.
+pub(crate) fn parse_journal(contents: &str) -> Result<Vec<JournalEntry>> {
+    let records: Vec<JournalRecord> = jsonl::parse(contents, "journal")?;
+    let mut outstanding: Vec<JournalEntry> = Vec::new();
+
+    for record in records {
+        match record {
+            JournalRecord::Created(entry) => {
+                outstanding.retain(|e| e.id != entry.id);
+                outstanding.push(entry);
+            }
+            JournalRecord::Finished { id, .. } | JournalRecord::Forgotten { id } => {
+                outstanding.retain(|e| e.id != id);
+            }
+        }
+    }
+
+    Ok(outstanding)
+}
+
+impl Client {
+    /// Records every prediction this client creates to `journal` until it
+    /// finishes.
+    pub fn with_journal(mut self, journal: PredictionJournal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Applies `policy` to every outstanding prediction in the journal, all
+    /// at once. Predictions found finished, waited on or canceled leave the
+    /// journal as the client sees them finish. Ones that can't be fetched
+    /// stay until they're dropped with `PredictionJournal::forget`.
+    pub async fn recover_predictions(
+        &self,
+        policy: impl Fn(&JournalEntry) -> RecoveryPolicy
+    ) -> Result<Vec<RecoveredPrediction>> {
+        let journal = self.journal.as_ref().ok_or_else(|| anyhow!("No journal attached"))?;
+
+        let recoveries = journal
+            .outstanding()
+            .into_iter()
+            .map(|entry| {
+                let policy = policy(&entry);
+                async move {
+                    let result = self.recover_prediction(&entry.id, policy).await;
+                    RecoveredPrediction { entry, policy, result }
+                }
+            });
+        Ok(join_all(recoveries).await)
+    }
+
+    async fn recover_prediction(&self, id: &str, policy: RecoveryPolicy) -> Result<Prediction> {
+        let prediction = self.get_prediction(id).await?;
+        if prediction.status.is_terminated() {
+            return Ok(prediction);
+        }
+
+        match policy {
+            RecoveryPolicy::Resume => self.wait(&prediction).await,
+            RecoveryPolicy::Cancel => self.cancel_prediction(id).await,
+            RecoveryPolicy::Inspect => Ok(prediction),
+        }
+    }
+
+    pub(crate) async fn journal_created(
+        &self,
+        prediction: &Prediction,
+        tags: BTreeMap<String, String>
+    ) {
+        if let Some(journal) = &self.journal {
+            if let Err(e) = journal.record_created(prediction, tags).await {
+                log::error!("Failed to journal prediction {}: {}", prediction.id, e);
+            }
+        }
+    }
+
+    pub(crate) async fn journal_finished(&self, prediction: &Prediction) {
+        if let Some(journal) = &self.journal {
+            if let Err(e) = journal.record_finished(prediction).await {
+                log::error!("Failed to journal prediction {}: {}", prediction.id, e);
+            }
+        }
+    }
+}
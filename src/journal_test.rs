@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use super::super::journal::{ parse_journal, JournalRecord, PredictionJournal };
+    use super::super::status::Status;
+    use super::super::test_support::prediction;
+
+    fn created(id: &str) -> String {
+        let prediction = prediction(id, Status::Starting);
+        format!(
+            r#"{{"event":"created","id":"{}","model":"{}","created_at":"{}"}}"#,
+            id,
+            prediction.model,
+            prediction.created_at.to_rfc3339()
+        )
+    }
+
+    fn finished(id: &str) -> String {
+        serde_json
+            ::to_string(
+                &(JournalRecord::Finished { id: id.to_string(), status: Status::Succeeded })
+            )
+            .unwrap()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env
+            ::temp_dir()
+            .join(format!("repli-journal-{}-{}.jsonl", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_parse_journal() {
+        let contents = format!("{}\n{}\n\n{}\n", created("a"), created("b"), finished("a"));
+        let outstanding = parse_journal(&contents).unwrap();
+
+        assert_eq!(outstanding.len(), 1);
+        assert_eq!(outstanding[0].id, "b");
+        assert!(outstanding[0].tags.is_empty());
+    }
+
+    #[test]
+    fn test_parse_journal_drops_truncated_last_line() {
+        let line = created("b");
+        let contents = format!("{}\n{}", created("a"), &line[..line.len() / 2]);
+        assert_eq!(parse_journal(&contents).unwrap().len(), 1);
+
+        let contents = format!("{{not json\n{}\n", created("a"));
+        assert!(parse_journal(&contents).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_open_compacts_finished_predictions() {
+        let path = temp_path("compact");
+        let journal = PredictionJournal::open(&path).await.unwrap();
+        let tags = [("job".to_string(), "nightly".to_string())].into();
+        journal.record_created(&prediction("a", Status::Starting), tags).await.unwrap();
+        for (id, status) in [("b", Status::Starting), ("c", Status::Succeeded)] {
+            journal.record_created(&prediction(id, status), Default::default()).await.unwrap();
+        }
+        journal.record_finished(&prediction("b", Status::Processing)).await.unwrap();
+        journal.record_finished(&prediction("b", Status::Failed)).await.unwrap();
+        journal.record_finished(&prediction("b", Status::Failed)).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 5);
+        drop(journal);
+
+        let journal = PredictionJournal::open(&path).await.unwrap();
+        let outstanding = journal.outstanding();
+        assert_eq!(outstanding.len(), 1);
+        assert_eq!(outstanding[0].id, "a");
+        assert_eq!(outstanding[0].tags["job"], "nightly");
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+
+        assert!(journal.forget("a").await.unwrap());
+        assert!(!journal.forget("a").await.unwrap());
+        drop(journal);
+        assert!(PredictionJournal::open(&path).await.unwrap().outstanding().is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_recover_predictions() {
+        use super::super::journal::RecoveryPolicy;
+        use super::super::request::{ PredictionRequest, Target };
+        use super::super::testing::MockServer;
+
+        let path = temp_path("recover");
+        let server = MockServer::start_with_step_delay(None).await.unwrap();
+        let client = server.client().with_journal(PredictionJournal::open(&path).await.unwrap());
+        let mut ids = Vec::new();
+        for policy in ["resume", "cancel", "inspect"] {
+            let target = Target::Version("abc123".to_string());
+            let request = PredictionRequest::new(target, Default::default()).with_tag(
+                "policy",
+                policy
+            );
+            ids.push(client.create_prediction_from_request(request).await.unwrap().id);
+        }
+        drop(client);
+
+        // The first one finished while nothing was watching.
+        server.advance(&ids[0]).await.unwrap();
+        server.advance(&ids[0]).await.unwrap();
+
+        let journal = PredictionJournal::open(&path).await.unwrap();
+        assert_eq!(journal.outstanding().len(), 3);
+        let client = server.client().with_journal(journal.clone());
+        let recovered = client
+            .recover_predictions(|entry| {
+                match entry.tags["policy"].as_str() {
+                    "resume" => RecoveryPolicy::Resume,
+                    "cancel" => RecoveryPolicy::Cancel,
+                    _ => RecoveryPolicy::Inspect,
+                }
+            }).await
+            .unwrap();
+
+        let statuses: Vec<Status> = recovered
+            .iter()
+            .map(|r| r.result.as_ref().unwrap().status)
+            .collect();
+        assert_eq!(statuses, [Status::Succeeded, Status::Canceled, Status::Starting]);
+        assert_eq!(recovered[2].policy, RecoveryPolicy::Inspect);
+        let outstanding = journal.outstanding();
+        assert_eq!(outstanding.len(), 1);
+        assert_eq!(outstanding[0].id, ids[2]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,79 @@
+//! JSON Lines files shared by the prediction journal and batch checkpoints.
+//!
+//! Both append one record per line as work happens and rewrite the file
+//! when it's opened. A process that dies mid-append leaves a partial last
+//! line, so reading drops a malformed last line and rejects one anywhere
+//! else.
+
+use std::path::Path;
+use anyhow::{ Result, anyhow };
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::fs::{ File, OpenOptions };
+use tokio::io::AsyncWriteExt;
+
+/// The contents of `path`, or nothing if it doesn't exist.
+pub(crate) async fn read(path: &Path) -> Result<String> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(contents) => Ok(contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Parses one record per non-blank line. `kind` names the file in errors and
+/// warnings.
+pub(crate) fn parse<T: DeserializeOwned>(contents: &str, kind: &str) -> Result<Vec<T>> {
+    let lines: Vec<&str> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let mut records = Vec::with_capacity(lines.len());
+
+    for (i, line) in lines.iter().enumerate() {
+        match serde_json::from_str(line) {
+            Ok(record) => records.push(record),
+            Err(e) if i == lines.len() - 1 => {
+                log::warn!("Ignoring truncated {} line: {}", kind, e);
+            }
+            Err(e) => {
+                return Err(anyhow!("invalid {} line {}: {}", kind, i + 1, e));
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+/// Replaces the contents of `path` with `records`. The new file is renamed
+/// into place so a crash leaves either the old contents or the new ones.
+pub(crate) async fn rewrite<T: Serialize>(
+    path: &Path,
+    records: impl IntoIterator<Item = T>
+) -> Result<()> {
+    let mut contents = String::new();
+    for record in records {
+        contents.push_str(&serde_json::to_string(&record)?);
+        contents.push('\n');
+    }
+
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    tokio::fs::write(&tmp, contents).await?;
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+/// Opens `path` for appending, creating it if needed.
+pub(crate) async fn open_append(path: &Path) -> Result<File> {
+    Ok(OpenOptions::new().create(true).append(true).open(path).await?)
+}
+
+/// Writes `record` as one line and flushes it.
+pub(crate) async fn append<T: Serialize>(file: &mut File, record: &T) -> Result<()> {
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    file.write_all(line.as_bytes()).await?;
+    file.flush().await?;
+    Ok(())
+}
@@ -0,0 +1,33 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::{ json, Value };
+    use super::super::jsonl;
+
+    #[tokio::test]
+    async fn test_rewrite_then_append() {
+        let path = std::env
+            ::temp_dir()
+            .join(format!("repli-jsonl-{}.jsonl", std::process::id()));
+        tokio::fs::write(&path, "{\"n\":0}\n{\"n\":").await.unwrap();
+
+        let records: Vec<Value> = jsonl::parse(&jsonl::read(&path).await.unwrap(), "test").unwrap();
+        assert_eq!(records, [json!({ "n": 0 })]);
+        jsonl::rewrite(&path, &records).await.unwrap();
+        let mut file = jsonl::open_append(&path).await.unwrap();
+        jsonl::append(&mut file, &json!({ "n": 1 })).await.unwrap();
+
+        assert_eq!(jsonl::read(&path).await.unwrap(), "{\"n\":0}\n{\"n\":1}\n");
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        assert!(!std::path::Path::new(&tmp).exists());
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(jsonl::read(&path).await.unwrap(), "");
+    }
+
+    #[test]
+    fn test_parse_rejects_corrupt_line_before_the_last() {
+        let error = jsonl::parse::<Value>("{\n{}\n", "test").unwrap_err();
+        assert_eq!(error.to_string().split(':').next(), Some("invalid test line 1"));
+    }
+}
@@ -3,48 +3,161 @@
 //! This is a Rust client for Replicate's API.
 //!
 //! See https://replicate.com/docs for more information.
+//!
+//! The resource types keep any fields the API returns that they don't model
+//! yet in their `extra` map, and serialize them back out unchanged.
 
 // Declare modules
 mod account;
 mod api;
 mod backoff;
+mod batch;
+pub mod blocking;
+mod cache;
+mod cassette;
 mod client;
 mod collection;
 mod deployment;
 mod error;
 mod examples;
+mod fields;
 mod files;
+mod hardware;
 mod identifier;
+mod interceptor;
+mod journal;
+mod jsonl;
 mod model;
+mod output;
 mod paginate;
 mod prediction;
+mod progress;
+#[cfg(feature = "webhook-receiver")]
+mod receiver;
+mod reconcile;
+mod request;
+mod rollout;
 mod run;
 mod status;
 mod stream;
+mod telemetry;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod training;
+mod transport;
+mod usage;
 mod wait;
 mod webhook;
+mod batch_test;
+#[cfg(feature = "testing")]
+mod blocking_test;
+mod cache_test;
+mod cassette_test;
+mod client_test;
+mod deployment_test;
+mod fixtures_test;
+mod hardware_test;
 mod identifier_test;
+mod interceptor_test;
+mod journal_test;
+mod jsonl_test;
+mod model_test;
+mod output_test;
+mod progress_test;
+#[cfg(feature = "webhook-receiver")]
+mod receiver_test;
+mod reconcile_test;
+mod request_test;
+mod rollout_test;
+mod telemetry_test;
+#[cfg(test)]
+mod test_support;
+mod transport_test;
+mod usage_test;
+mod wait_test;
+mod webhook_test;
 // Re-export main structs and functions
 pub use crate::account::Account;
-pub use crate::backoff::{ Backoff, ExponentialBackoff };
+pub use crate::backoff::{ Backoff, ConstantBackoff, ExponentialBackoff };
+pub use crate::batch::{ BatchRunner, BatchOrder, BatchResult, RetryPolicy };
+pub use crate::cache::{ CachedPrediction, PredictionCache };
+pub use crate::cassette::{ Cassette, CassetteMode, MatchRules };
 pub use crate::client::Client;
 pub use crate::collection::Collection;
-pub use crate::deployment::{ Deployment, CreateDeploymentOptions, UpdateDeploymentOptions };
-pub use crate::error::ModelError;
+pub use crate::deployment::{
+    Deployment,
+    DeploymentRelease,
+    DeploymentConfiguration,
+    CreateDeploymentOptions,
+    UpdateDeploymentOptions,
+};
+pub use crate::error::{ APIError, ModelError, RequestError };
 pub use crate::files::{ File, CreateFileOptions };
+pub use crate::hardware::{ Hardware, HardwareInfo, UnsupportedHardwareError };
 pub use crate::identifier::{ Identifier, InvalidIdentifierError };
-pub use crate::model::{ Model, ModelVersion, CreateModelOptions };
+pub use crate::interceptor::{ Interceptor, LogInterceptor };
+pub use crate::journal::{
+    JournalEntry,
+    PredictionJournal,
+    RecoveredPrediction,
+    RecoveryPolicy,
+};
+pub use crate::model::{
+    Model,
+    ModelVersion,
+    CreateModelOptions,
+    UpdateModelOptions,
+    Visibility,
+};
+pub use crate::output::{ output_urls, DownloadedFile };
 pub use crate::paginate::Page;
 pub use crate::prediction::{
     Prediction,
     PredictionInput,
     PredictionOutput,
-    PredictionProgress,
     PredictionMetrics,
+    CreatePredictionParams,
     Source,
 };
+pub use crate::progress::{
+    PredictionProgress,
+    ProgressFormat,
+    ProgressKind,
+    ProgressReporter,
+    ProgressUpdate,
+};
+#[cfg(feature = "webhook-receiver")]
+pub use crate::receiver::{ WebhookReceiver, WebhookPayload };
+pub use crate::reconcile::{
+    DeploymentAction,
+    ReconcilePlan,
+    ReconcileOptions,
+    load_desired_deployments,
+};
+pub use crate::request::{ PredictionRequest, Target };
+pub use crate::rollout::{ RolloutOptions, RolloutReport, RolloutError };
 pub use crate::status::Status;
 pub use crate::stream::{ SSEEvent, InvalidUTF8DataError };
-pub use crate::training::Training;
-pub use crate::webhook::{ Webhook, WebhookEvent };
+pub use crate::training::{ Training, CreateTrainingOptions };
+pub use crate::transport::{ Transport, HttpRequest, HttpResponse, ReqwestTransport };
+#[cfg(feature = "tower")]
+pub use crate::transport::TowerTransport;
+pub use crate::usage::{
+    UsageTracker,
+    UsageSummary,
+    ModelUsage,
+    ModelUsageSummary,
+    PriceTable,
+    TokenPrice,
+};
+pub use crate::wait::WebhookWaiter;
+pub use crate::webhook::{
+    Webhook,
+    WebhookEvent,
+    WebhookEventType,
+    WebhookSigningSecret,
+    WebhookValidationError,
+    validate_webhook,
+    validate_webhook_with_tolerance,
+    DEFAULT_WEBHOOK_TOLERANCE,
+};
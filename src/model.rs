@@ -1,37 +1,86 @@
+use std::collections::HashMap;
+use chrono::{ DateTime, Utc };
 use serde::{ Deserialize, Serialize };
+use crate::hardware::Hardware;
 use crate::prediction::Prediction;
+use crate::fields::null_as_default;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Visibility {
+    #[serde(rename = "public")]
+    Public,
+    #[serde(rename = "private")]
+    Private,
+}
+
+impl std::fmt::Display for Visibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Visibility::Public => write!(f, "public"),
+            Visibility::Private => write!(f, "private"),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Model {
     pub url: String,
     pub owner: String,
     pub name: String,
-    pub description: String,
-    pub visibility: String,
-    pub github_url: String,
-    pub paper_url: String,
-    pub license_url: String,
-    pub run_count: i32,
-    pub cover_image_url: String,
+    pub description: Option<String>,
+    pub visibility: Visibility,
+    pub github_url: Option<String>,
+    pub paper_url: Option<String>,
+    pub license_url: Option<String>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub run_count: i64,
+    pub cover_image_url: Option<String>,
     pub default_example: Option<Prediction>,
     pub latest_version: Option<ModelVersion>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelVersion {
     pub id: String,
-    pub created_at: String,
-    pub cog_version: String,
+    pub created_at: DateTime<Utc>,
+    pub cog_version: Option<String>,
+    #[serde(default)]
     pub openapi_schema: serde_json::Value,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateModelOptions {
-    pub visibility: String,
-    pub hardware: String,
+    pub visibility: Visibility,
+    pub hardware: Hardware,
+    pub description: Option<String>,
+    pub github_url: Option<String>,
+    pub paper_url: Option<String>,
+    pub license_url: Option<String>,
+    pub cover_image_url: Option<String>,
+}
+
+/// Fields to change on an existing model. Fields left as `None` are not sent,
+/// so the API keeps their current value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateModelOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<Visibility>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub readme: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub github_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub paper_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub license_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weights_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub cover_image_url: Option<String>,
 }
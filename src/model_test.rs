@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use reqwest::StatusCode;
+    use super::super::model::{ Model, UpdateModelOptions, Visibility };
+    use super::super::test_support::{ json_response, ScriptedTransport };
+    use serde_json::json;
+
+    /// Serves `/models` as pages of two models each.
+    fn catalog() -> ScriptedTransport {
+        ScriptedTransport::new(|request| {
+            let page: usize = request.url
+                .query_pairs()
+                .find(|(key, _)| key == "page")
+                .and_then(|(_, value)| value.parse().ok())
+                .unwrap_or(0);
+
+            let owners = [["acme", "other"], ["other", "other"], ["acme", "acme"]];
+            let results: Vec<Model> = owners[page]
+                .iter()
+                .enumerate()
+                .map(|(i, owner)| {
+                    let mut model: Model = serde_json
+                        ::from_str(include_str!("fixtures/model.json"))
+                        .unwrap();
+                    model.owner = owner.to_string();
+                    model.name = format!("model-{}-{}", page, i);
+                    model
+                })
+                .collect();
+            let next = (page + 1 < owners.len()).then(|| {
+                format!("https://api.replicate.com/v1/models?page={}", page + 1)
+            });
+            json_response(StatusCode::OK, &json!({ "results": results, "next": next }))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_list_models_by_owner_fetches_pages_lazily() {
+        let catalog = catalog();
+        let client = catalog.client();
+
+        let first = client.list_models_by_owner("acme", 10).next().await.unwrap().unwrap();
+        assert_eq!(first.name, "model-0-0");
+        assert_eq!(catalog.requests().len(), 1);
+
+        let names: Vec<String> = client
+            .list_models_by_owner("acme", 10)
+            .map(|model| model.unwrap().name)
+            .collect().await;
+        assert_eq!(names, ["model-0-0", "model-2-0", "model-2-1"]);
+        assert_eq!(catalog.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_list_models_by_owner_stops_at_page_limit() {
+        let catalog = catalog();
+
+        let names: Vec<String> = catalog
+            .client()
+            .list_models_by_owner("acme", 2)
+            .map(|model| model.unwrap().name)
+            .collect().await;
+        assert_eq!(names, ["model-0-0"]);
+        assert_eq!(catalog.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_visibility() {
+        assert_eq!(serde_json::to_value(Visibility::Private).unwrap(), json!("private"));
+        assert!(serde_json::from_value::<Visibility>(json!("secret")).is_err());
+    }
+
+    #[test]
+    fn test_update_model_options_skips_unset_fields() {
+        let options = UpdateModelOptions {
+            description: Some("A new description".to_string()),
+            visibility: Some(Visibility::Public),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(options).unwrap(),
+            json!({ "description": "A new description", "visibility": "public" })
+        );
+    }
+}
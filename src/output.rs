@@ -0,0 +1,128 @@
+//! Downloading the files a prediction's output links to.
+//!
+//! `Client::download_outputs` saves every URL in an output to a directory. The
+//! prediction cache uses it to keep outputs past the hour Replicate's delivery
+//! URLs last, and the `repli` binary to save what a run produced.
+
+use std::collections::HashSet;
+use std::path::{ Path, PathBuf };
+use anyhow::Result;
+use bytes::Bytes;
+use reqwest::{ Method, Url };
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+use crate::client::Client;
+use crate::transport::HttpRequest;
+
+/// An output URL and where it was saved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadedFile {
+    pub url: String,
+    pub path: PathBuf,
+}
+
+/// Every `http(s)://` string in `output`, in order.
+pub fn output_urls(output: &Value) -> Vec<&str> {
+    match output {
+        Value::String(text) if text.starts_with("https://") || text.starts_with("http://") => {
+            vec![text.as_str()]
+        }
+        Value::Array(items) => items.iter().flat_map(output_urls).collect(),
+        Value::Object(fields) => fields.values().flat_map(output_urls).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Local file names for `urls`: the last path segment, prefixed with its
+/// position when two URLs share a name.
+pub(crate) fn file_names(urls: &[&str]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    urls.iter()
+        .enumerate()
+        .map(|(i, url)| {
+            let name = Url::parse(url)
+                .ok()
+                .and_then(|url| url.path_segments()?.next_back().map(str::to_string))
+                .filter(|name| !name.is_empty() && name != "." && name != "..")
+                .unwrap_or_else(|| format!("output-{}", i));
+            if seen.insert(name.clone()) { name } else { format!("{}-{}", i, name) }
+        })
+        .collect()
+}
+
+/// Creates `name` in `dir`, or "stem-1.ext", "stem-2.ext" and so on if it's
+/// taken, and writes `contents` to it.
+async fn write_new(dir: &Path, name: &str, contents: &[u8]) -> Result<PathBuf> {
+    let name = Path::new(name);
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = name
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    for n in 0.. {
+        let path = match n {
+            0 => dir.join(name),
+            n => dir.join(format!("{}-{}{}", stem, n, extension)),
+        };
+        let file = tokio::fs::OpenOptions::new().write(true).create_new(true).open(&path).await;
+        match file {
+            Ok(mut file) => {
+                file.write_all(contents).await?;
+                file.flush().await?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(e) => {
+                return Err(e.into());
+            }
+        }
+    }
+    unreachable!()
+}
+
+impl Client {
+    /// Downloads every URL in `output` into `dir`, creating it if there's
+    /// anything to save. Existing files are never overwritten; a name that's
+    /// taken gets a numbered suffix instead.
+    pub async fn download_outputs(
+        &self,
+        output: &Value,
+        dir: &Path
+    ) -> Result<Vec<DownloadedFile>> {
+        let urls = output_urls(output);
+        if urls.is_empty() {
+            return Ok(Vec::new());
+        }
+        tokio::fs::create_dir_all(dir).await?;
+
+        let mut files = Vec::new();
+        for (url, name) in urls.iter().zip(file_names(&urls)) {
+            let contents = self.download(url).await?;
+            let path = write_new(dir, &name, &contents).await?;
+            files.push(DownloadedFile { url: url.to_string(), path });
+        }
+        Ok(files)
+    }
+
+    /// Fetches `url`, sending the auth token only if it's on the API's host.
+    /// An API file URL is fetched through `download_file`, since the URL
+    /// itself returns the file's metadata.
+    pub(crate) async fn download(&self, url: &str) -> Result<Bytes> {
+        let file_id = url
+            .strip_prefix(&format!("{}/files/", self.base_url))
+            .filter(|id| !id.is_empty() && !id.contains(['/', '?', '#']));
+        if let Some(file_id) = file_id {
+            return self.download_file(file_id).await;
+        }
+
+        let url = Url::parse(url)?;
+        let api_host = Url::parse(&self.base_url)?.host_str().map(str::to_string);
+        let request = if url.host_str().map(str::to_string) == api_host {
+            self.request(Method::GET, url)
+        } else {
+            HttpRequest::new(Method::GET, url)
+        };
+        self.send_request(request).await?.bytes().await
+    }
+}
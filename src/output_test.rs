@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::super::output::{ file_names, output_urls };
+
+    #[test]
+    fn test_output_urls() {
+        let output = json!({
+            "images": [
+                "https://replicate.delivery/a/out.png",
+                "https://replicate.delivery/b/out.png"
+            ],
+            "caption": "a cat",
+            "seed": 42,
+            "mask": "http://example.com/mask",
+        });
+        let urls = output_urls(&output);
+        assert_eq!(urls.len(), 3);
+        assert!(output_urls(&json!("just text")).is_empty());
+
+        let names = file_names(&[
+            "https://replicate.delivery/a/out.png",
+            "https://replicate.delivery/b/out.png",
+            "https://replicate.delivery/",
+            "https://replicate.delivery/a/..",
+        ]);
+        assert_eq!(names, ["out.png", "1-out.png", "output-2", "output-3"]);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_download_outputs_keeps_existing_files() {
+        use super::super::testing::MockServer;
+
+        let server = MockServer::start().await.unwrap();
+        let client = server.client();
+        let file = client.create_file_from_bytes(b"hello", None).await.unwrap();
+        let dir = std::env
+            ::temp_dir()
+            .join(format!("repli-output-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(&file.id), "old").unwrap();
+
+        let output = json!({ "file": file.urls["get"], "caption": "hi" });
+        let downloaded = client.download_outputs(&output, &dir).await.unwrap();
+
+        assert_eq!(downloaded.len(), 1);
+        assert_eq!(downloaded[0].url, file.urls["get"]);
+        assert_eq!(downloaded[0].path, dir.join(format!("{}-1", file.id)));
+        assert_eq!(std::fs::read(&downloaded[0].path).unwrap(), b"hello");
+        assert_eq!(std::fs::read_to_string(dir.join(&file.id)).unwrap(), "old");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
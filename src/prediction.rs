@@ -1,13 +1,16 @@
+use chrono::{ DateTime, Utc };
 use serde::{ Deserialize, Serialize };
 use std::collections::HashMap;
+use std::time::Duration;
 use anyhow::Result;
 use reqwest::Method;
-use regex::Regex;
-use anyhow::anyhow;
 use crate::status::Status;
 use crate::webhook::{ Webhook, WebhookEventType };
 use crate::client::Client;
 use crate::paginate::Page;
+use crate::progress::{ parse_progress, PredictionProgress };
+use crate::request::{ PredictionRequest, Target };
+use crate::fields::null_as_default;
 pub type PredictionInput = HashMap<String, serde_json::Value>;
 pub type PredictionOutput = serde_json::Value;
 
@@ -26,14 +29,19 @@ pub struct PredictionMetrics {
     pub output_token_count: Option<i32>,
     pub time_to_first_token: Option<f64>,
     pub tokens_per_second: Option<f64>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Prediction {
     pub id: String,
     pub status: Status,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub model: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub version: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub input: PredictionInput,
     pub output: Option<PredictionOutput>,
     #[serde(default)] // This makes the field optional during deserialization
@@ -44,47 +52,26 @@ pub struct Prediction {
     pub webhook: Option<String>,
     pub webhook_events_filter: Option<Vec<WebhookEventType>>,
     pub urls: Option<HashMap<String, String>>,
-    pub created_at: String,
-    pub started_at: Option<String>,
-    pub completed_at: Option<String>,
-}
-
-#[derive(Debug, Clone)]
-pub struct PredictionProgress {
-    pub percentage: f64,
-    pub current: i32,
-    pub total: i32,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl Prediction {
-    pub fn progress(&self) -> Option<PredictionProgress> {
-        if let Some(logs) = &self.logs {
-            if logs.is_empty() {
-                return None;
-            }
-
-            let re = Regex::new(
-                r"^\s*(?P<percentage>\d+)%\s*\|.+?\|\s*(?P<current>\d+)\/(?P<total>\d+)"
-            ).unwrap();
-            let lines: Vec<&str> = logs.lines().collect();
-
-            for line in lines.iter().rev() {
-                let line = line.trim();
-                if let Some(captures) = re.captures(line) {
-                    let percentage: f64 = captures["percentage"].parse().unwrap();
-                    let current: i32 = captures["current"].parse().unwrap();
-                    let total: i32 = captures["total"].parse().unwrap();
+    /// Time spent waiting for a worker, from creation until the prediction started.
+    pub fn queue_duration(&self) -> Option<Duration> {
+        (self.started_at? - self.created_at).to_std().ok()
+    }
 
-                    return Some(PredictionProgress {
-                        percentage: percentage / 100.0,
-                        current,
-                        total,
-                    });
-                }
-            }
-        }
+    /// Time spent running, from start until the prediction completed.
+    pub fn run_duration(&self) -> Option<Duration> {
+        (self.completed_at? - self.started_at?).to_std().ok()
+    }
 
-        None
+    pub fn progress(&self) -> Option<PredictionProgress> {
+        self.logs.as_deref().and_then(parse_progress)
     }
 }
 
@@ -92,7 +79,7 @@ impl Prediction {
 pub struct CreatePredictionParams {
     pub webhook: Option<String>,
     pub webhook_completed: Option<String>,
-    pub webhook_events_filter: Option<Vec<String>>,
+    pub webhook_events_filter: Option<Vec<WebhookEventType>>,
     pub stream: Option<bool>,
 }
 
@@ -105,63 +92,29 @@ impl Client {
         input: Option<PredictionInput>,
         params: Option<CreatePredictionParams>
     ) -> Result<Prediction> {
-        if
-            [model.is_some(), version.is_some(), deployment.is_some()]
-                .iter()
-                .filter(|&&x| x)
-                .count() != 1
-        {
-            return Err(
-                anyhow!("Exactly one of 'model', 'version', or 'deployment' must be specified.")
-            );
-        }
-
-        let mut body = serde_json::json!({
-            "input": input.unwrap_or_default(),
-        });
-
-        if let Some(version) = version {
-            body["version"] = serde_json::json!(version);
-        }
-
-        if let Some(params) = params {
-            if let Some(webhook) = params.webhook {
-                body["webhook"] = serde_json::json!(webhook);
-            }
-            if let Some(webhook_completed) = params.webhook_completed {
-                body["webhook_completed"] = serde_json::json!(webhook_completed);
-            }
-            if let Some(webhook_events_filter) = params.webhook_events_filter {
-                body["webhook_events_filter"] = serde_json::json!(webhook_events_filter);
-            }
-            if let Some(stream) = params.stream {
-                body["stream"] = serde_json::json!(stream);
-            }
-        }
-
-        let endpoint = if let Some(model) = model {
-            format!("/models/{}/predictions", model)
-        } else if let Some(deployment) = deployment {
-            format!("/deployments/{}/predictions", deployment)
-        } else {
-            "/predictions".to_string()
-        };
-
-        let prediction: Prediction = self.fetch(Method::POST, &endpoint, Some(body)).await?;
-
-        Ok(prediction)
+        let request = PredictionRequest::from_parts(model, version, deployment, input, params)?;
+        self.create_prediction_from_request(request).await
     }
 
     pub async fn list_predictions(&self) -> Result<Page<Prediction>> {
-        self.fetch(Method::GET, "/predictions", None).await
+        let page: Page<Prediction> = self.fetch(Method::GET, "/predictions", None).await?;
+        for prediction in &page.results {
+            self.observe_prediction(prediction).await;
+        }
+        Ok(page)
     }
 
     pub async fn get_prediction(&self, id: &str) -> Result<Prediction> {
-        self.fetch(Method::GET, &format!("/predictions/{}", id), None).await
+        let prediction = self.fetch(Method::GET, &format!("/predictions/{}", id), None).await?;
+        self.observe_prediction(&prediction).await;
+        Ok(prediction)
     }
 
     pub async fn cancel_prediction(&self, id: &str) -> Result<Prediction> {
-        self.fetch(Method::POST, &format!("/predictions/{}/cancel", id), None).await
+        let path = format!("/predictions/{}/cancel", id);
+        let prediction = self.fetch(Method::POST, &path, None).await?;
+        self.observe_prediction(&prediction).await;
+        Ok(prediction)
     }
 
     pub async fn create_prediction_with_model(
@@ -172,25 +125,14 @@ impl Client {
         webhook: Option<&Webhook>,
         stream: bool
     ) -> Result<Prediction> {
-        let mut data = serde_json::json!({
-            "input": input,
-        });
-
+        let target = Target::Model {
+            owner: owner.to_string(),
+            name: name.to_string(),
+        };
+        let mut request = PredictionRequest::new(target, input).with_stream(stream);
         if let Some(webhook) = webhook {
-            data["webhook"] = serde_json::json!(webhook.url);
-            if !webhook.events.is_empty() {
-                data["webhook_events_filter"] = serde_json::json!(webhook.events);
-            }
+            request = request.with_webhook(webhook);
         }
-
-        if stream {
-            data["stream"] = serde_json::json!(true);
-        }
-
-        self.fetch(
-            Method::POST,
-            &format!("/models/{}/{}/predictions", owner, name),
-            Some(data)
-        ).await
+        self.create_prediction_from_request(request).await
     }
 }
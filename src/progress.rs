@@ -0,0 +1,325 @@
+//! Progress parsed from prediction and training logs.
+//!
+//! `parse_progress` finds the latest progress line in a model's logs. It
+//! recognizes tqdm bars, including the elapsed time, ETA and rate tqdm prints
+//! after them, "step x/y" lines and "epoch x/y" lines.
+//!
+//! A `ProgressReporter` attached with `Client::with_progress_reporter` parses
+//! the logs of every prediction and training the client polls while waiting,
+//! and the `logs` events of every prediction it streams, and broadcasts a
+//! `ProgressUpdate` to its subscribers whenever one moves. Updates carry an
+//! ETA: tqdm's own, or one estimated from how fast progress has moved since
+//! the reporter first saw it.
+
+use std::collections::HashMap;
+use std::sync::{ Arc, Mutex, OnceLock };
+use std::time::{ Duration, Instant };
+use futures::Stream;
+use regex::Regex;
+use serde::{ Deserialize, Serialize };
+use tokio::sync::broadcast;
+use crate::client::Client;
+use crate::prediction::Prediction;
+use crate::training::Training;
+
+const UPDATE_BUFFER: usize = 256;
+// Ids are forgotten when they finish, but a wait or stream that ends early
+// never sees that, so idle ones expire and the total is capped.
+const TRACKED_TTL: Duration = Duration::from_secs(3600); // 1 hour
+const MAX_TRACKED: usize = 1024;
+
+/// The kind of log line progress was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressFormat {
+    /// `45%|████▌     | 450/1000 [03:12<03:55,  2.34it/s]`
+    Tqdm,
+    /// `step 12/50` or `Step 12 of 50`
+    Step,
+    /// `Epoch 3/10` or `epoch [3/10]`
+    Epoch,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PredictionProgress {
+    /// Fraction done, from 0 to 1.
+    pub percentage: f64,
+    pub current: i32,
+    pub total: i32,
+    pub format: ProgressFormat,
+    /// Time since the bar started, as tqdm reports it.
+    pub elapsed: Option<Duration>,
+    /// Time left, as tqdm reports it.
+    pub eta: Option<Duration>,
+    /// Iterations per second, as tqdm reports it.
+    pub rate: Option<f64>,
+}
+
+struct Patterns {
+    tqdm: Regex,
+    step: Regex,
+    epoch: Regex,
+}
+
+// Compiled once and shared, since logs are parsed on every poll.
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        tqdm: Regex::new(
+            concat!(
+                r"(?:^|[\s:])(?P<percentage>\d{1,3})%\s*\|[^|]*\|",
+                r"\s*(?P<current>\d+)/(?P<total>\d+)",
+                r"(?:\s*\[(?P<elapsed>[\d:]+)<(?P<eta>[\d:?]+)",
+                r"(?:,\s*(?P<rate>[\d.]+)\s*(?:(?P<per_second>[A-Za-z]+/s)|s/[A-Za-z]+))?)?"
+            )
+        ).unwrap(),
+        step: Regex::new(
+            r"(?i)\bsteps?\s*:?\s*\[?(?P<current>\d+)\s*(?:/|of)\s*(?P<total>\d+)"
+        ).unwrap(),
+        epoch: Regex::new(
+            r"(?i)\bepochs?\s*:?\s*\[?(?P<current>\d+)\s*(?:/|of)\s*(?P<total>\d+)"
+        ).unwrap(),
+    })
+}
+
+/// Finds the most recent progress line in `logs`. A line with both an epoch
+/// and a step counter reports the epoch.
+pub(crate) fn parse_progress(logs: &str) -> Option<PredictionProgress> {
+    logs.lines().rev().find_map(parse_line)
+}
+
+fn parse_line(line: &str) -> Option<PredictionProgress> {
+    // tqdm redraws its bar in place, so one log line can hold several.
+    let line = line.rsplit('\r').find(|part| !part.trim().is_empty())?;
+    let patterns = patterns();
+
+    if let Some(captures) = patterns.tqdm.captures(line) {
+        let rate = captures
+            .name("rate")
+            .and_then(|rate| rate.as_str().parse::<f64>().ok())
+            .filter(|rate| *rate > 0.0)
+            .map(|rate| if captures.name("per_second").is_some() { rate } else { 1.0 / rate });
+        return Some(PredictionProgress {
+            percentage: captures["percentage"].parse::<f64>().ok()? / 100.0,
+            current: captures["current"].parse().ok()?,
+            total: captures["total"].parse().ok()?,
+            format: ProgressFormat::Tqdm,
+            elapsed: captures.name("elapsed").and_then(|t| parse_clock(t.as_str())),
+            eta: captures.name("eta").and_then(|t| parse_clock(t.as_str())),
+            rate,
+        });
+    }
+
+    let (captures, format) = match patterns.epoch.captures(line) {
+        Some(captures) => (captures, ProgressFormat::Epoch),
+        None => (patterns.step.captures(line)?, ProgressFormat::Step),
+    };
+    let current: i32 = captures["current"].parse().ok()?;
+    let total: i32 = captures["total"].parse().ok()?;
+    if total <= 0 {
+        return None;
+    }
+    Some(PredictionProgress {
+        percentage: (current as f64) / (total as f64),
+        current,
+        total,
+        format,
+        elapsed: None,
+        eta: None,
+        rate: None,
+    })
+}
+
+/// Parses tqdm's "MM:SS" and "H:MM:SS". "?" and anything else is `None`.
+fn parse_clock(clock: &str) -> Option<Duration> {
+    let mut seconds = 0;
+    for part in clock.split(':') {
+        seconds = seconds * 60 + part.parse::<u64>().ok()?;
+    }
+    Some(Duration::from_secs(seconds))
+}
+
+/// What a `ProgressUpdate` is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressKind {
+    Prediction,
+    Training,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressUpdate {
+    pub kind: ProgressKind,
+    pub id: String,
+    pub progress: PredictionProgress,
+    /// `progress.eta` when the logs report it, otherwise an estimate from
+    /// the progress made since the first update for this id.
+    pub eta: Option<Duration>,
+}
+
+/// Turns the progress bars in the logs of predictions being waited on into a
+/// stream of `ProgressUpdate`s, with an ETA once a prediction has moved.
+/// Every subscriber gets every update.
+#[derive(Debug, Clone)]
+pub struct ProgressReporter {
+    inner: Arc<ReporterState>,
+}
+
+#[derive(Debug)]
+struct ReporterState {
+    sender: broadcast::Sender<ProgressUpdate>,
+    tracked: Mutex<HashMap<String, Tracked>>,
+}
+
+#[derive(Debug)]
+struct Tracked {
+    first_seen: Instant,
+    first_percentage: f64,
+    last: PredictionProgress,
+    updated: Instant,
+}
+
+impl Default for ProgressReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressReporter {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(UPDATE_BUFFER);
+        Self {
+            inner: Arc::new(ReporterState { sender, tracked: Mutex::new(HashMap::new()) }),
+        }
+    }
+
+    /// Updates from now on. A subscriber that falls more than 256 updates
+    /// behind skips the oldest ones.
+    pub fn subscribe(&self) -> impl Stream<Item = ProgressUpdate> {
+        futures::stream::unfold(self.inner.sender.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(update) => {
+                        return Some((update, receiver));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("Progress subscriber skipped {} updates", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        return None;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Reports the progress in `prediction`'s logs, and forgets it once it's
+    /// finished.
+    pub fn record_prediction(&self, prediction: &Prediction) {
+        self.observe(
+            ProgressKind::Prediction,
+            &prediction.id,
+            prediction.logs.as_deref(),
+            prediction.status.is_terminated()
+        );
+    }
+
+    pub fn record_training(&self, training: &Training) {
+        self.observe(
+            ProgressKind::Training,
+            &training.id,
+            training.logs.as_deref(),
+            training.status.is_terminated()
+        );
+    }
+
+    fn observe(&self, kind: ProgressKind, id: &str, logs: Option<&str>, terminated: bool) {
+        self.record_logs(kind, id, logs.unwrap_or_default());
+        if terminated {
+            self.finish(id);
+        }
+    }
+
+    /// Reports the latest progress in `logs`, which can be the whole log so
+    /// far or just the lines since the last call.
+    pub fn record_logs(&self, kind: ProgressKind, id: &str, logs: &str) {
+        if let Some(progress) = parse_progress(logs) {
+            self.record(kind, id, progress, Instant::now());
+        }
+    }
+
+    /// Forgets `id`, so the next update for it starts a new ETA estimate.
+    pub fn finish(&self, id: &str) {
+        self.inner.tracked.lock().unwrap().remove(id);
+    }
+
+    pub(crate) fn record(
+        &self,
+        kind: ProgressKind,
+        id: &str,
+        progress: PredictionProgress,
+        now: Instant
+    ) {
+        let mut tracked = self.inner.tracked.lock().unwrap();
+        let eta = match tracked.get_mut(id) {
+            Some(entry) if entry.last == progress => {
+                entry.updated = now;
+                return;
+            }
+            Some(entry) => {
+                entry.last = progress.clone();
+                entry.updated = now;
+                progress.eta.or_else(|| estimate_eta(entry, &progress, now))
+            }
+            None => {
+                tracked.retain(|_, entry| {
+                    now.saturating_duration_since(entry.updated) < TRACKED_TTL
+                });
+                if tracked.len() >= MAX_TRACKED {
+                    let oldest = tracked
+                        .iter()
+                        .min_by_key(|(_, entry)| entry.updated)
+                        .map(|(id, _)| id.clone());
+                    if let Some(oldest) = oldest {
+                        tracked.remove(&oldest);
+                    }
+                }
+                tracked.insert(id.to_string(), Tracked {
+                    first_seen: now,
+                    first_percentage: progress.percentage,
+                    last: progress.clone(),
+                    updated: now,
+                });
+                progress.eta
+            }
+        };
+        drop(tracked);
+
+        // Nobody listening isn't an error.
+        let _ = self.inner.sender.send(ProgressUpdate { kind, id: id.to_string(), progress, eta });
+    }
+}
+
+/// Extrapolates the rate of progress since `tracked` was first seen.
+fn estimate_eta(
+    tracked: &Tracked,
+    progress: &PredictionProgress,
+    now: Instant
+) -> Option<Duration> {
+    let done = progress.percentage - tracked.first_percentage;
+    if done <= 0.0 {
+        return None;
+    }
+    let elapsed = now.duration_since(tracked.first_seen).as_secs_f64();
+    let remaining = (1.0 - progress.percentage).max(0.0);
+    Duration::try_from_secs_f64((elapsed * remaining) / done).ok()
+}
+
+impl Client {
+    /// Reports the progress of every prediction and training this client
+    /// waits on or streams to `reporter`.
+    pub fn with_progress_reporter(mut self, reporter: ProgressReporter) -> Self {
+        self.progress_reporter = Some(reporter);
+        self
+    }
+}
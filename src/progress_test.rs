@@ -0,0 +1,152 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::{ Duration, Instant };
+    use futures::{ FutureExt, StreamExt };
+    use reqwest::StatusCode;
+    use serde_json::json;
+    use super::super::prediction::Prediction;
+    use super::super::progress::{
+        parse_progress,
+        ProgressFormat,
+        ProgressKind,
+        ProgressReporter,
+    };
+    use super::super::status::Status;
+    use super::super::test_support::{ self, json_response, ScriptedTransport };
+
+    fn prediction(logs: &str, status: Status) -> Prediction {
+        let mut prediction = test_support::prediction("abc", status);
+        prediction.logs = Some(logs.to_string());
+        prediction
+    }
+
+    #[test]
+    fn test_parse_tqdm() {
+        let progress = parse_progress(
+            "Loading\n 45%|████▌     | 450/1000 [03:12<03:55,  2.34it/s]\n"
+        ).unwrap();
+        assert_eq!(progress.format, ProgressFormat::Tqdm);
+        assert_eq!((progress.current, progress.total), (450, 1000));
+        assert_eq!(progress.percentage, 0.45);
+        assert_eq!(progress.elapsed, Some(Duration::from_secs(192)));
+        assert_eq!(progress.eta, Some(Duration::from_secs(235)));
+        assert_eq!(progress.rate, Some(2.34));
+
+        let progress = parse_progress(
+            concat!(
+                "Sampling:  10%|█         | 5/50 [00:01<?, ?it/s]\r",
+                "Sampling:  20%|██        | 10/50 [1:00:05<00:20,  2.00s/it]"
+            )
+        ).unwrap();
+        assert_eq!(progress.current, 10);
+        assert_eq!(progress.elapsed, Some(Duration::from_secs(3605)));
+        assert_eq!(progress.rate, Some(0.5));
+
+        let progress = parse_progress(" 0%|          | 0/50 [00:00<?, ?it/s]").unwrap();
+        assert_eq!(progress.eta, None);
+        assert_eq!(progress.rate, None);
+    }
+
+    #[test]
+    fn test_parse_steps_and_epochs() {
+        let progress = parse_progress("loss=0.3 Step 12 of 50").unwrap();
+        assert_eq!(progress.format, ProgressFormat::Step);
+        assert_eq!(progress.percentage, 0.24);
+
+        let progress = parse_progress("Epoch [3/10] step 5/100 loss=0.1\nsaving\n").unwrap();
+        assert_eq!(progress.format, ProgressFormat::Epoch);
+        assert_eq!((progress.current, progress.total), (3, 10));
+
+        assert!(parse_progress("Running prediction\nsteps: 0/0\n").is_none());
+        assert!(parse_progress("").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reporter_estimates_eta() {
+        let reporter = ProgressReporter::new();
+        let mut updates = Box::pin(reporter.subscribe());
+        let start = Instant::now();
+        let step = |current| parse_progress(&format!("step {}/100", current)).unwrap();
+
+        reporter.record(ProgressKind::Training, "t", step(10), start);
+        let first = updates.next().await.unwrap();
+        assert_eq!(first.kind, ProgressKind::Training);
+        assert_eq!(first.eta, None);
+
+        reporter.record(ProgressKind::Training, "t", step(10), start + Duration::from_secs(5));
+        assert!(updates.next().now_or_never().is_none());
+
+        reporter.record(ProgressKind::Training, "t", step(30), start + Duration::from_secs(10));
+        let update = updates.next().await.unwrap();
+        assert_eq!(update.progress.current, 30);
+        assert_eq!(update.eta.map(|eta| eta.as_secs()), Some(35));
+    }
+
+    #[tokio::test]
+    async fn test_reporter_expires_idle_ids() {
+        let reporter = ProgressReporter::new();
+        let mut updates = Box::pin(reporter.subscribe());
+        let start = Instant::now();
+        let step = parse_progress("step 1/10").unwrap();
+
+        reporter.record(ProgressKind::Prediction, "a", step.clone(), start);
+        reporter.record(ProgressKind::Prediction, "a", step.clone(), start);
+        updates.next().await.unwrap();
+        assert!(updates.next().now_or_never().is_none());
+
+        // Tracking a new id two hours later drops "a", so it's reported anew.
+        let later = start + Duration::from_secs(7200);
+        reporter.record(ProgressKind::Prediction, "b", step.clone(), later);
+        reporter.record(ProgressKind::Prediction, "a", step, later);
+        let ids: Vec<String> = [updates.next().await, updates.next().await]
+            .into_iter()
+            .map(|update| update.unwrap().id)
+            .collect();
+        assert_eq!(ids, ["b", "a"]);
+    }
+
+    /// Serves `polls` in turn for `/predictions/abc`, and a page of one
+    /// running prediction for `/predictions`.
+    fn api(polls: Vec<Prediction>) -> ScriptedTransport {
+        let polls = Mutex::new(polls);
+        ScriptedTransport::new(move |request| {
+            if request.url.path() == "/v1/predictions" {
+                let page = json!({
+                    "results": [prediction("step 5/10", Status::Processing)],
+                    "next": null,
+                });
+                json_response(StatusCode::OK, &page)
+            } else {
+                json_response(StatusCode::OK, &polls.lock().unwrap().remove(0))
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_reporter_follows_waits_only() {
+        let reporter = ProgressReporter::new();
+        let api = api(
+            vec![
+                prediction("step 4/10", Status::Processing),
+                prediction("step 4/10\nstep 10/10", Status::Succeeded)
+            ]
+        );
+        let client = api.client().with_progress_reporter(reporter.clone());
+        let mut updates = Box::pin(reporter.subscribe());
+
+        client.list_predictions().await.unwrap();
+        assert!(updates.next().now_or_never().is_none());
+
+        let mut prediction = prediction("", Status::Starting);
+        prediction.id = "abc".to_string();
+        let poll_interval = Duration::from_millis(1);
+        client.wait_with_options(&prediction, poll_interval, Duration::from_secs(5)).await.unwrap();
+        let percentages: Vec<f64> = [updates.next().await, updates.next().await]
+            .into_iter()
+            .map(|update| update.unwrap().progress.percentage)
+            .collect();
+        assert_eq!(percentages, [0.4, 1.0]);
+        assert!(updates.next().now_or_never().is_none());
+    }
+}
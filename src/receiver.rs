@@ -0,0 +1,219 @@
+//! An embeddable server for receiving Replicate webhooks.
+//!
+//! Enabled with the `webhook-receiver` feature. `WebhookReceiver::router`
+//! returns an axum `Router` that can be nested into an existing app, or
+//! `WebhookReceiver::serve` runs it standalone.
+
+use std::collections::{ HashMap, HashSet, VecDeque };
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+use anyhow::Result;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{ HeaderMap, StatusCode };
+use axum::routing::post;
+use axum::Router;
+use futures::future::BoxFuture;
+use crate::prediction::Prediction;
+use crate::training::Training;
+use crate::wait::WebhookWaiter;
+use crate::webhook::{
+    validate_webhook_with_tolerance,
+    WebhookEventType,
+    WebhookSigningSecret,
+    DEFAULT_WEBHOOK_TOLERANCE,
+};
+
+const DEFAULT_DEDUPE_CAPACITY: usize = 10_000;
+
+/// The object carried by a webhook delivery.
+#[derive(Debug, Clone)]
+pub enum WebhookPayload {
+    Prediction(Prediction),
+    Training(Training),
+}
+
+impl WebhookPayload {
+    fn parse(body: &[u8]) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_slice(body)?;
+        let is_training = value["urls"]["get"]
+            .as_str()
+            .is_some_and(|url| url.contains("/trainings/"));
+
+        if is_training {
+            Ok(WebhookPayload::Training(serde_json::from_value(value)?))
+        } else {
+            Ok(WebhookPayload::Prediction(serde_json::from_value(value)?))
+        }
+    }
+
+    /// Infers which event triggered the delivery. Replicate doesn't send the
+    /// event type, so it is derived from the status and output.
+    pub fn event_type(&self) -> WebhookEventType {
+        let (status, has_output) = match self {
+            WebhookPayload::Prediction(p) => (p.status, p.output.is_some()),
+            WebhookPayload::Training(t) => (t.status, t.output.is_some()),
+        };
+
+        if status.is_terminated() {
+            WebhookEventType::Completed
+        } else if status == crate::status::Status::Starting {
+            WebhookEventType::Start
+        } else if has_output {
+            WebhookEventType::Output
+        } else {
+            WebhookEventType::Logs
+        }
+    }
+}
+
+type Handler = Arc<dyn Fn(WebhookPayload) -> BoxFuture<'static, ()> + Send + Sync>;
+
+pub struct WebhookReceiver {
+    secret: WebhookSigningSecret,
+    tolerance: Duration,
+    handlers: HashMap<WebhookEventType, Vec<Handler>>,
+    dedupe_capacity: usize,
+}
+
+impl WebhookReceiver {
+    pub fn new(secret: WebhookSigningSecret) -> Self {
+        Self {
+            secret,
+            tolerance: DEFAULT_WEBHOOK_TOLERANCE,
+            handlers: HashMap::new(),
+            dedupe_capacity: DEFAULT_DEDUPE_CAPACITY,
+        }
+    }
+
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets how many recent `webhook-id`s are remembered for deduplication.
+    pub fn with_dedupe_capacity(mut self, capacity: usize) -> Self {
+        self.dedupe_capacity = capacity;
+        self
+    }
+
+    /// Registers `handler` for deliveries of `event_type`. Several handlers
+    /// can be registered for the same event; they run in registration order.
+    pub fn on<F, Fut>(mut self, event_type: WebhookEventType, handler: F) -> Self
+        where
+            F: Fn(WebhookPayload) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = ()> + Send + 'static
+    {
+        let handler: Handler = Arc::new(move |payload| Box::pin(handler(payload)));
+        self.handlers.entry(event_type).or_default().push(handler);
+        self
+    }
+
+    /// Completes waits registered with `waiter` from `completed` deliveries.
+    pub fn with_waiter(self, waiter: WebhookWaiter) -> Self {
+        self.on(WebhookEventType::Completed, move |payload| {
+            if let WebhookPayload::Prediction(prediction) = payload {
+                waiter.deliver(prediction);
+            }
+            async {}
+        })
+    }
+
+    /// Builds a router that accepts deliveries with `POST /`.
+    pub fn router(self) -> Router {
+        let state = Arc::new(ReceiverState {
+            seen: Mutex::new(SeenIds::new(self.dedupe_capacity)),
+            receiver: self,
+        });
+
+        Router::new().route("/", post(receive)).with_state(state)
+    }
+
+    pub async fn serve(self, addr: SocketAddr) -> Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        axum::serve(listener, self.router()).await?;
+        Ok(())
+    }
+}
+
+struct ReceiverState {
+    receiver: WebhookReceiver,
+    seen: Mutex<SeenIds>,
+}
+
+/// A bounded set of recently delivered webhook ids.
+struct SeenIds {
+    capacity: usize,
+    order: VecDeque<String>,
+    ids: HashSet<String>,
+}
+
+impl SeenIds {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::new(),
+            ids: HashSet::new(),
+        }
+    }
+
+    /// Records `id`, returning false if it was already seen.
+    fn insert(&mut self, id: &str) -> bool {
+        if self.ids.contains(id) {
+            return false;
+        }
+
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        self.order.push_back(id.to_string());
+        self.ids.insert(id.to_string());
+        true
+    }
+}
+
+async fn receive(
+    State(state): State<Arc<ReceiverState>>,
+    headers: HeaderMap,
+    body: Bytes
+) -> StatusCode {
+    let receiver = &state.receiver;
+
+    let validation = validate_webhook_with_tolerance(
+        &headers,
+        &body,
+        &receiver.secret,
+        receiver.tolerance
+    );
+    if let Err(e) = validation {
+        log::warn!("Rejected webhook: {}", e);
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let payload = match WebhookPayload::parse(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            log::warn!("Failed to parse webhook body: {}", e);
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    // Validation guarantees the header is present.
+    let id = headers["webhook-id"].to_str().unwrap_or_default();
+    if !state.seen.lock().unwrap().insert(id) {
+        log::debug!("Ignoring duplicate webhook {}", id);
+        return StatusCode::OK;
+    }
+
+    if let Some(handlers) = receiver.handlers.get(&payload.event_type()) {
+        for handler in handlers {
+            handler(payload.clone()).await;
+        }
+    }
+
+    StatusCode::OK
+}
@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod tests {
+    use super::super::receiver::{ WebhookPayload, WebhookReceiver };
+    use super::super::webhook::{ sign_webhook, WebhookEventType, WebhookSigningSecret };
+    use std::sync::{ Arc, Mutex };
+
+    const SECRET: &str = "whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw";
+
+    fn sign(id: &str, timestamp: i64, body: &str) -> String {
+        let secret = WebhookSigningSecret { key: SECRET.to_string() };
+        sign_webhook(id, &timestamp.to_string(), body.as_bytes(), &secret).unwrap()
+    }
+
+    async fn start(receiver: WebhookReceiver) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, receiver.router()).await.unwrap();
+        });
+        format!("http://{}/", addr)
+    }
+
+    async fn deliver(url: &str, id: &str, body: &str, signature: Option<String>) -> u16 {
+        let timestamp = chrono::Utc::now().timestamp();
+        reqwest::Client
+            ::new()
+            .post(url)
+            .header("webhook-id", id)
+            .header("webhook-timestamp", timestamp.to_string())
+            .header("webhook-signature", signature.unwrap_or_else(|| sign(id, timestamp, body)))
+            .body(body.to_string())
+            .send().await
+            .unwrap()
+            .status()
+            .as_u16()
+    }
+
+    #[tokio::test]
+    async fn test_dispatches_and_dedupes() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let receiver = WebhookReceiver::new(WebhookSigningSecret {
+            key: SECRET.to_string(),
+        }).on(WebhookEventType::Completed, move |payload| {
+            let sink = sink.clone();
+            async move {
+                if let WebhookPayload::Prediction(prediction) = payload {
+                    sink.lock().unwrap().push(prediction.id);
+                }
+            }
+        });
+        let url = start(receiver).await;
+
+        let body = include_str!("fixtures/prediction.json");
+        assert_eq!(deliver(&url, "msg_1", body, None).await, 200);
+        assert_eq!(deliver(&url, "msg_1", body, None).await, 200);
+
+        assert_eq!(*received.lock().unwrap(), vec!["gm3qorzdhgbfurvjtvhg6dckhu".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_routes_trainings() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let receiver = WebhookReceiver::new(WebhookSigningSecret {
+            key: SECRET.to_string(),
+        }).on(WebhookEventType::Output, move |payload| {
+            let sink = sink.clone();
+            async move {
+                sink.lock().unwrap().push(matches!(payload, WebhookPayload::Training(_)));
+            }
+        });
+        let url = start(receiver).await;
+
+        // The fixture is still processing and has no output yet.
+        let body = include_str!("fixtures/training.json");
+        assert_eq!(deliver(&url, "msg_2", body, None).await, 200);
+        assert!(received.lock().unwrap().is_empty());
+
+        let body = body.replace("\"output\": null", "\"output\": { \"weights\": \"w.tar\" }");
+        assert_eq!(deliver(&url, "msg_3", &body, None).await, 200);
+        assert_eq!(*received.lock().unwrap(), vec![true]);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_signature() {
+        let receiver = WebhookReceiver::new(WebhookSigningSecret { key: SECRET.to_string() });
+        let url = start(receiver).await;
+
+        let body = include_str!("fixtures/prediction.json");
+        let status = deliver(&url, "msg_4", body, Some("v1,bm9wZQ==".to_string())).await;
+        assert_eq!(status, 401);
+    }
+}
@@ -0,0 +1,302 @@
+//! Declarative management of deployments.
+//!
+//! A desired-state file lists deployments as `CreateDeploymentOptions`, either
+//! as a bare list or under a `deployments` key (the only form TOML allows).
+//! `Client::plan_deployments` diffs it against the account's deployments and
+//! `Client::apply_deployment_plan` carries the plan out.
+
+use std::fmt;
+use std::path::Path;
+use anyhow::{ Result, anyhow };
+use serde::{ Deserialize, Serialize };
+use crate::client::Client;
+use crate::deployment::{ CreateDeploymentOptions, Deployment, UpdateDeploymentOptions };
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum DesiredState {
+    List(Vec<CreateDeploymentOptions>),
+    Table {
+        deployments: Vec<CreateDeploymentOptions>,
+    },
+}
+
+impl From<DesiredState> for Vec<CreateDeploymentOptions> {
+    fn from(state: DesiredState) -> Self {
+        match state {
+            DesiredState::List(deployments) => deployments,
+            DesiredState::Table { deployments } => deployments,
+        }
+    }
+}
+
+/// Reads a desired-state file, picking the format from its extension.
+///
+/// JSON is always supported; YAML and TOML need the `yaml` and `toml`
+/// features.
+pub fn load_desired_deployments(path: &Path) -> Result<Vec<CreateDeploymentOptions>> {
+    let contents = std::fs::read_to_string(path)?;
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+
+    let state: DesiredState = match extension {
+        "json" => serde_json::from_str(&contents)?,
+        #[cfg(feature = "yaml")]
+        "yaml" | "yml" => serde_yaml::from_str(&contents)?,
+        #[cfg(feature = "toml")]
+        "toml" => toml::from_str(&contents)?,
+        _ => {
+            return Err(anyhow!("unsupported desired-state file: {}", path.display()));
+        }
+    };
+
+    Ok(state.into())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum DeploymentAction {
+    Create {
+        options: CreateDeploymentOptions,
+    },
+    Update {
+        owner: String,
+        name: String,
+        /// `None` if the deployment has no release yet, in which case
+        /// `changes` sets every field.
+        current: Option<CreateDeploymentOptions>,
+        changes: UpdateDeploymentOptions,
+    },
+    Delete {
+        owner: String,
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReconcilePlan {
+    pub actions: Vec<DeploymentAction>,
+}
+
+impl ReconcilePlan {
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Compares `desired` with `existing` deployments by name. Deployments
+    /// missing from `desired` are only deleted when `prune` is set. One with
+    /// no current release gets an update setting every field.
+    pub fn diff(
+        desired: &[CreateDeploymentOptions],
+        existing: &[Deployment],
+        prune: bool
+    ) -> Self {
+        let mut actions = Vec::new();
+
+        for options in desired {
+            match existing.iter().find(|d| d.name == options.name) {
+                None => actions.push(DeploymentAction::Create { options: options.clone() }),
+                Some(deployment) => {
+                    let current = deployment.current_release.as_ref().map(|release| {
+                        CreateDeploymentOptions {
+                            name: deployment.name.clone(),
+                            model: release.model.clone(),
+                            version: release.version.clone(),
+                            hardware: release.configuration.hardware.clone(),
+                            min_instances: release.configuration.min_instances,
+                            max_instances: release.configuration.max_instances,
+                        }
+                    });
+                    let changes = match &current {
+                        Some(current) => UpdateDeploymentOptions {
+                            model: changed(&current.model, &options.model),
+                            version: changed(&current.version, &options.version),
+                            hardware: changed(&current.hardware, &options.hardware),
+                            min_instances: changed(&current.min_instances, &options.min_instances),
+                            max_instances: changed(&current.max_instances, &options.max_instances),
+                        },
+                        None => {
+                            log::warn!(
+                                "Deployment {}/{} has no release; planning a full update",
+                                deployment.owner,
+                                deployment.name
+                            );
+                            UpdateDeploymentOptions {
+                                model: Some(options.model.clone()),
+                                version: Some(options.version.clone()),
+                                hardware: Some(options.hardware.clone()),
+                                min_instances: Some(options.min_instances),
+                                max_instances: Some(options.max_instances),
+                            }
+                        }
+                    };
+                    if !changes.is_empty() {
+                        actions.push(DeploymentAction::Update {
+                            owner: deployment.owner.clone(),
+                            name: deployment.name.clone(),
+                            current,
+                            changes,
+                        });
+                    }
+                }
+            }
+        }
+
+        if prune {
+            for deployment in existing {
+                if !desired.iter().any(|options| options.name == deployment.name) {
+                    actions.push(DeploymentAction::Delete {
+                        owner: deployment.owner.clone(),
+                        name: deployment.name.clone(),
+                    });
+                }
+            }
+        }
+
+        ReconcilePlan { actions }
+    }
+}
+
+fn changed<T: PartialEq + Clone>(current: &T, desired: &T) -> Option<T> {
+    if current == desired { None } else { Some(desired.clone()) }
+}
+
+fn write_change<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    field: &str,
+    current: Option<&T>,
+    desired: &Option<T>
+) -> fmt::Result {
+    match (current, desired) {
+        (Some(current), Some(desired)) => {
+            writeln!(f, "      {}: {} -> {}", field, current, desired)
+        }
+        (None, Some(desired)) => writeln!(f, "      {}: (no release) -> {}", field, desired),
+        (_, None) => Ok(()),
+    }
+}
+
+impl fmt::Display for ReconcilePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.actions.is_empty() {
+            return writeln!(f, "No changes. Deployments match the desired state.");
+        }
+
+        for action in &self.actions {
+            match action {
+                DeploymentAction::Create { options } => {
+                    writeln!(f, "  + create {}", options.name)?;
+                    writeln!(f, "      model: {}:{}", options.model, options.version)?;
+                    writeln!(f, "      hardware: {}", options.hardware)?;
+                    writeln!(
+                        f,
+                        "      instances: {}..{}",
+                        options.min_instances,
+                        options.max_instances
+                    )?;
+                }
+                DeploymentAction::Update { owner, name, current, changes } => {
+                    writeln!(f, "  ~ update {}/{}", owner, name)?;
+                    let current = current.as_ref();
+                    write_change(f, "model", current.map(|c| &c.model), &changes.model)?;
+                    write_change(f, "version", current.map(|c| &c.version), &changes.version)?;
+                    write_change(f, "hardware", current.map(|c| &c.hardware), &changes.hardware)?;
+                    write_change(
+                        f,
+                        "min_instances",
+                        current.map(|c| &c.min_instances),
+                        &changes.min_instances
+                    )?;
+                    write_change(
+                        f,
+                        "max_instances",
+                        current.map(|c| &c.max_instances),
+                        &changes.max_instances
+                    )?;
+                }
+                DeploymentAction::Delete { owner, name } => {
+                    writeln!(f, "  - delete {}/{}", owner, name)?;
+                }
+            }
+        }
+
+        let (mut create, mut update, mut delete) = (0, 0, 0);
+        for action in &self.actions {
+            match action {
+                DeploymentAction::Create { .. } => {
+                    create += 1;
+                }
+                DeploymentAction::Update { .. } => {
+                    update += 1;
+                }
+                DeploymentAction::Delete { .. } => {
+                    delete += 1;
+                }
+            }
+        }
+        writeln!(f, "Plan: {} to create, {} to update, {} to delete.", create, update, delete)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReconcileOptions {
+    /// Compute and return the plan without changing anything.
+    pub dry_run: bool,
+    /// Delete deployments that aren't in the desired state.
+    pub prune: bool,
+}
+
+impl Client {
+    pub async fn plan_deployments(
+        &self,
+        desired: &[CreateDeploymentOptions],
+        prune: bool
+    ) -> Result<ReconcilePlan> {
+        let mut existing = Vec::new();
+        let mut page = Some(self.list_deployments().await?);
+        while let Some(current) = page {
+            page = self.paginate_next(&current).await?;
+            existing.extend(current.results);
+        }
+
+        Ok(ReconcilePlan::diff(desired, &existing, prune))
+    }
+
+    /// Carries out every action in `plan`, stopping at the first failure.
+    pub async fn apply_deployment_plan(&self, plan: &ReconcilePlan) -> Result<()> {
+        for action in &plan.actions {
+            match action {
+                DeploymentAction::Create { options } => {
+                    log::info!("Creating deployment {}", options.name);
+                    self.create_deployment(options.clone()).await?;
+                }
+                DeploymentAction::Update { owner, name, changes, .. } => {
+                    log::info!("Updating deployment {}/{}", owner, name);
+                    self.update_deployment(owner, name, changes.clone()).await?;
+                }
+                DeploymentAction::Delete { owner, name } => {
+                    log::info!("Deleting deployment {}/{}", owner, name);
+                    self.delete_deployment(owner, name).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Plans the changes needed to reach `desired` and, unless this is a dry
+    /// run, applies them. Returns the plan either way.
+    pub async fn reconcile_deployments(
+        &self,
+        desired: &[CreateDeploymentOptions],
+        options: ReconcileOptions
+    ) -> Result<ReconcilePlan> {
+        let plan = self.plan_deployments(desired, options.prune).await?;
+        if !options.dry_run {
+            self.apply_deployment_plan(&plan).await?;
+        }
+        Ok(plan)
+    }
+}
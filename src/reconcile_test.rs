@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod tests {
+    use super::super::deployment::{ CreateDeploymentOptions, Deployment };
+    use super::super::hardware::Hardware;
+    use super::super::reconcile::{ load_desired_deployments, DeploymentAction, ReconcilePlan };
+    use std::path::Path;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/fixtures/desired");
+
+    fn desired() -> Vec<CreateDeploymentOptions> {
+        load_desired_deployments(&Path::new(FIXTURES).join("deployments.json")).unwrap()
+    }
+
+    fn existing(name: &str) -> Deployment {
+        let mut deployment: Deployment = serde_json
+            ::from_str(include_str!("fixtures/deployment.json"))
+            .unwrap();
+        deployment.name = name.to_string();
+        deployment
+    }
+
+    #[test]
+    fn test_plan() {
+        let existing = vec![existing("image-upscaler"), existing("old-model")];
+        let plan = ReconcilePlan::diff(&desired(), &existing, true);
+
+        assert_eq!(plan.actions.len(), 3);
+        match &plan.actions[0] {
+            DeploymentAction::Update { name, changes, .. } => {
+                assert_eq!(name, "image-upscaler");
+                assert_eq!(changes.hardware, Some(Hardware::GpuA40Small));
+                assert!(changes.version.is_some());
+                assert_eq!(changes.model, None);
+                assert_eq!(changes.min_instances, None);
+            }
+            action => panic!("unexpected action: {:?}", action),
+        }
+        match &plan.actions[1] {
+            DeploymentAction::Create { options } => assert_eq!(options.name, "text-embedder"),
+            action => panic!("unexpected action: {:?}", action),
+        }
+        match &plan.actions[2] {
+            DeploymentAction::Delete { name, .. } => assert_eq!(name, "old-model"),
+            action => panic!("unexpected action: {:?}", action),
+        }
+
+        let output = plan.to_string();
+        assert!(output.contains("~ update acme/image-upscaler"));
+        assert!(output.contains("hardware: gpu-t4 -> gpu-a40-small"));
+        assert!(output.contains("Plan: 1 to create, 1 to update, 1 to delete."));
+    }
+
+    #[test]
+    fn test_plan_without_prune_keeps_unlisted() {
+        let existing = vec![existing("old-model")];
+        let plan = ReconcilePlan::diff(&desired(), &existing, false);
+        assert!(
+            plan.actions
+                .iter()
+                .all(|action| matches!(action, DeploymentAction::Create { .. }))
+        );
+    }
+
+    #[test]
+    fn test_plan_in_sync() {
+        let desired = desired();
+        let mut deployment = existing("image-upscaler");
+        let release = deployment.current_release.as_mut().unwrap();
+        release.version = desired[0].version.clone();
+        release.configuration.hardware = Hardware::GpuA40Small;
+
+        let plan = ReconcilePlan::diff(&desired[..1], &[deployment], true);
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn test_plan_updates_deployment_without_release() {
+        let desired = desired();
+        let mut deployment = existing("image-upscaler");
+        deployment.current_release = None;
+
+        let plan = ReconcilePlan::diff(&desired[..1], &[deployment], true);
+        assert_eq!(plan.actions.len(), 1);
+        match &plan.actions[0] {
+            DeploymentAction::Update { current, changes, .. } => {
+                assert!(current.is_none());
+                assert_eq!(changes.model.as_ref(), Some(&desired[0].model));
+                assert_eq!(changes.version.as_ref(), Some(&desired[0].version));
+                assert_eq!(changes.hardware, Some(Hardware::GpuA40Small));
+                assert_eq!(changes.min_instances, Some(desired[0].min_instances));
+                assert_eq!(changes.max_instances, Some(desired[0].max_instances));
+            }
+            action => panic!("unexpected action: {:?}", action),
+        }
+        assert!(plan.to_string().contains("hardware: (no release) -> gpu-a40-small"));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_load_yaml() {
+        let desired = load_desired_deployments(&Path::new(FIXTURES).join("deployments.yaml"));
+        assert_eq!(desired.unwrap()[0].hardware, Hardware::GpuA40Small);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_load_toml() {
+        let desired = load_desired_deployments(&Path::new(FIXTURES).join("deployments.toml"));
+        assert_eq!(desired.unwrap()[0].name, "image-upscaler");
+    }
+}
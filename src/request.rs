@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+use anyhow::{ Result, anyhow };
+use reqwest::Method;
+use serde_json::{ json, Value };
+use crate::client::Client;
+use crate::identifier::{ Identifier, InvalidIdentifierError };
+use crate::prediction::{ CreatePredictionParams, Prediction, PredictionInput };
+use crate::webhook::{ Webhook, WebhookEventType };
+
+pub(crate) const DEPLOYMENT_PREFIX: &str = "deployments/";
+
+/// What a prediction runs against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// A specific model version, by id.
+    Version(String),
+    /// The latest version of an official model.
+    Model {
+        owner: String,
+        name: String,
+    },
+    Deployment {
+        owner: String,
+        name: String,
+    },
+}
+
+impl Target {
+    /// Parses "owner/name", "owner/name:version" or "deployments/owner/name".
+    pub fn parse(identifier: &str) -> Result<Self, InvalidIdentifierError> {
+        if let Some(deployment) = identifier.strip_prefix(DEPLOYMENT_PREFIX) {
+            let id = Identifier::parse(deployment)?;
+            if id.version.is_some() {
+                return Err(InvalidIdentifierError);
+            }
+            return Ok(Target::Deployment { owner: id.owner, name: id.name });
+        }
+
+        let id = Identifier::parse(identifier)?;
+        Ok(match id.version {
+            Some(version) => Target::Version(version),
+            None => Target::Model { owner: id.owner, name: id.name },
+        })
+    }
+
+    pub fn path(&self) -> String {
+        match self {
+            Target::Version(_) => "/predictions".to_string(),
+            Target::Model { owner, name } => format!("/models/{}/{}/predictions", owner, name),
+            Target::Deployment { owner, name } => {
+                format!("/deployments/{}/{}/predictions", owner, name)
+            }
+        }
+    }
+}
+
+/// A prediction to create. Every `create_prediction*` method and `run`/`stream`
+/// build one of these, so they all send the same request body.
+#[derive(Debug, Clone)]
+pub struct PredictionRequest {
+    pub target: Target,
+    pub input: PredictionInput,
+    pub webhook: Option<String>,
+    pub webhook_completed: Option<String>,
+    pub webhook_events_filter: Option<Vec<WebhookEventType>>,
+    pub stream: bool,
+    /// Recorded with the prediction in the client's journal, if it has one.
+    /// Not sent to the API.
+    pub tags: BTreeMap<String, String>,
+}
+
+impl PredictionRequest {
+    pub fn new(target: Target, input: PredictionInput) -> Self {
+        Self {
+            target,
+            input,
+            webhook: None,
+            webhook_completed: None,
+            webhook_events_filter: None,
+            stream: false,
+            tags: BTreeMap::new(),
+        }
+    }
+
+    /// Sends deliveries to `webhook.url`, filtered to `webhook.events` unless
+    /// that is empty.
+    pub fn with_webhook(mut self, webhook: &Webhook) -> Self {
+        self.webhook = Some(webhook.url.clone());
+        if !webhook.events.is_empty() {
+            self.webhook_events_filter = Some(webhook.events.clone());
+        }
+        self
+    }
+
+    pub fn with_webhook_completed(mut self, url: String) -> Self {
+        self.webhook_completed = Some(url);
+        self
+    }
+
+    pub fn with_stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    pub fn with_tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn with_params(mut self, params: CreatePredictionParams) -> Self {
+        if params.webhook.is_some() {
+            self.webhook = params.webhook;
+        }
+        if params.webhook_completed.is_some() {
+            self.webhook_completed = params.webhook_completed;
+        }
+        if params.webhook_events_filter.is_some() {
+            self.webhook_events_filter = params.webhook_events_filter;
+        }
+        if let Some(stream) = params.stream {
+            self.stream = stream;
+        }
+        self
+    }
+
+    /// Builds a request from the arguments of `Client::create_prediction`.
+    /// `model` and `deployment` are given as "owner/name".
+    pub(crate) fn from_parts(
+        model: Option<&str>,
+        version: Option<&str>,
+        deployment: Option<&str>,
+        input: Option<PredictionInput>,
+        params: Option<CreatePredictionParams>
+    ) -> Result<Self> {
+        let target = match (model, version, deployment) {
+            (Some(model), None, None) => {
+                let id = Identifier::parse(model)?;
+                if id.version.is_some() {
+                    return Err(InvalidIdentifierError.into());
+                }
+                Target::Model { owner: id.owner, name: id.name }
+            }
+            (None, Some(version), None) => Target::Version(version.to_string()),
+            (None, None, Some(deployment)) => {
+                let id = Identifier::parse(deployment)?;
+                if id.version.is_some() {
+                    return Err(InvalidIdentifierError.into());
+                }
+                Target::Deployment { owner: id.owner, name: id.name }
+            }
+            _ => {
+                return Err(
+                    anyhow!("Exactly one of 'model', 'version', or 'deployment' must be specified.")
+                );
+            }
+        };
+
+        let request = PredictionRequest::new(target, input.unwrap_or_default());
+        Ok(match params {
+            Some(params) => request.with_params(params),
+            None => request,
+        })
+    }
+
+    pub fn body(&self) -> Value {
+        let mut body = json!({
+            "input": self.input,
+        });
+
+        if let Target::Version(version) = &self.target {
+            body["version"] = json!(version);
+        }
+        if let Some(webhook) = &self.webhook {
+            body["webhook"] = json!(webhook);
+        }
+        if let Some(webhook_completed) = &self.webhook_completed {
+            body["webhook_completed"] = json!(webhook_completed);
+        }
+        if let Some(webhook_events_filter) = &self.webhook_events_filter {
+            body["webhook_events_filter"] = json!(webhook_events_filter);
+        }
+        if self.stream {
+            body["stream"] = json!(true);
+        }
+
+        body
+    }
+}
+
+impl Client {
+    pub async fn create_prediction_from_request(
+        &self,
+        request: PredictionRequest
+    ) -> Result<Prediction> {
+        let prediction = self.fetch(
+            Method::POST,
+            &request.target.path(),
+            Some(request.body())
+        ).await?;
+        self.journal_created(&prediction, request.tags).await;
+        self.observe_prediction(&prediction).await;
+        Ok(prediction)
+    }
+}
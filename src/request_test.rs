@@ -0,0 +1,195 @@
+#[cfg(test)]
+mod tests {
+    use reqwest::Method;
+    use super::super::prediction::{ CreatePredictionParams, PredictionInput };
+    use super::super::request::{ PredictionRequest, Target };
+    use super::super::test_support::ScriptedTransport;
+    use super::super::webhook::{ Webhook, WebhookEventType };
+    use serde_json::{ json, Value };
+
+    fn input() -> PredictionInput {
+        serde_json::from_value(json!({ "prompt": "a cat" })).unwrap()
+    }
+
+    fn webhook() -> Webhook {
+        Webhook {
+            url: "https://example.com/hook".to_string(),
+            events: vec![WebhookEventType::Completed],
+        }
+    }
+
+    #[test]
+    fn test_parse_targets() {
+        let version = Target::parse("owner/name:abc123").unwrap();
+        assert_eq!(version, Target::Version("abc123".to_string()));
+        assert_eq!(Target::parse("owner/name").unwrap(), Target::Model {
+            owner: "owner".to_string(),
+            name: "name".to_string(),
+        });
+        assert_eq!(Target::parse("deployments/owner/name").unwrap(), Target::Deployment {
+            owner: "owner".to_string(),
+            name: "name".to_string(),
+        });
+        assert!(Target::parse("deployments/owner/name:abc123").is_err());
+        assert!(Target::parse("deployments/owner").is_err());
+    }
+
+    #[test]
+    fn test_version_body() {
+        let request = PredictionRequest::new(Target::Version("abc123".to_string()), input())
+            .with_webhook(&webhook())
+            .with_stream(true);
+
+        assert_eq!(request.target.path(), "/predictions");
+        assert_eq!(
+            request.body(),
+            json!({
+                "input": { "prompt": "a cat" },
+                "version": "abc123",
+                "webhook": "https://example.com/hook",
+                "webhook_events_filter": ["completed"],
+                "stream": true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_model_body() {
+        let target = Target::Model { owner: "meta".to_string(), name: "llama".to_string() };
+        let request = PredictionRequest::new(target, input());
+
+        assert_eq!(request.target.path(), "/models/meta/llama/predictions");
+        assert_eq!(request.body(), json!({ "input": { "prompt": "a cat" } }));
+    }
+
+    #[test]
+    fn test_deployment_body_with_empty_events() {
+        let target = Target::Deployment { owner: "acme".to_string(), name: "upscaler".to_string() };
+        let webhook = Webhook { events: vec![], ..webhook() };
+        let request = PredictionRequest::new(target, input())
+            .with_webhook(&webhook)
+            .with_webhook_completed("https://example.com/done".to_string());
+
+        assert_eq!(request.target.path(), "/deployments/acme/upscaler/predictions");
+        assert_eq!(
+            request.body(),
+            json!({
+                "input": { "prompt": "a cat" },
+                "webhook": "https://example.com/hook",
+                "webhook_completed": "https://example.com/done",
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_create_prediction_arguments() {
+        let params = CreatePredictionParams {
+            webhook: Some("https://example.com/hook".to_string()),
+            webhook_completed: None,
+            webhook_events_filter: Some(vec![WebhookEventType::Start]),
+            stream: Some(false),
+        };
+        let request = PredictionRequest::from_parts(
+            None,
+            None,
+            Some("acme/upscaler"),
+            Some(input()),
+            Some(params)
+        ).unwrap();
+
+        assert_eq!(request.target.path(), "/deployments/acme/upscaler/predictions");
+        assert_eq!(
+            request.body(),
+            json!({
+                "input": { "prompt": "a cat" },
+                "webhook": "https://example.com/hook",
+                "webhook_events_filter": ["start"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_create_prediction_arguments_requires_one_target() {
+        assert!(PredictionRequest::from_parts(None, None, None, None, None).is_err());
+        assert!(
+            PredictionRequest::from_parts(Some("a/b"), Some("abc123"), None, None, None).is_err()
+        );
+        assert!(PredictionRequest::from_parts(Some("single"), None, None, None, None).is_err());
+    }
+
+    /// The path and JSON body of the one request `transport` saw.
+    fn sent(transport: &ScriptedTransport) -> (String, Value) {
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, Method::POST);
+        let body = serde_json::from_slice(requests[0].body.as_ref().unwrap()).unwrap();
+        (requests[0].url.path().to_string(), body)
+    }
+
+    #[tokio::test]
+    async fn test_create_prediction_sends_each_target() {
+        let cases = [
+            (Some("meta/llama"), None, None, "/v1/models/meta/llama/predictions"),
+            (None, Some("abc123"), None, "/v1/predictions"),
+            (None, None, Some("acme/upscaler"), "/v1/deployments/acme/upscaler/predictions"),
+        ];
+
+        for (model, version, deployment, path) in cases {
+            let transport = ScriptedTransport::ok(include_str!("fixtures/prediction.json"));
+            let params = CreatePredictionParams {
+                webhook: Some("https://example.com/hook".to_string()),
+                webhook_completed: None,
+                webhook_events_filter: Some(vec![WebhookEventType::Completed]),
+                stream: Some(true),
+            };
+            transport
+                .client()
+                .create_prediction(model, version, deployment, Some(input()), Some(params)).await
+                .unwrap();
+
+            let mut expected = json!({
+                "input": { "prompt": "a cat" },
+                "webhook": "https://example.com/hook",
+                "webhook_events_filter": ["completed"],
+                "stream": true,
+            });
+            if let Some(version) = version {
+                expected["version"] = json!(version);
+            }
+            assert_eq!(sent(&transport), (path.to_string(), expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_prediction_with_model_sends_model_path() {
+        let transport = ScriptedTransport::ok(include_str!("fixtures/prediction.json"));
+        transport
+            .client()
+            .create_prediction_with_model("meta", "llama", input(), Some(&webhook()), false).await
+            .unwrap();
+
+        let (path, body) = sent(&transport);
+        assert_eq!(path, "/v1/models/meta/llama/predictions");
+        assert_eq!(
+            body,
+            json!({
+                "input": { "prompt": "a cat" },
+                "webhook": "https://example.com/hook",
+                "webhook_events_filter": ["completed"],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_prediction_with_deployment_sends_deployment_path() {
+        let transport = ScriptedTransport::ok(include_str!("fixtures/prediction.json"));
+        transport
+            .client()
+            .create_prediction_with_deployment("acme", "upscaler", input(), None, true).await
+            .unwrap();
+
+        let (path, body) = sent(&transport);
+        assert_eq!(path, "/v1/deployments/acme/upscaler/predictions");
+        assert_eq!(body, json!({ "input": { "prompt": "a cat" }, "stream": true }));
+    }
+}
@@ -0,0 +1,238 @@
+use std::time::{ Duration, Instant };
+use anyhow::{ Result, anyhow };
+use thiserror::Error;
+use tokio::time::sleep;
+use crate::client::Client;
+use crate::deployment::{ Deployment, DeploymentRelease, UpdateDeploymentOptions };
+use crate::prediction::{ Prediction, PredictionInput };
+use crate::status::Status;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(900); // 15 minutes
+const DEFAULT_RELEASE_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone)]
+pub struct RolloutOptions {
+    /// Input for a prediction run against the deployment once the new release
+    /// is live. The rollout fails unless that prediction succeeds.
+    pub smoke_input: Option<PredictionInput>,
+    pub poll_interval: Duration,
+    /// Limit for the smoke prediction to finish.
+    pub timeout: Duration,
+    /// Limit for the deployment to report the new release.
+    pub release_timeout: Duration,
+}
+
+impl Default for RolloutOptions {
+    fn default() -> Self {
+        Self {
+            smoke_input: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+            release_timeout: DEFAULT_RELEASE_TIMEOUT,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RolloutReport {
+    pub previous_release: Option<DeploymentRelease>,
+    pub new_release: Option<DeploymentRelease>,
+    pub smoke_prediction: Option<Prediction>,
+    /// Set when the rollout failed and the previous release was restored.
+    pub rolled_back_to: Option<DeploymentRelease>,
+}
+
+#[derive(Debug, Error)]
+#[error(
+    "rollout of deployment {deployment} failed: {reason}{}",
+    rollback_error.as_ref().map(|e| format!(" (rollback also failed: {:#})", e)).unwrap_or_default()
+)]
+pub struct RolloutError {
+    pub deployment: String,
+    pub reason: String,
+    pub report: RolloutReport,
+    /// Why restoring the previous release failed, if it did. The deployment
+    /// is then left on the new release.
+    pub rollback_error: Option<anyhow::Error>,
+}
+
+impl Client {
+    /// Updates a deployment, waits for the new release to go live and runs an
+    /// optional smoke prediction. If either step fails, the deployment is
+    /// rolled back to the model, version and configuration it had before and a
+    /// `RolloutError` is returned.
+    pub async fn rollout_deployment(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str,
+        update: UpdateDeploymentOptions,
+        options: RolloutOptions
+    ) -> Result<RolloutReport> {
+        let before = self.get_deployment(deployment_owner, deployment_name).await?;
+        let mut report = RolloutReport {
+            previous_release: before.current_release,
+            new_release: None,
+            smoke_prediction: None,
+            rolled_back_to: None,
+        };
+
+        let updated = self.update_deployment(deployment_owner, deployment_name, update).await?;
+        let target = updated.current_release
+            .as_ref()
+            .map(|release| release.number)
+            .ok_or_else(|| anyhow!("updated deployment has no current release"))?;
+        report.new_release = updated.current_release;
+
+        let live = self.wait_for_release(deployment_owner, deployment_name, target, &options).await;
+        let failure = match live {
+            Ok(deployment) => {
+                report.new_release = deployment.current_release;
+                None
+            }
+            Err(e) => Some(e.to_string()),
+        };
+
+        let failure = match (failure, &options.smoke_input) {
+            (Some(reason), _) => Some(reason),
+            (None, Some(input)) => {
+                let result = self.smoke_test(
+                    deployment_owner,
+                    deployment_name,
+                    input.clone(),
+                    &options
+                ).await;
+                match result {
+                    Ok(prediction) if prediction.status == Status::Succeeded => {
+                        report.smoke_prediction = Some(prediction);
+                        None
+                    }
+                    Ok(prediction) => {
+                        let reason = format!(
+                            "smoke prediction {} ended with status {}",
+                            prediction.id,
+                            prediction.status
+                        );
+                        report.smoke_prediction = Some(prediction);
+                        Some(reason)
+                    }
+                    Err(e) => Some(format!("smoke prediction failed: {}", e)),
+                }
+            }
+            (None, None) => None,
+        };
+
+        let Some(reason) = failure else {
+            return Ok(report);
+        };
+
+        log::warn!(
+            "Rollout of {}/{} failed, rolling back: {}",
+            deployment_owner,
+            deployment_name,
+            reason
+        );
+        let mut rollback_error = None;
+        if let Some(previous) = &report.previous_release {
+            let restored = self.update_deployment(
+                deployment_owner,
+                deployment_name,
+                UpdateDeploymentOptions::from(previous)
+            ).await;
+            match restored {
+                Ok(restored) => {
+                    report.rolled_back_to = restored.current_release;
+                }
+                Err(e) => {
+                    rollback_error = Some(e);
+                }
+            }
+        }
+
+        Err(
+            (RolloutError {
+                deployment: format!("{}/{}", deployment_owner, deployment_name),
+                reason,
+                report,
+                rollback_error,
+            }).into()
+        )
+    }
+
+    /// Restores the release before the deployment's current one, as recorded
+    /// in `deployment_releases`.
+    pub async fn rollback_deployment(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str
+    ) -> Result<Deployment> {
+        let current = self.get_deployment(deployment_owner, deployment_name).await?;
+        let current_number = current.current_release
+            .as_ref()
+            .map(|release| release.number)
+            .ok_or_else(|| anyhow!("deployment has no current release"))?;
+
+        let previous = self
+            .deployment_releases(deployment_owner, deployment_name)
+            .into_iter()
+            .rev()
+            .find(|release| release.number < current_number)
+            .ok_or_else(|| {
+                anyhow!(
+                    "no earlier release of {}/{} has been observed by this client",
+                    deployment_owner,
+                    deployment_name
+                )
+            })?;
+
+        self.update_deployment(
+            deployment_owner,
+            deployment_name,
+            UpdateDeploymentOptions::from(&previous)
+        ).await
+    }
+
+    /// Polls the deployment until it reports release `release_number` or a
+    /// later one.
+    async fn wait_for_release(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str,
+        release_number: i32,
+        options: &RolloutOptions
+    ) -> Result<Deployment> {
+        let start = Instant::now();
+
+        loop {
+            let deployment = self.get_deployment(deployment_owner, deployment_name).await?;
+            let live = deployment.current_release
+                .as_ref()
+                .is_some_and(|release| release.number >= release_number);
+            if live {
+                return Ok(deployment);
+            }
+
+            if start.elapsed() > options.release_timeout {
+                return Err(anyhow!("release {} did not go live in time", release_number));
+            }
+            sleep(options.poll_interval).await;
+        }
+    }
+
+    async fn smoke_test(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str,
+        input: PredictionInput,
+        options: &RolloutOptions
+    ) -> Result<Prediction> {
+        let prediction = self.create_prediction_with_deployment(
+            deployment_owner,
+            deployment_name,
+            input,
+            None,
+            false
+        ).await?;
+        self.wait_with_options(&prediction, options.poll_interval, options.timeout).await
+    }
+}
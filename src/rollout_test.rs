@@ -0,0 +1,254 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::{ Arc, Mutex };
+    use std::time::Duration;
+    use anyhow::Result;
+    use reqwest::{ Method, StatusCode };
+    use serde_json::json;
+    use super::super::client::Client;
+    use super::super::deployment::{ Deployment, UpdateDeploymentOptions };
+    use super::super::prediction::PredictionInput;
+    use super::super::rollout::{ RolloutError, RolloutOptions };
+    use super::super::status::Status;
+    use super::super::test_support::{
+        json_response,
+        prediction,
+        ScriptedTransport,
+        PREDICTION_ID,
+    };
+    use super::super::transport::{ HttpRequest, HttpResponse };
+
+    const DEPLOYMENT: &str = "/v1/deployments/acme/image-upscaler";
+
+    /// Serves one deployment. Updates bump its release number until
+    /// `updates_left` runs out, and smoke predictions end with `smoke_status`.
+    /// After an update, the next `lag` reads still show the old release.
+    #[derive(Clone)]
+    struct Api {
+        deployment: Arc<Mutex<Deployment>>,
+        smoke_status: Status,
+        updates_left: Arc<Mutex<usize>>,
+        lag: usize,
+        stale: Arc<Mutex<Option<(Deployment, usize)>>>,
+    }
+
+    impl Api {
+        fn new(smoke_status: Status, updates_left: usize) -> Self {
+            let deployment = serde_json
+                ::from_str(include_str!("fixtures/deployment.json"))
+                .unwrap();
+            Self {
+                deployment: Arc::new(Mutex::new(deployment)),
+                smoke_status,
+                updates_left: Arc::new(Mutex::new(updates_left)),
+                lag: 0,
+                stale: Arc::default(),
+            }
+        }
+
+        fn with_lag(mut self, lag: usize) -> Self {
+            self.lag = lag;
+            self
+        }
+
+        fn version(&self) -> String {
+            let deployment = self.deployment.lock().unwrap();
+            deployment.current_release.as_ref().unwrap().version.clone()
+        }
+
+        fn handle(&self, request: &HttpRequest) -> Result<HttpResponse> {
+            let (status, body) = self.route(request);
+            json_response(status, &body)
+        }
+
+        fn route(&self, request: &HttpRequest) -> (StatusCode, serde_json::Value) {
+            let path = request.url.path();
+            match (&request.method, path) {
+                (&Method::GET, DEPLOYMENT) => {
+                    let mut stale = self.stale.lock().unwrap();
+                    if let Some((deployment, reads_left)) = stale.as_mut() {
+                        if *reads_left > 0 {
+                            *reads_left -= 1;
+                            return (StatusCode::OK, json!(deployment));
+                        }
+                    }
+                    *stale = None;
+                    (StatusCode::OK, json!(*self.deployment.lock().unwrap()))
+                }
+                (&Method::PATCH, DEPLOYMENT) => {
+                    let mut updates_left = self.updates_left.lock().unwrap();
+                    if *updates_left == 0 {
+                        return (StatusCode::BAD_REQUEST, json!({ "detail": "update refused" }));
+                    }
+                    *updates_left -= 1;
+
+                    let update: UpdateDeploymentOptions = serde_json
+                        ::from_slice(request.body.as_ref().unwrap())
+                        .unwrap();
+                    let mut deployment = self.deployment.lock().unwrap();
+                    *self.stale.lock().unwrap() = Some((deployment.clone(), self.lag));
+                    let release = deployment.current_release.as_mut().unwrap();
+                    release.number += 1;
+                    if let Some(version) = update.version {
+                        release.version = version;
+                    }
+                    if let Some(hardware) = update.hardware {
+                        release.configuration.hardware = hardware;
+                    }
+                    (StatusCode::OK, json!(*deployment))
+                }
+                (&Method::POST, _) if path == format!("{}/predictions", DEPLOYMENT) => {
+                    (StatusCode::CREATED, json!(prediction(PREDICTION_ID, Status::Starting)))
+                }
+                (&Method::GET, _) if path.starts_with("/v1/predictions/") => {
+                    (StatusCode::OK, json!(prediction(PREDICTION_ID, self.smoke_status)))
+                }
+                (&Method::GET, "/v1/hardware") => {
+                    (StatusCode::OK, json!([{ "name": "Nvidia T4 GPU", "sku": "gpu-t4" }]))
+                }
+                _ => (StatusCode::NOT_FOUND, json!({ "detail": "not found" })),
+            }
+        }
+    }
+
+    fn transport(api: &Api) -> ScriptedTransport {
+        let api = api.clone();
+        ScriptedTransport::new(move |request| api.handle(request))
+    }
+
+    fn client(api: &Api) -> Client {
+        transport(api).client()
+    }
+
+    fn options() -> RolloutOptions {
+        RolloutOptions {
+            smoke_input: Some(PredictionInput::from([("image".to_string(), json!("a.png"))])),
+            poll_interval: Duration::from_millis(1),
+            timeout: Duration::from_secs(5),
+            release_timeout: Duration::from_secs(5),
+        }
+    }
+
+    fn update(version: &str) -> UpdateDeploymentOptions {
+        UpdateDeploymentOptions {
+            version: Some(version.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rollout_succeeds() {
+        let api = Api::new(Status::Succeeded, 1);
+        let client = client(&api);
+
+        let report = client
+            .rollout_deployment("acme", "image-upscaler", update("v2"), options()).await
+            .unwrap();
+        assert_eq!(report.previous_release.unwrap().number, 1);
+        assert_eq!(report.new_release.unwrap().version, "v2");
+        assert_eq!(report.smoke_prediction.unwrap().status, Status::Succeeded);
+        assert!(report.rolled_back_to.is_none());
+
+        let numbers: Vec<i32> = client
+            .deployment_releases("acme", "image-upscaler")
+            .iter()
+            .map(|release| release.number)
+            .collect();
+        assert_eq!(numbers, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_rollout_waits_for_release() {
+        let api = Api::new(Status::Succeeded, 1).with_lag(2);
+        let transport = transport(&api);
+
+        let report = transport
+            .client()
+            .rollout_deployment("acme", "image-upscaler", update("v2"), options()).await
+            .unwrap();
+        assert_eq!(report.new_release.unwrap().number, 2);
+
+        // The smoke prediction is only created after a read shows release 2.
+        let requests = transport.requests();
+        let smoke = requests
+            .iter()
+            .position(|request| request.method == Method::POST)
+            .unwrap();
+        let reads = requests[..smoke]
+            .iter()
+            .filter(|request| request.method == Method::GET && request.url.path() == DEPLOYMENT)
+            .count();
+        assert_eq!(reads, 4);
+    }
+
+    #[tokio::test]
+    async fn test_rollout_rolls_back_release_that_never_goes_live() {
+        let api = Api::new(Status::Succeeded, 2).with_lag(usize::MAX);
+        let original = api.version();
+        let options = RolloutOptions {
+            release_timeout: Duration::from_millis(20),
+            ..options()
+        };
+
+        let error = client(&api)
+            .rollout_deployment("acme", "image-upscaler", update("v2"), options).await
+            .unwrap_err();
+        let error = error.downcast::<RolloutError>().unwrap();
+        assert!(error.reason.contains("release 2 did not go live in time"));
+        assert!(error.report.smoke_prediction.is_none());
+        assert_eq!(api.version(), original);
+    }
+
+    #[tokio::test]
+    async fn test_rollout_rolls_back_failed_smoke_prediction() {
+        let api = Api::new(Status::Failed, 2);
+        let original = api.version();
+
+        let error = client(&api)
+            .rollout_deployment("acme", "image-upscaler", update("v2"), options()).await
+            .unwrap_err();
+        let error = error.downcast::<RolloutError>().unwrap();
+        assert!(error.reason.contains("ended with status failed"));
+        assert!(error.rollback_error.is_none());
+
+        let restored = error.report.rolled_back_to.unwrap();
+        assert_eq!((restored.number, restored.version.as_str()), (3, original.as_str()));
+        assert_eq!(api.version(), original);
+    }
+
+    #[tokio::test]
+    async fn test_rollout_reports_failed_rollback() {
+        let api = Api::new(Status::Failed, 1);
+
+        let error = client(&api)
+            .rollout_deployment("acme", "image-upscaler", update("v2"), options()).await
+            .unwrap_err();
+        let error = error.downcast::<RolloutError>().unwrap();
+        assert!(error.reason.contains("ended with status failed"));
+        let rollback_error = error.rollback_error.as_ref().unwrap();
+        assert!(rollback_error.to_string().contains("update refused"));
+        assert!(error.report.rolled_back_to.is_none());
+        assert_eq!(error.report.new_release.as_ref().unwrap().version, "v2");
+        assert!(error.to_string().contains("rollback also failed"));
+        assert_eq!(api.version(), "v2");
+    }
+
+    #[tokio::test]
+    async fn test_rollback_restores_observed_release() {
+        let api = Api::new(Status::Succeeded, 2);
+        let original = api.version();
+
+        let fresh = client(&api);
+        fresh.get_deployment("acme", "image-upscaler").await.unwrap();
+        assert!(fresh.rollback_deployment("acme", "image-upscaler").await.is_err());
+
+        let client = client(&api);
+        client.get_deployment("acme", "image-upscaler").await.unwrap();
+        client.update_deployment("acme", "image-upscaler", update("v2")).await.unwrap();
+        let restored = client.rollback_deployment("acme", "image-upscaler").await.unwrap();
+
+        let release = restored.current_release.unwrap();
+        assert_eq!((release.number, release.version), (3, original));
+        assert_eq!(client.deployment_releases("acme", "image-upscaler").len(), 3);
+    }
+}
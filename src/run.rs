@@ -1,10 +1,11 @@
 use anyhow::{ Result, anyhow, Context };
 use crate::client::Client;
-use crate::prediction::{ PredictionInput, PredictionOutput, CreatePredictionParams };
+use crate::prediction::{ PredictionInput, PredictionOutput };
+use crate::request::{ PredictionRequest, Target };
 use crate::webhook::Webhook;
-use crate::identifier::Identifier;
-use tokio::time::{ sleep, Duration };
+use tokio::time::Duration;
 use crate::Status;
+
 impl Client {
     pub async fn run(
         &self,
@@ -12,46 +13,62 @@ impl Client {
         input: PredictionInput,
         webhook: Option<&Webhook>
     ) -> Result<PredictionOutput> {
-        let id = Identifier::parse(identifier)?;
+        let target = Target::parse(identifier)?;
+        self.run_request(PredictionRequest::new(target, input), webhook).await
+    }
 
-        let params = CreatePredictionParams {
-            webhook: webhook.map(|w| w.url.clone()),
-            webhook_events_filter: webhook.map(|w| w.events.clone()),
-            stream: Some(false),
-            webhook_completed: None,
+    /// Runs a prediction on a deployment and waits for its output.
+    pub async fn run_deployment(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str,
+        input: PredictionInput,
+        webhook: Option<&Webhook>
+    ) -> Result<PredictionOutput> {
+        let target = Target::Deployment {
+            owner: deployment_owner.to_string(),
+            name: deployment_name.to_string(),
         };
+        self.run_request(PredictionRequest::new(target, input), webhook).await
+    }
 
-        let mut prediction = match id.version {
-            Some(version) => {
-                self
-                    .create_prediction(None, Some(&version), None, Some(input), Some(params)).await
-                    .context("Failed to create prediction with version")?
-            }
-            None => {
-                self
-                    .create_prediction(
-                        Some(&format!("{}/{}", id.owner, id.name)),
-                        None,
-                        None,
-                        Some(input),
-                        Some(params)
-                    ).await
-                    .context("Failed to create prediction with model")?
-            }
-        };
+    async fn run_request(
+        &self,
+        mut request: PredictionRequest,
+        webhook: Option<&Webhook>
+    ) -> Result<PredictionOutput> {
+        let waiter_webhook = self.webhook_waiter.as_ref().map(|waiter| waiter.webhook());
+        let webhook = webhook.or(waiter_webhook.as_ref());
+        if let Some(webhook) = webhook {
+            request = request.with_webhook(webhook);
+        }
 
-        let timeout = Duration::from_secs(600); // 10 minutes timeout
-        let start_time = std::time::Instant::now();
+        if let Some(output) = self.cached_output(&request).await {
+            return Ok(output);
+        }
+        let cacheable = self.cache.is_some().then(|| request.clone());
 
-        while prediction.status == Status::Starting || prediction.status == Status::Processing {
-            if start_time.elapsed() > timeout {
-                return Err(anyhow!("Prediction timed out after {:?}", timeout));
-            }
-            sleep(Duration::from_secs(5)).await;
-            prediction = self.get_prediction(&prediction.id).await?;
+        let context = match request.target {
+            Target::Version(_) => "Failed to create prediction with version",
+            Target::Model { .. } => "Failed to create prediction with model",
+            Target::Deployment { .. } => "Failed to create prediction with deployment",
+        };
+        let mut prediction = self.create_prediction_from_request(request).await.context(context)?;
+
+        if let Some(webhook) = webhook {
+            prediction.webhook.get_or_insert_with(|| webhook.url.clone());
         }
 
+        let poll_interval = Duration::from_secs(5);
+        let timeout = Duration::from_secs(600); // 10 minutes timeout
+        let prediction = self.wait_with_options(&prediction, poll_interval, timeout).await?;
+
         if prediction.status == Status::Succeeded {
+            if let Some(request) = cacheable {
+                if let Some(output) = self.cache_output(&request, &prediction).await {
+                    return Ok(output);
+                }
+            }
             prediction.output.ok_or_else(|| anyhow!("Prediction succeeded but no output available"))
         } else if prediction.status == Status::Failed {
             Err(anyhow!("Prediction failed: {:?}", prediction.error))
@@ -1,17 +1,20 @@
 use std::error::Error;
 use std::fmt;
-use std::sync::Arc;
-use std::time::Duration;
+use std::time::{ Duration, Instant };
 use anyhow::{ anyhow, Result };
 use futures::{ Stream, StreamExt };
 use reqwest::header::{ HeaderMap, HeaderValue };
+use reqwest::{ Method, Url };
 use serde::{ Deserialize, Serialize };
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 
-use crate::prediction::{ Prediction, PredictionInput, CreatePredictionParams };
+use crate::prediction::{ Prediction, PredictionInput };
+use crate::progress::ProgressKind;
+use crate::request::{ PredictionRequest, Target };
+use crate::telemetry::{ self, Route };
+use crate::transport::HttpRequest;
 use crate::webhook::Webhook;
-use crate::identifier::Identifier;
 use crate::Client;
 
 #[derive(Debug)]
@@ -26,6 +29,7 @@ impl fmt::Display for InvalidUTF8DataError {
 impl Error for InvalidUTF8DataError {}
 
 const SSE_TYPE_DONE: &str = "done";
+const SSE_TYPE_LOGS: &str = "logs";
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SSEEvent {
     #[serde(rename = "type")]
@@ -83,33 +87,42 @@ impl Client {
         input: PredictionInput,
         webhook: Option<&Webhook>
     ) -> Result<(impl Stream<Item = SSEEvent>, impl Stream<Item = anyhow::Error>)> {
-        let id = Identifier::parse(identifier)?;
+        let target = Target::parse(identifier)?;
+        let prediction = self.create_streaming_prediction(target, input, webhook).await?;
 
-        let params = CreatePredictionParams {
-            webhook: webhook.map(|w| w.url.clone()),
-            webhook_events_filter: webhook.map(|w| w.events.clone()),
-            stream: Some(true),
-            webhook_completed: None,
-        };
+        self.stream_prediction(prediction, None).await
+    }
 
-        let prediction = match id.version {
-            Some(version) => {
-                self.create_prediction(None, Some(&version), None, Some(input), Some(params)).await?
-            }
-            None => {
-                self.create_prediction(
-                    Some(&format!("{}/{}", id.owner, id.name)),
-                    None,
-                    None,
-                    Some(input),
-                    Some(params)
-                ).await?
-            }
+    /// Runs a streaming prediction on a deployment.
+    pub async fn stream_deployment(
+        &self,
+        deployment_owner: &str,
+        deployment_name: &str,
+        input: PredictionInput,
+        webhook: Option<&Webhook>
+    ) -> Result<(impl Stream<Item = SSEEvent>, impl Stream<Item = anyhow::Error>)> {
+        let target = Target::Deployment {
+            owner: deployment_owner.to_string(),
+            name: deployment_name.to_string(),
         };
+        let prediction = self.create_streaming_prediction(target, input, webhook).await?;
 
         self.stream_prediction(prediction, None).await
     }
 
+    async fn create_streaming_prediction(
+        &self,
+        target: Target,
+        input: PredictionInput,
+        webhook: Option<&Webhook>
+    ) -> Result<Prediction> {
+        let mut request = PredictionRequest::new(target, input).with_stream(true);
+        if let Some(webhook) = webhook {
+            request = request.with_webhook(webhook);
+        }
+        self.create_prediction_from_request(request).await
+    }
+
     pub async fn stream_prediction(
         &self,
         prediction: Prediction,
@@ -125,10 +138,14 @@ impl Client {
                 anyhow!("streaming not supported or not enabled for this prediction")
             })?;
 
-        let client = Arc::new(self.client.clone());
-        let url = url.to_string();
+        let transport = self.transport.clone();
+        let interceptors = self.interceptors.clone();
+        let progress_reporter = self.progress_reporter.clone();
+        let url = Url::parse(url)?;
+        let route = Route::new(&url);
 
-        tokio::spawn(async move {
+        let span = telemetry::stream_span(&prediction.id);
+        tokio::spawn(telemetry::instrument(async move {
             let mut headers = HeaderMap::new();
             headers.insert("Accept", HeaderValue::from_static("text/event-stream"));
             headers.insert("Cache-Control", HeaderValue::from_static("no-cache"));
@@ -139,22 +156,35 @@ impl Client {
             }
 
             loop {
-                let resp = match client.get(&url).headers(headers.clone()).send().await {
+                let mut request = HttpRequest::new(Method::GET, url.clone());
+                request.headers = headers.clone();
+                if let Err(e) = interceptors.before_request(&mut request) {
+                    let _ = err_tx.send(e).await;
+                    return;
+                }
+
+                let started = Instant::now();
+                let resp = match transport.send(request.clone()).await {
                     Ok(resp) => resp,
                     Err(e) => {
+                        telemetry::record_error(&request, &route, &e);
+                        interceptors.on_error(&request, &e);
                         let _ = err_tx.send(anyhow!("Failed to send request: {}", e)).await;
                         return;
                     }
                 };
-
-                if !resp.status().is_success() {
-                    let _ = err_tx.send(
-                        anyhow!("Received invalid status code: {}", resp.status())
-                    ).await;
+                let elapsed = started.elapsed();
+                telemetry::record_response(&request, &route, 1, resp.status, elapsed);
+                interceptors.after_response(&request, &resp, elapsed);
+
+                if !resp.status.is_success() {
+                    let error = anyhow!("Received invalid status code: {}", resp.status);
+                    interceptors.on_error(&request, &error);
+                    let _ = err_tx.send(error).await;
                     return;
                 }
 
-                let mut stream = resp.bytes_stream();
+                let mut stream = resp.body;
                 let mut buffer = String::new();
 
                 while let Some(chunk) = stream.next().await {
@@ -167,6 +197,18 @@ impl Client {
 
                                 match SSEEvent::decode(&event_str) {
                                     Ok(event) => {
+                                        telemetry::record_stream_event(&event.event_type);
+                                        if let Some(reporter) = &progress_reporter {
+                                            match event.event_type.as_str() {
+                                                SSE_TYPE_LOGS => reporter.record_logs(
+                                                    ProgressKind::Prediction,
+                                                    &prediction.id,
+                                                    &event.data
+                                                ),
+                                                SSE_TYPE_DONE => reporter.finish(&prediction.id),
+                                                _ => {}
+                                            }
+                                        }
                                         if let Err(e) = sse_tx.send(event.clone()).await {
                                             let _ = err_tx.send(
                                                 anyhow!("Failed to send SSE event: {}", e)
@@ -191,7 +233,7 @@ impl Client {
                     }
                 }
             }
-        });
+        }, span));
 
         Ok((ReceiverStream::new(sse_rx), ReceiverStream::new(err_rx)))
     }
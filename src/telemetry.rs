@@ -0,0 +1,277 @@
+//! Tracing spans and metrics for API calls.
+//!
+//! With the `tracing` feature every API call runs in a `replicate.request`
+//! span carrying the method, route, resource ids, attempt number, status and
+//! latency, and waits and streams get spans of their own. With the `metrics`
+//! feature the client reports through the `metrics` facade:
+//!
+//! - `replicate_requests_total` (counter; method, route, status)
+//! - `replicate_request_duration_seconds` (histogram; method, route)
+//! - `replicate_request_errors_total` (counter; method, route)
+//! - `replicate_retries_total` (counter; method, route)
+//! - `replicate_stream_events_total` (counter; event)
+//! - `replicate_wait_duration_seconds` (histogram; kind, outcome)
+//!
+//! Routes are paths with ids replaced, such as `/predictions/:id`, so labels
+//! stay low-cardinality. Paths outside the API's resources are labeled
+//! `other`, and URLs on other hosts, such as output files, `download`.
+//! Without either feature everything here is a no-op.
+
+#![cfg_attr(not(all(feature = "tracing", feature = "metrics")), allow(unused_variables))]
+#![cfg_attr(not(any(feature = "tracing", feature = "metrics")), allow(dead_code))]
+
+use std::future::Future;
+use std::time::Duration;
+use reqwest::{ StatusCode, Url };
+use crate::transport::HttpRequest;
+
+/// The API's top-level resources. Any other path gets the `other` route.
+const RESOURCES: [&str; 10] = [
+    "account",
+    "collections",
+    "deployments",
+    "files",
+    "hardware",
+    "models",
+    "predictions",
+    "streams",
+    "trainings",
+    "webhooks",
+];
+
+#[cfg(feature = "tracing")]
+pub(crate) type Span = tracing::Span;
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct Span;
+
+/// The templated path of an API URL and the resource ids in it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Route {
+    pub(crate) template: String,
+    pub(crate) prediction_id: Option<String>,
+    pub(crate) training_id: Option<String>,
+    /// `owner/name`.
+    pub(crate) model: Option<String>,
+    /// `owner/name`.
+    pub(crate) deployment: Option<String>,
+}
+
+impl Route {
+    /// The route of a URL on the API.
+    pub(crate) fn new(url: &Url) -> Self {
+        let segments: Vec<&str> = url
+            .path_segments()
+            .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        let segments = match segments.first() {
+            Some(&"v1") => &segments[1..],
+            _ => &segments[..],
+        };
+        if !segments.first().is_some_and(|resource| RESOURCES.contains(resource)) {
+            return Self::fixed("other");
+        }
+
+        let mut route = Route::default();
+        let mut template = Vec::new();
+        let mut i = 0;
+        while i < segments.len() {
+            let segment = segments[i];
+            template.push(segment);
+            i += 1;
+
+            match (segment, segments.get(i), segments.get(i + 1)) {
+                ("models" | "deployments", Some(owner), Some(name)) => {
+                    let full_name = Some(format!("{}/{}", owner, name));
+                    if segment == "models" {
+                        route.model = full_name;
+                    } else {
+                        route.deployment = full_name;
+                    }
+                    template.extend([":owner", ":name"]);
+                    i += 2;
+                }
+                ("predictions" | "trainings", Some(id), _) => {
+                    if segment == "predictions" {
+                        route.prediction_id = Some(id.to_string());
+                    } else {
+                        route.training_id = Some(id.to_string());
+                    }
+                    template.push(":id");
+                    i += 1;
+                }
+                ("collections" | "files" | "streams" | "versions", Some(_), _) => {
+                    template.push(":id");
+                    i += 1;
+                }
+                _ => {}
+            }
+        }
+
+        route.template = format!("/{}", template.join("/"));
+        route
+    }
+
+    /// The route of a URL that isn't on the API, like a file download.
+    pub(crate) fn download() -> Self {
+        Self::fixed("download")
+    }
+
+    fn fixed(template: &str) -> Self {
+        Self { template: template.to_string(), ..Default::default() }
+    }
+}
+
+/// Runs `future` inside `span`.
+pub(crate) fn instrument<F: Future>(future: F, span: Span) -> impl Future<Output = F::Output> {
+    #[cfg(feature = "tracing")]
+    {
+        tracing::Instrument::instrument(future, span)
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        future
+    }
+}
+
+pub(crate) fn request_span(request: &HttpRequest, route: &Route) -> Span {
+    #[cfg(feature = "tracing")]
+    {
+        tracing::info_span!(
+            "replicate.request",
+            method = %request.method,
+            route = %route.template,
+            prediction_id = route.prediction_id.as_deref(),
+            training_id = route.training_id.as_deref(),
+            model = route.model.as_deref(),
+            deployment = route.deployment.as_deref(),
+            attempt = tracing::field::Empty,
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty
+        )
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        Span
+    }
+}
+
+pub(crate) fn stream_span(prediction_id: &str) -> Span {
+    #[cfg(feature = "tracing")]
+    {
+        tracing::info_span!("replicate.stream", prediction_id)
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        Span
+    }
+}
+
+/// `kind` is `prediction` or `training`.
+pub(crate) fn wait_span(kind: &'static str, id: &str) -> Span {
+    #[cfg(feature = "tracing")]
+    {
+        tracing::info_span!("replicate.wait", kind, id)
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        Span
+    }
+}
+
+/// Records one attempt's response. `attempt` counts from 1.
+pub(crate) fn record_response(
+    request: &HttpRequest,
+    route: &Route,
+    attempt: u32,
+    status: StatusCode,
+    elapsed: Duration
+) {
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::Span::current();
+        let latency_ms = elapsed.as_millis() as u64;
+        span.record("attempt", attempt);
+        span.record("status", status.as_u16());
+        span.record("latency_ms", latency_ms);
+        tracing::debug!(attempt, status = status.as_u16(), latency_ms, "response received");
+    }
+    #[cfg(feature = "metrics")]
+    {
+        let route = route.template.clone();
+        let method = request.method.to_string();
+        metrics::counter!(
+            "replicate_requests_total",
+            "method" => method.clone(),
+            "route" => route.clone(),
+            "status" => status.as_u16().to_string()
+        ).increment(1);
+        metrics::histogram!(
+            "replicate_request_duration_seconds",
+            "method" => method,
+            "route" => route
+        ).record(elapsed.as_secs_f64());
+    }
+}
+
+/// Records a request that got no response.
+pub(crate) fn record_error(request: &HttpRequest, route: &Route, error: &anyhow::Error) {
+    #[cfg(feature = "tracing")]
+    {
+        tracing::warn!(error = %error, "request failed");
+    }
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!(
+            "replicate_request_errors_total",
+            "method" => request.method.to_string(),
+            "route" => route.template.clone()
+        ).increment(1);
+    }
+}
+
+pub(crate) fn record_retry(request: &HttpRequest, route: &Route, delay: Duration) {
+    #[cfg(feature = "tracing")]
+    {
+        tracing::info!(delay_ms = delay.as_millis() as u64, "retrying");
+    }
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!(
+            "replicate_retries_total",
+            "method" => request.method.to_string(),
+            "route" => route.template.clone()
+        ).increment(1);
+    }
+}
+
+pub(crate) fn record_stream_event(event_type: &str) {
+    #[cfg(feature = "tracing")]
+    {
+        tracing::trace!(event = event_type, "stream event");
+    }
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!(
+            "replicate_stream_events_total",
+            "event" => event_type.to_string()
+        ).increment(1);
+    }
+}
+
+/// `outcome` is the final status, or `error` if the wait failed.
+pub(crate) fn record_wait(kind: &'static str, outcome: String, elapsed: Duration) {
+    #[cfg(feature = "tracing")]
+    {
+        let elapsed_ms = elapsed.as_millis() as u64;
+        tracing::info!(outcome = %outcome, elapsed_ms, "wait finished");
+    }
+    #[cfg(feature = "metrics")]
+    {
+        metrics::histogram!(
+            "replicate_wait_duration_seconds",
+            "kind" => kind,
+            "outcome" => outcome
+        ).record(elapsed.as_secs_f64());
+    }
+}
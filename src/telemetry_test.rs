@@ -0,0 +1,187 @@
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+    use super::super::telemetry::Route;
+
+    fn route(url: &str) -> Route {
+        Route::new(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_route_templates() {
+        let cases = [
+            ("/predictions", "/predictions"),
+            ("/predictions/abc/cancel", "/predictions/:id/cancel"),
+            ("/predictions?cursor=xyz", "/predictions"),
+            ("/models/acme/llm/predictions", "/models/:owner/:name/predictions"),
+            ("/models/acme/llm/versions/v1", "/models/:owner/:name/versions/:id"),
+            ("/deployments/acme/app", "/deployments/:owner/:name"),
+            ("/collections/text-to-image", "/collections/:id"),
+            ("/webhooks/default/secret", "/webhooks/default/secret"),
+        ];
+        for (path, template) in cases {
+            let url = format!("https://api.replicate.com/v1{}", path);
+            assert_eq!(route(&url).template, template, "{}", path);
+        }
+
+        let stream = route("https://streaming.api.replicate.com/v1/streams/abc");
+        assert_eq!(stream.template, "/streams/:id");
+
+        for url in ["https://api.replicate.com/v1", "https://api.replicate.com/v1/unknown/abc"] {
+            assert_eq!(route(url).template, "other", "{}", url);
+        }
+        assert_eq!(Route::download().template, "download");
+    }
+
+    #[test]
+    fn test_route_ids() {
+        let prediction = route("https://api.replicate.com/v1/predictions/abc");
+        assert_eq!(prediction.prediction_id.as_deref(), Some("abc"));
+        assert_eq!(prediction.model, None);
+
+        let model = route("https://api.replicate.com/v1/models/acme/llm/predictions");
+        assert_eq!(model.model.as_deref(), Some("acme/llm"));
+        assert_eq!(model.prediction_id, None);
+
+        let deployment = route("https://api.replicate.com/v1/deployments/acme/app/predictions");
+        assert_eq!(deployment.deployment.as_deref(), Some("acme/app"));
+
+        let training = route("https://api.replicate.com/v1/trainings/t1/cancel");
+        assert_eq!(training.training_id.as_deref(), Some("t1"));
+    }
+
+    #[cfg(feature = "metrics")]
+    mod metrics {
+        use std::collections::HashMap;
+        use std::sync::atomic::{ AtomicU64, Ordering };
+        use std::sync::{ Arc, Mutex };
+        use std::time::Duration;
+        use bytes::Bytes;
+        use metrics::{
+            Counter,
+            CounterFn,
+            Gauge,
+            Histogram,
+            Key,
+            KeyName,
+            Metadata,
+            Recorder,
+            SharedString,
+            Unit,
+        };
+        use reqwest::StatusCode;
+        use super::super::super::backoff::ConstantBackoff;
+        use super::super::super::test_support::{ response, ScriptedTransport };
+
+        /// Keeps counter values by name and labels.
+        #[derive(Default)]
+        struct Counters(Mutex<HashMap<String, Arc<AtomicU64>>>);
+
+        struct Value(Arc<AtomicU64>);
+
+        impl CounterFn for Value {
+            fn increment(&self, value: u64) {
+                self.0.fetch_add(value, Ordering::SeqCst);
+            }
+
+            fn absolute(&self, value: u64) {
+                self.0.fetch_max(value, Ordering::SeqCst);
+            }
+        }
+
+        impl Counters {
+            fn get(&self, key: &str) -> u64 {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .get(key)
+                    .map_or(0, |value| value.load(Ordering::SeqCst))
+            }
+        }
+
+        impl Recorder for Counters {
+            fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+            fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+                let labels: Vec<_> = key
+                    .labels()
+                    .map(|label| format!("{}={}", label.key(), label.value()))
+                    .collect();
+                let name = format!("{}{{{}}}", key.name(), labels.join(","));
+                let value = self.0.lock().unwrap().entry(name).or_default().clone();
+                Counter::from_arc(Arc::new(Value(value)))
+            }
+
+            fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+                Gauge::noop()
+            }
+
+            fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+                Histogram::noop()
+            }
+        }
+
+        #[test]
+        fn test_requests_and_retries_are_counted() {
+            let counters = Counters::default();
+            // Answers with 503 once, then with a prediction.
+            let attempts = AtomicU64::new(0);
+            let flaky = ScriptedTransport::new(move |_| {
+                let status = match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 => StatusCode::SERVICE_UNAVAILABLE,
+                    _ => StatusCode::OK,
+                };
+                Ok(response(status, Bytes::from(include_str!("fixtures/prediction.json"))))
+            });
+            let client = flaky
+                .client()
+                .with_backoff(
+                    Box::new(ConstantBackoff {
+                        base: Duration::ZERO,
+                        jitter: Duration::from_millis(1),
+                    })
+                );
+            let runtime = tokio::runtime::Builder
+                ::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+
+            metrics::with_local_recorder(&counters, || {
+                runtime.block_on(client.get_prediction("abc")).unwrap();
+            });
+
+            let labels = "method=GET,route=/predictions/:id";
+            let requests = |status| {
+                counters.get(&format!("replicate_requests_total{{{},status={}}}", labels, status))
+            };
+            assert_eq!(requests(503), 1);
+            assert_eq!(requests(200), 1);
+            assert_eq!(counters.get(&format!("replicate_retries_total{{{}}}", labels)), 1);
+        }
+
+        #[test]
+        fn test_downloads_share_one_route() {
+            let counters = Counters::default();
+            let files = ScriptedTransport::ok("contents");
+            let client = files.client();
+            let runtime = tokio::runtime::Builder
+                ::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+
+            metrics::with_local_recorder(&counters, || {
+                for name in ["a.png", "b.png"] {
+                    let url = format!("https://replicate.delivery/pbxt/{}", name);
+                    runtime.block_on(client.download(&url)).unwrap();
+                }
+            });
+
+            let key = "replicate_requests_total{method=GET,route=download,status=200}";
+            assert_eq!(counters.get(key), 2);
+        }
+    }
+}
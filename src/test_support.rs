@@ -0,0 +1,74 @@
+//! Doubles shared by the unit tests.
+
+use std::sync::{ Arc, Mutex };
+use anyhow::Result;
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde::Serialize;
+use crate::client::Client;
+use crate::prediction::Prediction;
+use crate::status::Status;
+use crate::transport::{ HttpRequest, HttpResponse, Transport };
+
+/// The id of the prediction in `fixtures/prediction.json`.
+pub(crate) const PREDICTION_ID: &str = "gm3qorzdhgbfurvjtvhg6dckhu";
+
+/// The prediction in `fixtures/prediction.json`, with `id` and `status`.
+pub(crate) fn prediction(id: &str, status: Status) -> Prediction {
+    let mut prediction: Prediction = serde_json
+        ::from_str(include_str!("fixtures/prediction.json"))
+        .unwrap();
+    prediction.id = id.to_string();
+    prediction.status = status;
+    prediction
+}
+
+type Handler = dyn Fn(&HttpRequest) -> Result<HttpResponse> + Send + Sync;
+
+/// Answers each request with whatever `handler` returns for it and keeps the
+/// requests it saw. Clones share both.
+#[derive(Clone)]
+pub(crate) struct ScriptedTransport {
+    handler: Arc<Handler>,
+    requests: Arc<Mutex<Vec<HttpRequest>>>,
+}
+
+impl ScriptedTransport {
+    pub(crate) fn new(
+        handler: impl Fn(&HttpRequest) -> Result<HttpResponse> + Send + Sync + 'static
+    ) -> Self {
+        Self { handler: Arc::new(handler), requests: Arc::default() }
+    }
+
+    /// Answers every request with 200 and `body`.
+    pub(crate) fn ok(body: &'static str) -> Self {
+        Self::new(move |_| Ok(response(StatusCode::OK, Bytes::from(body))))
+    }
+
+    pub(crate) fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// A client that sends everything here.
+    pub(crate) fn client(&self) -> Client {
+        Client::new(Some("r8_token".to_string())).unwrap().with_transport(self.clone())
+    }
+}
+
+impl Transport for ScriptedTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        self.requests.lock().unwrap().push(request.clone());
+        let response = (self.handler)(&request);
+        Box::pin(async move { response })
+    }
+}
+
+pub(crate) fn response(status: StatusCode, body: Bytes) -> HttpResponse {
+    HttpResponse::new(status, HeaderMap::new(), body)
+}
+
+pub(crate) fn json_response(status: StatusCode, body: &impl Serialize) -> Result<HttpResponse> {
+    Ok(response(status, Bytes::from(serde_json::to_vec(body)?)))
+}
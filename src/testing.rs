@@ -0,0 +1,815 @@
+//! An in-process fake of the Replicate API for offline tests.
+//!
+//! Enabled with the `testing` feature. `MockServer::start` binds a local port
+//! and serves the endpoints this crate calls, backed by in-memory state:
+//! predictions move from `starting` through `processing` to a terminal status,
+//! streaming predictions get an SSE endpoint, files and deployments can be
+//! created, updated and deleted, and predictions with a webhook get signed
+//! deliveries just like the real API sends.
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! let server = repli::testing::MockServer::start().await?;
+//! server.on_predict("acme/upper", |input| {
+//!     Ok(serde_json::json!(input["text"].as_str().unwrap_or_default().to_uppercase()))
+//! });
+//!
+//! let client = server.client();
+//! let output = client.run("acme/upper", [("text".to_string(), "hi".into())].into(), None).await?;
+//! assert_eq!(output, serde_json::json!("HI"));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+use anyhow::{ Result, anyhow };
+use axum::extract::{ Multipart, Path, Request, State };
+use axum::http::{ header, StatusCode };
+use axum::middleware::{ self, Next };
+use axum::response::{ IntoResponse, Response };
+use axum::routing::{ get, post };
+use axum::{ Json, Router };
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Utc;
+use serde_json::{ json, Value };
+use sha2::{ Digest, Sha256 };
+use tokio::sync::oneshot;
+use crate::account::Account;
+use crate::client::Client;
+use crate::collection::Collection;
+use crate::deployment::{
+    CreateDeploymentOptions,
+    Deployment,
+    DeploymentConfiguration,
+    DeploymentRelease,
+    UpdateDeploymentOptions,
+};
+use crate::files::File;
+use crate::hardware::{ Hardware, HardwareInfo };
+use crate::paginate::Page;
+use crate::prediction::{ Prediction, PredictionInput };
+use crate::status::Status;
+use crate::webhook::{ sign_webhook, WebhookEventType, WebhookSigningSecret };
+
+const MOCK_TOKEN: &str = "mock-token";
+const MOCK_OWNER: &str = "mock";
+const DEFAULT_STEP_DELAY: Duration = Duration::from_millis(10);
+const STREAM_POLL_INTERVAL: Duration = Duration::from_millis(5);
+// Deliveries are sent from inside request handlers, so a receiver that never
+// answers mustn't hold up the response for long.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+type PredictHandler = Arc<dyn Fn(&PredictionInput) -> Result<Value, String> + Send + Sync>;
+
+/// A webhook the server sent, kept for assertions.
+#[derive(Debug, Clone)]
+pub struct MockWebhookDelivery {
+    pub url: String,
+    pub event: WebhookEventType,
+    pub prediction: Prediction,
+    /// The receiver's response status, or `None` if the request failed.
+    pub status: Option<u16>,
+}
+
+pub struct MockServer {
+    state: Arc<MockState>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+struct MockState {
+    url: String,
+    secret: WebhookSigningSecret,
+    step_delay: Option<Duration>,
+    http: reqwest::Client,
+    data: Mutex<MockData>,
+}
+
+#[derive(Default)]
+struct MockData {
+    next_id: u64,
+    // Newest first, like the real list endpoint.
+    predictions: Vec<Prediction>,
+    handlers: HashMap<String, PredictHandler>,
+    files: Vec<(File, Vec<u8>)>,
+    deployments: Vec<Deployment>,
+    collections: Vec<Collection>,
+    hardware: Vec<HardwareInfo>,
+    deliveries: Vec<MockWebhookDelivery>,
+}
+
+impl MockData {
+    fn next_id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}{:08}", prefix, self.next_id)
+    }
+
+    fn prediction_mut(&mut self, id: &str) -> Option<&mut Prediction> {
+        self.predictions.iter_mut().find(|p| p.id == id)
+    }
+
+    fn deployment_mut(&mut self, owner: &str, name: &str) -> Option<&mut Deployment> {
+        self.deployments.iter_mut().find(|d| d.owner == owner && d.name == name)
+    }
+}
+
+impl MockServer {
+    /// Starts a server whose predictions advance one status every 10ms.
+    pub async fn start() -> Result<Self> {
+        Self::start_with_step_delay(Some(DEFAULT_STEP_DELAY)).await
+    }
+
+    /// Starts a server whose predictions advance one status per `step_delay`,
+    /// or only when `advance` is called if it is `None`.
+    pub async fn start_with_step_delay(step_delay: Option<Duration>) -> Result<Self> {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let url = format!("http://{}", listener.local_addr()?);
+
+        let hardware = [
+            ("CPU", Hardware::Cpu),
+            ("Nvidia T4 GPU", Hardware::GpuT4),
+            ("Nvidia L40S GPU", Hardware::GpuL40s),
+            ("Nvidia A100 (80GB) GPU", Hardware::GpuA100Large),
+            ("Nvidia H100 GPU", Hardware::GpuH100),
+        ];
+        let data = MockData {
+            hardware: hardware
+                .into_iter()
+                .map(|(name, sku)| HardwareInfo { name: name.to_string(), sku })
+                .collect(),
+            ..Default::default()
+        };
+
+        let state = Arc::new(MockState {
+            url,
+            secret: WebhookSigningSecret {
+                key: format!("whsec_{}", BASE64.encode(b"repli-mock-webhook-secret")),
+            },
+            step_delay,
+            http: reqwest::Client::builder().timeout(DELIVERY_TIMEOUT).build()?,
+            data: Mutex::new(data),
+        });
+
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        let app = router(state.clone());
+        tokio::spawn(async move {
+            let server = axum::serve(listener, app).with_graceful_shutdown(async {
+                let _ = stopped.await;
+            });
+            if let Err(e) = server.await {
+                log::error!("Mock server failed: {}", e);
+            }
+        });
+
+        Ok(Self { state, shutdown: Some(shutdown) })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.state.url
+    }
+
+    /// A client pointed at this server.
+    pub fn client(&self) -> Client {
+        Client::new(Some(MOCK_TOKEN.to_string()))
+            .expect("auth token is set")
+            .with_base_url(self.state.url.clone())
+    }
+
+    /// The secret deliveries are signed with, also served from
+    /// `/webhooks/default/secret`.
+    pub fn webhook_secret(&self) -> WebhookSigningSecret {
+        self.state.secret.clone()
+    }
+
+    /// Computes the output of predictions against `key`, which is a version id
+    /// or a model's "owner/name". An `Err` fails the prediction with that
+    /// message. Predictions without a handler echo their input.
+    pub fn on_predict<F>(&self, key: &str, handler: F)
+        where F: Fn(&PredictionInput) -> Result<Value, String> + Send + Sync + 'static
+    {
+        self.state.data.lock().unwrap().handlers.insert(key.to_string(), Arc::new(handler));
+    }
+
+    pub fn add_collection(&self, collection: Collection) {
+        self.state.data.lock().unwrap().collections.push(collection);
+    }
+
+    pub fn set_hardware(&self, hardware: Vec<HardwareInfo>) {
+        self.state.data.lock().unwrap().hardware = hardware;
+    }
+
+    pub fn prediction(&self, id: &str) -> Option<Prediction> {
+        self.state.data.lock().unwrap().predictions.iter().find(|p| p.id == id).cloned()
+    }
+
+    pub fn predictions(&self) -> Vec<Prediction> {
+        self.state.data.lock().unwrap().predictions.clone()
+    }
+
+    pub fn deployments(&self) -> Vec<Deployment> {
+        self.state.data.lock().unwrap().deployments.clone()
+    }
+
+    /// Uploaded files with their contents.
+    pub fn files(&self) -> Vec<(File, Vec<u8>)> {
+        self.state.data.lock().unwrap().files.clone()
+    }
+
+    pub fn webhook_deliveries(&self) -> Vec<MockWebhookDelivery> {
+        self.state.data.lock().unwrap().deliveries.clone()
+    }
+
+    /// Moves a prediction to its next status and returns it.
+    pub async fn advance(&self, prediction_id: &str) -> Result<Prediction> {
+        advance(&self.state, prediction_id).await.ok_or_else(|| {
+            anyhow!("no prediction with id {}", prediction_id)
+        })
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+fn router(state: Arc<MockState>) -> Router {
+    Router::new()
+        .route("/account", get(get_account))
+        .route("/hardware", get(list_hardware))
+        .route("/collections", get(list_collections))
+        .route("/collections/:slug", get(get_collection))
+        .route("/predictions", get(list_predictions).post(create_version_prediction))
+        .route("/predictions/:id", get(get_prediction))
+        .route("/predictions/:id/cancel", post(cancel_prediction))
+        .route("/models/:owner/:name/predictions", post(create_model_prediction))
+        .route("/deployments", get(list_deployments).post(create_deployment))
+        .route(
+            "/deployments/:owner/:name",
+            get(get_deployment).patch(update_deployment).delete(delete_deployment)
+        )
+        .route("/deployments/:owner/:name/predictions", post(create_deployment_prediction))
+        .route("/files", get(list_files).post(create_file))
+        .route("/files/:id", get(get_file).delete(delete_file))
+        .route("/files/:id/download", get(download_file))
+        .route("/webhooks/default/secret", get(get_webhook_secret))
+        .layer(middleware::from_fn(require_auth))
+        // Stream URLs are fetched without a token, as with the real API.
+        .route("/predictions/:id/stream", get(stream_prediction))
+        .with_state(state)
+}
+
+async fn require_auth(request: Request, next: Next) -> Response {
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("Bearer ") && value.len() > "Bearer ".len());
+
+    if authorized {
+        next.run(request).await
+    } else {
+        error(StatusCode::UNAUTHORIZED, "You did not pass a valid authentication token")
+    }
+}
+
+fn error(status: StatusCode, detail: &str) -> Response {
+    let body = json!({
+        "title": status.canonical_reason(),
+        "detail": detail,
+        "status": status.as_u16(),
+    });
+    (status, Json(body)).into_response()
+}
+
+fn not_found() -> Response {
+    error(StatusCode::NOT_FOUND, "Not found.")
+}
+
+fn page<T: serde::Serialize>(results: Vec<T>) -> Response {
+    Json(Page::new(results)).into_response()
+}
+
+fn mock_account() -> Account {
+    Account::new("user".to_string(), MOCK_OWNER.to_string(), "Mock".to_string(), None)
+}
+
+async fn get_account() -> Response {
+    Json(mock_account()).into_response()
+}
+
+async fn list_hardware(State(state): State<Arc<MockState>>) -> Response {
+    Json(state.data.lock().unwrap().hardware.clone()).into_response()
+}
+
+async fn list_collections(State(state): State<Arc<MockState>>) -> Response {
+    page(state.data.lock().unwrap().collections.clone())
+}
+
+async fn get_collection(
+    State(state): State<Arc<MockState>>,
+    Path(slug): Path<String>
+) -> Response {
+    let data = state.data.lock().unwrap();
+    match data.collections.iter().find(|c| c.slug == slug) {
+        Some(collection) => Json(collection).into_response(),
+        None => not_found(),
+    }
+}
+
+async fn list_predictions(State(state): State<Arc<MockState>>) -> Response {
+    page(state.data.lock().unwrap().predictions.clone())
+}
+
+async fn get_prediction(State(state): State<Arc<MockState>>, Path(id): Path<String>) -> Response {
+    let data = state.data.lock().unwrap();
+    match data.predictions.iter().find(|p| p.id == id) {
+        Some(prediction) => Json(prediction).into_response(),
+        None => not_found(),
+    }
+}
+
+async fn create_version_prediction(
+    State(state): State<Arc<MockState>>,
+    Json(body): Json<Value>
+) -> Response {
+    let Some(version) = body["version"].as_str() else {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "version is required");
+    };
+    let version = version.to_string();
+    create_prediction(&state, String::new(), version, body).await
+}
+
+async fn create_model_prediction(
+    State(state): State<Arc<MockState>>,
+    Path((owner, name)): Path<(String, String)>,
+    Json(body): Json<Value>
+) -> Response {
+    create_prediction(&state, format!("{}/{}", owner, name), String::new(), body).await
+}
+
+async fn create_deployment_prediction(
+    State(state): State<Arc<MockState>>,
+    Path((owner, name)): Path<(String, String)>,
+    Json(body): Json<Value>
+) -> Response {
+    let release = {
+        let mut data = state.data.lock().unwrap();
+        data.deployment_mut(&owner, &name).and_then(|d| d.current_release.clone())
+    };
+    match release {
+        Some(release) => create_prediction(&state, release.model, release.version, body).await,
+        None => not_found(),
+    }
+}
+
+async fn create_prediction(
+    state: &Arc<MockState>,
+    model: String,
+    version: String,
+    body: Value
+) -> Response {
+    let prediction = {
+        let mut data = state.data.lock().unwrap();
+        let id = data.next_id("mockpred");
+        let base = format!("{}/predictions/{}", state.url, id);
+        let mut urls = json!({
+            "get": base,
+            "cancel": format!("{}/cancel", base),
+        });
+        if body["stream"].as_bool().unwrap_or(false) {
+            urls["stream"] = json!(format!("{}/stream", base));
+        }
+
+        let value = json!({
+            "id": id,
+            "model": model,
+            "version": version,
+            "input": body["input"],
+            "status": "starting",
+            "logs": "",
+            "webhook": body["webhook"],
+            "webhook_events_filter": body["webhook_events_filter"],
+            "urls": urls,
+            "created_at": Utc::now(),
+        });
+        let prediction: Prediction = match serde_json::from_value(value) {
+            Ok(prediction) => prediction,
+            Err(e) => {
+                return error(StatusCode::UNPROCESSABLE_ENTITY, &e.to_string());
+            }
+        };
+        data.predictions.insert(0, prediction.clone());
+        prediction
+    };
+
+    deliver(state, WebhookEventType::Start, &prediction).await;
+
+    if let Some(step_delay) = state.step_delay {
+        let state = state.clone();
+        let id = prediction.id.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(step_delay).await;
+                match advance(&state, &id).await {
+                    Some(prediction) if !prediction.status.is_terminated() => {}
+                    _ => {
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    (StatusCode::CREATED, Json(prediction)).into_response()
+}
+
+/// Moves a prediction one status forward: `starting` to `processing`, then
+/// to `succeeded` or `failed` depending on its handler.
+async fn advance(state: &MockState, id: &str) -> Option<Prediction> {
+    let (prediction, event) = {
+        let mut data = state.data.lock().unwrap();
+        let handler = {
+            let prediction = data.predictions.iter().find(|p| p.id == id)?;
+            data.handlers
+                .get(&prediction.version)
+                .or_else(|| data.handlers.get(&prediction.model))
+                .cloned()
+        };
+        let prediction = data.prediction_mut(id)?;
+        let now = Utc::now();
+
+        let event = match prediction.status {
+            Status::Starting => {
+                prediction.status = Status::Processing;
+                prediction.started_at = Some(now);
+                prediction.logs = Some("Running prediction\n".to_string());
+                WebhookEventType::Logs
+            }
+            Status::Processing => {
+                match handler {
+                    Some(handler) => {
+                        match handler(&prediction.input) {
+                            Ok(output) => {
+                                prediction.status = Status::Succeeded;
+                                prediction.output = Some(output);
+                            }
+                            Err(e) => {
+                                prediction.status = Status::Failed;
+                                prediction.error = Some(json!(e));
+                            }
+                        }
+                    }
+                    None => {
+                        prediction.status = Status::Succeeded;
+                        prediction.output = Some(json!(prediction.input));
+                    }
+                }
+                prediction.completed_at = Some(now);
+                let predict_time = prediction.run_duration().unwrap_or_default().as_secs_f64();
+                prediction.metrics = serde_json
+                    ::from_value(json!({ "predict_time": predict_time }))
+                    .ok();
+                WebhookEventType::Completed
+            }
+            _ => {
+                return Some(prediction.clone());
+            }
+        };
+        (prediction.clone(), event)
+    };
+
+    deliver(state, event, &prediction).await;
+    Some(prediction)
+}
+
+async fn cancel_prediction(
+    State(state): State<Arc<MockState>>,
+    Path(id): Path<String>
+) -> Response {
+    let canceled = {
+        let mut data = state.data.lock().unwrap();
+        let Some(prediction) = data.prediction_mut(&id) else {
+            return not_found();
+        };
+        if prediction.status.is_terminated() {
+            return Json(prediction.clone()).into_response();
+        }
+        prediction.status = Status::Canceled;
+        prediction.completed_at = Some(Utc::now());
+        prediction.clone()
+    };
+
+    deliver(&state, WebhookEventType::Completed, &canceled).await;
+    Json(canceled).into_response()
+}
+
+/// Waits for the prediction to finish, then sends its output as SSE `output`
+/// events followed by `done`.
+async fn stream_prediction(
+    State(state): State<Arc<MockState>>,
+    Path(id): Path<String>
+) -> Response {
+    let prediction = loop {
+        let prediction = state.data.lock().unwrap().predictions
+            .iter()
+            .find(|p| p.id == id)
+            .cloned();
+        match prediction {
+            None => {
+                return not_found();
+            }
+            Some(prediction) if prediction.status.is_terminated() => {
+                break prediction;
+            }
+            Some(_) => tokio::time::sleep(STREAM_POLL_INTERVAL).await,
+        }
+    };
+
+    let chunks = match &prediction.output {
+        Some(Value::Array(items)) => items.iter().map(sse_data).collect(),
+        Some(Value::Null) | None => Vec::new(),
+        Some(output) => vec![sse_data(output)],
+    };
+
+    let mut events = Vec::new();
+    for chunk in chunks {
+        events.push(("output", chunk));
+    }
+    if let Some(error) = &prediction.error {
+        events.push(("error", sse_data(error)));
+    }
+    events.push(("done", "{}".to_string()));
+
+    let body: String = events
+        .iter()
+        .enumerate()
+        .map(|(i, (event, data))| {
+            let data: Vec<String> = data
+                .lines()
+                .map(|line| format!("data: {}", line))
+                .collect();
+            format!("event: {}\nid: {}\n{}\n\n", event, i, data.join("\n"))
+        })
+        .collect();
+
+    ([(header::CONTENT_TYPE, "text/event-stream")], body).into_response()
+}
+
+fn sse_data(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Sends a signed delivery if the prediction has a webhook that accepts
+/// `event`, and records it.
+async fn deliver(state: &MockState, event: WebhookEventType, prediction: &Prediction) {
+    let Some(url) = prediction.webhook.clone() else {
+        return;
+    };
+    let wanted = prediction.webhook_events_filter
+        .as_ref()
+        .is_none_or(|events| events.contains(&event));
+    if !wanted {
+        return;
+    }
+
+    let id = state.data.lock().unwrap().next_id("msg_");
+    let timestamp = Utc::now().timestamp().to_string();
+    let body = serde_json::to_vec(prediction).unwrap_or_default();
+    let signature = sign_webhook(&id, &timestamp, &body, &state.secret).ok();
+
+    let mut request = state.http
+        .post(&url)
+        .header("Content-Type", "application/json")
+        .header("webhook-id", &id)
+        .header("webhook-timestamp", &timestamp)
+        .body(body);
+    if let Some(signature) = signature {
+        request = request.header("webhook-signature", signature);
+    }
+    let status = match request.send().await {
+        Ok(response) => Some(response.status().as_u16()),
+        Err(e) => {
+            log::warn!("Mock webhook delivery to {} failed: {}", url, e);
+            None
+        }
+    };
+
+    state.data.lock().unwrap().deliveries.push(MockWebhookDelivery {
+        url,
+        event,
+        prediction: prediction.clone(),
+        status,
+    });
+}
+
+async fn list_deployments(State(state): State<Arc<MockState>>) -> Response {
+    page(state.data.lock().unwrap().deployments.clone())
+}
+
+async fn get_deployment(
+    State(state): State<Arc<MockState>>,
+    Path((owner, name)): Path<(String, String)>
+) -> Response {
+    let mut data = state.data.lock().unwrap();
+    match data.deployment_mut(&owner, &name) {
+        Some(deployment) => Json(deployment.clone()).into_response(),
+        None => not_found(),
+    }
+}
+
+fn release(number: i32, options: &CreateDeploymentOptions) -> DeploymentRelease {
+    DeploymentRelease {
+        number,
+        model: options.model.clone(),
+        version: options.version.clone(),
+        created_at: Utc::now(),
+        created_by: mock_account(),
+        configuration: DeploymentConfiguration {
+            hardware: options.hardware.clone(),
+            min_instances: options.min_instances,
+            max_instances: options.max_instances,
+        },
+        extra: HashMap::new(),
+    }
+}
+
+async fn create_deployment(
+    State(state): State<Arc<MockState>>,
+    Json(options): Json<CreateDeploymentOptions>
+) -> Response {
+    let mut data = state.data.lock().unwrap();
+    if data.deployment_mut(MOCK_OWNER, &options.name).is_some() {
+        return error(StatusCode::CONFLICT, "A deployment with this name already exists.");
+    }
+
+    let deployment = Deployment {
+        owner: MOCK_OWNER.to_string(),
+        name: options.name.clone(),
+        current_release: Some(release(1, &options)),
+        extra: HashMap::new(),
+    };
+    data.deployments.push(deployment.clone());
+    (StatusCode::CREATED, Json(deployment)).into_response()
+}
+
+async fn update_deployment(
+    State(state): State<Arc<MockState>>,
+    Path((owner, name)): Path<(String, String)>,
+    Json(update): Json<UpdateDeploymentOptions>
+) -> Response {
+    let mut data = state.data.lock().unwrap();
+    let Some(deployment) = data.deployment_mut(&owner, &name) else {
+        return not_found();
+    };
+    let Some(current) = &deployment.current_release else {
+        return not_found();
+    };
+
+    let options = CreateDeploymentOptions {
+        name: name.clone(),
+        model: update.model.unwrap_or_else(|| current.model.clone()),
+        version: update.version.unwrap_or_else(|| current.version.clone()),
+        hardware: update.hardware.unwrap_or_else(|| current.configuration.hardware.clone()),
+        min_instances: update.min_instances.unwrap_or(current.configuration.min_instances),
+        max_instances: update.max_instances.unwrap_or(current.configuration.max_instances),
+    };
+    deployment.current_release = Some(release(current.number + 1, &options));
+    Json(deployment.clone()).into_response()
+}
+
+async fn delete_deployment(
+    State(state): State<Arc<MockState>>,
+    Path((owner, name)): Path<(String, String)>
+) -> Response {
+    let mut data = state.data.lock().unwrap();
+    let before = data.deployments.len();
+    data.deployments.retain(|d| !(d.owner == owner && d.name == name));
+    if data.deployments.len() == before {
+        not_found()
+    } else {
+        StatusCode::NO_CONTENT.into_response()
+    }
+}
+
+async fn list_files(State(state): State<Arc<MockState>>) -> Response {
+    let files: Vec<File> = state.data
+        .lock()
+        .unwrap()
+        .files.iter()
+        .map(|(file, _)| file.clone())
+        .collect();
+    page(files)
+}
+
+async fn get_file(State(state): State<Arc<MockState>>, Path(id): Path<String>) -> Response {
+    let data = state.data.lock().unwrap();
+    match data.files.iter().find(|(file, _)| file.id == id) {
+        Some((file, _)) => Json(file).into_response(),
+        None => not_found(),
+    }
+}
+
+async fn download_file(State(state): State<Arc<MockState>>, Path(id): Path<String>) -> Response {
+    let data = state.data.lock().unwrap();
+    match data.files.iter().find(|(file, _)| file.id == id) {
+        Some((file, bytes)) => {
+            ([(header::CONTENT_TYPE, file.content_type.clone())], bytes.clone()).into_response()
+        }
+        None => not_found(),
+    }
+}
+
+async fn create_file(State(state): State<Arc<MockState>>, mut multipart: Multipart) -> Response {
+    let mut content = None;
+    let mut metadata = Value::Object(Default::default());
+
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => {
+                break;
+            }
+            Err(e) => {
+                return error(StatusCode::BAD_REQUEST, &e.to_string());
+            }
+        };
+
+        match field.name() {
+            Some("content") => {
+                let name = field.file_name().unwrap_or("file").to_string();
+                let content_type = field
+                    .content_type()
+                    .unwrap_or("application/octet-stream")
+                    .to_string();
+                match field.bytes().await {
+                    Ok(bytes) => {
+                        content = Some((name, content_type, bytes.to_vec()));
+                    }
+                    Err(e) => {
+                        return error(StatusCode::BAD_REQUEST, &e.to_string());
+                    }
+                }
+            }
+            Some("metadata") => {
+                let text = field.text().await.unwrap_or_default();
+                metadata = serde_json::from_str(&text).unwrap_or(metadata);
+            }
+            _ => {}
+        }
+    }
+
+    let Some((name, content_type, bytes)) = content else {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "content is required");
+    };
+
+    let mut data = state.data.lock().unwrap();
+    let id = data.next_id("mockfile");
+    let checksum = format!("{:x}", Sha256::digest(&bytes));
+    let now = Utc::now();
+    let value = json!({
+        "id": id,
+        "name": name,
+        "content_type": content_type,
+        "size": bytes.len(),
+        "etag": checksum,
+        "checksums": { "sha256": checksum },
+        "metadata": metadata,
+        "created_at": now,
+        "expires_at": now + chrono::Duration::days(1),
+        "urls": { "get": format!("{}/files/{}", state.url, id) },
+    });
+    let file: File = match serde_json::from_value(value) {
+        Ok(file) => file,
+        Err(e) => {
+            return error(StatusCode::UNPROCESSABLE_ENTITY, &e.to_string());
+        }
+    };
+
+    data.files.push((file.clone(), bytes));
+    (StatusCode::CREATED, Json(file)).into_response()
+}
+
+async fn delete_file(State(state): State<Arc<MockState>>, Path(id): Path<String>) -> Response {
+    let mut data = state.data.lock().unwrap();
+    let before = data.files.len();
+    data.files.retain(|(file, _)| file.id != id);
+    if data.files.len() == before {
+        not_found()
+    } else {
+        StatusCode::NO_CONTENT.into_response()
+    }
+}
+
+async fn get_webhook_secret(State(state): State<Arc<MockState>>) -> Response {
+    Json(state.secret.clone()).into_response()
+}
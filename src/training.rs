@@ -1,63 +1,106 @@
+use std::collections::HashMap;
+use std::time::Duration;
 use anyhow::Result;
+use chrono::{ DateTime, Utc };
 use serde::{ Deserialize, Serialize };
 use crate::client::Client;
-use crate::model::Model;
+use crate::paginate::Page;
+use crate::prediction::{ PredictionMetrics, Source };
+use crate::progress::{ parse_progress, PredictionProgress };
+use crate::status::Status;
+use crate::webhook::WebhookEventType;
+use crate::fields::null_as_default;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Training {
     pub id: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub model: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub version: String,
-    pub status: String,
+    pub status: Status,
+    #[serde(default)]
     pub input: serde_json::Value,
     pub output: Option<serde_json::Value>,
-    pub error: Option<String>,
+    #[serde(default)]
+    pub source: Option<Source>,
+    pub error: Option<serde_json::Value>,
     pub logs: Option<String>,
-    pub webhook_completed: Option<String>,
+    pub metrics: Option<PredictionMetrics>,
+    pub webhook: Option<String>,
+    pub webhook_events_filter: Option<Vec<WebhookEventType>>,
+    pub urls: Option<HashMap<String, String>>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-impl Client {
-    pub async fn create_training(
-        &self,
-        model: &Model,
-        version: &str,
-        input: serde_json::Value
-    ) -> Result<Training> {
-        let path = format!("/models/{}/{}/versions/{}/trainings", model.owner, model.name, version);
-        let body = serde_json::json!({
-            "input": input,
-        });
-        self.fetch(reqwest::Method::POST, &path, Some(body)).await
+impl Training {
+    /// Time spent waiting for a worker, from creation until the training started.
+    pub fn queue_duration(&self) -> Option<Duration> {
+        (self.started_at? - self.created_at).to_std().ok()
     }
 
-    pub async fn get_training(
-        &self,
-        model: &Model,
-        version: &str,
-        training_id: &str
-    ) -> Result<Training> {
-        let path = format!(
-            "/models/{}/{}/versions/{}/trainings/{}",
-            model.owner,
-            model.name,
-            version,
-            training_id
-        );
-        self.fetch(reqwest::Method::GET, &path, None).await
+    /// Time spent running, from start until the training completed.
+    pub fn run_duration(&self) -> Option<Duration> {
+        (self.completed_at? - self.started_at?).to_std().ok()
+    }
+
+    pub fn progress(&self) -> Option<PredictionProgress> {
+        self.logs.as_deref().and_then(parse_progress)
     }
+}
 
-    pub async fn cancel_training(
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateTrainingOptions {
+    /// The model to push the trained version to, as "owner/name".
+    pub destination: String,
+    pub input: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook_events_filter: Option<Vec<WebhookEventType>>,
+}
+
+impl Client {
+    pub async fn create_training(
         &self,
-        model: &Model,
-        version: &str,
-        training_id: &str
+        model_owner: &str,
+        model_name: &str,
+        version_id: &str,
+        options: CreateTrainingOptions
     ) -> Result<Training> {
         let path = format!(
-            "/models/{}/{}/versions/{}/trainings/{}/cancel",
-            model.owner,
-            model.name,
-            version,
-            training_id
+            "/models/{}/{}/versions/{}/trainings",
+            model_owner,
+            model_name,
+            version_id
         );
-        self.fetch(reqwest::Method::POST, &path, None).await
+        let body = serde_json::to_value(options)?;
+        let training = self.fetch(reqwest::Method::POST, &path, Some(body)).await?;
+        self.observe_training(&training);
+        Ok(training)
+    }
+
+    pub async fn list_trainings(&self) -> Result<Page<Training>> {
+        let page: Page<Training> = self.fetch(reqwest::Method::GET, "/trainings", None).await?;
+        page.results.iter().for_each(|training| self.observe_training(training));
+        Ok(page)
+    }
+
+    pub async fn get_training(&self, training_id: &str) -> Result<Training> {
+        let path = format!("/trainings/{}", training_id);
+        let training = self.fetch(reqwest::Method::GET, &path, None).await?;
+        self.observe_training(&training);
+        Ok(training)
+    }
+
+    pub async fn cancel_training(&self, training_id: &str) -> Result<Training> {
+        let path = format!("/trainings/{}/cancel", training_id);
+        let training = self.fetch(reqwest::Method::POST, &path, None).await?;
+        self.observe_training(&training);
+        Ok(training)
     }
 }
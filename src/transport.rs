@@ -0,0 +1,146 @@
+//! The HTTP layer under `Client`.
+//!
+//! Every request the client makes, including SSE streams and file uploads,
+//! goes through a `Transport`. `ReqwestTransport` is the default; pass another
+//! to `Client::with_transport` to add middleware, use a test double or a
+//! different HTTP stack. With the `tower` feature, any `tower::Service` over
+//! `HttpRequest` can be used through `TowerTransport`.
+
+use anyhow::{ Result, anyhow };
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::{ self, BoxStream, StreamExt };
+use reqwest::header::HeaderMap;
+use reqwest::{ Method, StatusCode, Url };
+
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub body: Option<Bytes>,
+}
+
+impl HttpRequest {
+    pub fn new(method: Method, url: Url) -> Self {
+        Self {
+            method,
+            url,
+            headers: HeaderMap::new(),
+            body: None,
+        }
+    }
+}
+
+/// A response whose body is read as a stream of chunks, so SSE bodies can be
+/// consumed as they arrive.
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: BoxStream<'static, Result<Bytes>>,
+}
+
+impl HttpResponse {
+    /// A response with the whole body in one chunk.
+    pub fn new(status: StatusCode, headers: HeaderMap, body: Bytes) -> Self {
+        Self {
+            status,
+            headers,
+            body: stream::once(async move { Ok(body) }).boxed(),
+        }
+    }
+
+    pub async fn bytes(mut self) -> Result<Bytes> {
+        let mut body = Vec::new();
+        while let Some(chunk) = self.body.next().await {
+            body.extend_from_slice(&chunk?);
+        }
+        Ok(body.into())
+    }
+
+    pub async fn text(self) -> Result<String> {
+        String::from_utf8(self.bytes().await?.to_vec()).map_err(|e| anyhow!(e))
+    }
+}
+
+pub trait Transport: Send + Sync {
+    /// Sends `request`. Non-2xx responses are returned as `Ok`; `Client`
+    /// decides what to retry and what is an error.
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    /// Uses a preconfigured client, e.g. one with custom TLS, proxies or
+    /// timeouts.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        Box::pin(async move {
+            let mut builder = self.client
+                .request(request.method, request.url)
+                .headers(request.headers);
+            if let Some(body) = request.body {
+                builder = builder.body(body);
+            }
+
+            let response = builder.send().await?;
+            Ok(HttpResponse {
+                status: response.status(),
+                headers: response.headers().clone(),
+                body: response
+                    .bytes_stream()
+                    .map(|chunk| chunk.map_err(anyhow::Error::from))
+                    .boxed(),
+            })
+        })
+    }
+}
+
+/// Adapts a `tower::Service` to `Transport`. The service is cloned for each
+/// request, as is usual for tower services. It only has to be `Send`, so
+/// boxed services such as `tower::util::BoxCloneService` work too.
+#[cfg(feature = "tower")]
+#[derive(Debug)]
+pub struct TowerTransport<S> {
+    // Held only while cloning, so a service that isn't `Sync` still makes a
+    // `Sync` transport.
+    service: std::sync::Mutex<S>,
+}
+
+#[cfg(feature = "tower")]
+impl<S> TowerTransport<S> {
+    pub fn new(service: S) -> Self {
+        Self { service: std::sync::Mutex::new(service) }
+    }
+}
+
+#[cfg(feature = "tower")]
+impl<S: Clone> Clone for TowerTransport<S> {
+    fn clone(&self) -> Self {
+        Self::new(self.service.lock().unwrap().clone())
+    }
+}
+
+#[cfg(feature = "tower")]
+impl<S> Transport for TowerTransport<S>
+    where
+        S: tower::Service<HttpRequest, Response = HttpResponse> + Clone + Send + 'static,
+        S::Future: Send,
+        S::Error: Into<tower::BoxError>
+{
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        use tower::ServiceExt;
+
+        let service = self.service.lock().unwrap().clone();
+        Box::pin(async move { service.oneshot(request).await.map_err(|e| anyhow!(e.into())) })
+    }
+}
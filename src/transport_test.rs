@@ -0,0 +1,151 @@
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use reqwest::header::AUTHORIZATION;
+    use super::super::client::Client;
+    use super::super::files::CreateFileOptions;
+    use super::super::status::Status;
+    use super::super::test_support::{ prediction, ScriptedTransport, PREDICTION_ID };
+    use super::super::transport::Transport;
+
+    fn client(transport: impl Transport + 'static) -> Client {
+        Client::new(Some("r8_token".to_string())).unwrap().with_transport(transport)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_goes_through_transport() {
+        let double = ScriptedTransport::ok(include_str!("fixtures/prediction.json"));
+        let prediction = client(double.clone()).get_prediction("abc").await.unwrap();
+        assert_eq!(prediction.id, "gm3qorzdhgbfurvjtvhg6dckhu");
+
+        let requests = double.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].url.as_str(), "https://api.replicate.com/v1/predictions/abc");
+        assert_eq!(requests[0].headers[AUTHORIZATION], "Bearer r8_token");
+    }
+
+    #[tokio::test]
+    async fn test_stream_goes_through_transport() {
+        let double = ScriptedTransport::ok("event: output\ndata: hi\n\nevent: done\ndata: {}\n\n");
+        let mut prediction = prediction(PREDICTION_ID, Status::Processing);
+        prediction.urls
+            .get_or_insert_with(Default::default)
+            .insert("stream".to_string(), "https://stream.example.com/abc".to_string());
+
+        let (events, _errors) = client(double.clone())
+            .stream_prediction(prediction, None).await
+            .unwrap();
+        let events: Vec<_> = events.collect().await;
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].to_string(), "hi");
+        assert_eq!(double.requests()[0].url.host_str(), Some("stream.example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_file_upload_goes_through_transport() {
+        let double = ScriptedTransport::ok(include_str!("fixtures/file.json"));
+        client(double.clone()).create_file_from_bytes(b"hello", None).await.unwrap();
+
+        let requests = double.requests();
+        let content_type = requests[0].headers["content-type"].to_str().unwrap();
+        assert!(content_type.starts_with("multipart/form-data; boundary="));
+        let body = String::from_utf8_lossy(requests[0].body.as_ref().unwrap()).into_owned();
+        assert!(body.contains("name=\"content\"; filename=\"file\""));
+        assert!(body.contains("\r\n\r\nhello\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_file_upload_rejects_unsafe_headers() {
+        let double = ScriptedTransport::ok(include_str!("fixtures/file.json"));
+        let client = client(double.clone());
+
+        for content_type in ["not a type", "text/plain\r\nX-Injected: 1"] {
+            let options = CreateFileOptions {
+                content_type: Some(content_type.to_string()),
+                ..Default::default()
+            };
+            let error = client.create_file_from_bytes(b"hello", Some(options)).await.unwrap_err();
+            assert!(error.to_string().contains("invalid content type"));
+        }
+        assert!(double.requests().is_empty());
+
+        let options = CreateFileOptions {
+            filename: Some("a\"b\r\nX-Injected: 1.txt".to_string()),
+            content_type: Some("text/plain; charset=utf-8".to_string()),
+            ..Default::default()
+        };
+        client.create_file_from_bytes(b"hello", Some(options)).await.unwrap();
+        let requests = double.requests();
+        let body = String::from_utf8_lossy(requests[0].body.as_ref().unwrap()).into_owned();
+        assert!(body.contains("filename=\"a%22b%0D%0AX-Injected: 1.txt\"\r\n"));
+        assert!(body.contains("Content-Type: text/plain; charset=utf-8\r\n"));
+    }
+
+    #[cfg(feature = "tower")]
+    #[tokio::test]
+    async fn test_tower_service() {
+        use std::sync::{ Arc, Mutex };
+        use bytes::Bytes;
+        use reqwest::StatusCode;
+        use super::super::test_support::response;
+        use super::super::transport::{ HttpRequest, TowerTransport };
+
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let service = tower::service_fn({
+            let paths = paths.clone();
+            move |request: HttpRequest| {
+                paths.lock().unwrap().push(request.url.path().to_string());
+                async move {
+                    let body = Bytes::from(include_str!("fixtures/prediction.json"));
+                    Ok::<_, anyhow::Error>(response(StatusCode::OK, body))
+                }
+            }
+        });
+
+        let prediction = client(TowerTransport::new(service)).get_prediction("abc").await.unwrap();
+        assert_eq!(prediction.model, "replicate/hello-world");
+        assert_eq!(*paths.lock().unwrap(), vec!["/v1/predictions/abc"]);
+    }
+
+    #[cfg(feature = "tower")]
+    #[tokio::test]
+    async fn test_tower_timeout() {
+        use std::time::Duration;
+        use bytes::Bytes;
+        use reqwest::StatusCode;
+        use tower::timeout::Timeout;
+        use super::super::test_support::response;
+        use super::super::transport::{ HttpRequest, TowerTransport };
+
+        let service = tower::service_fn(|_: HttpRequest| async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            let body = Bytes::from(include_str!("fixtures/prediction.json"));
+            Ok::<_, tower::BoxError>(response(StatusCode::OK, body))
+        });
+        let transport = TowerTransport::new(Timeout::new(service, Duration::from_millis(10)));
+
+        let error = client(transport).get_prediction("abc").await.unwrap_err();
+        assert!(error.to_string().contains("timed out"));
+    }
+
+    #[cfg(feature = "tower")]
+    #[tokio::test]
+    async fn test_tower_box_clone_service() {
+        use bytes::Bytes;
+        use reqwest::StatusCode;
+        use tower::util::BoxCloneService;
+        use super::super::test_support::response;
+        use super::super::transport::{ HttpRequest, HttpResponse, TowerTransport };
+
+        let service = tower::service_fn(|_: HttpRequest| async {
+            let body = Bytes::from(include_str!("fixtures/prediction.json"));
+            Ok::<_, tower::BoxError>(response(StatusCode::OK, body))
+        });
+        // Send but not Sync.
+        let service: BoxCloneService<HttpRequest, HttpResponse, _> = BoxCloneService::new(service);
+
+        let prediction = client(TowerTransport::new(service)).get_prediction("abc").await.unwrap();
+        assert_eq!(prediction.id, PREDICTION_ID);
+    }
+}
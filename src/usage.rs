@@ -0,0 +1,258 @@
+//! Usage and cost accounting.
+//!
+//! A `UsageTracker` attached with `Client::with_usage_tracker` sees every
+//! prediction and training the client creates, fetches, lists, cancels or
+//! waits on, and adds the metrics of each one to its model's totals the first
+//! time it's seen in a terminal state. Predictions run by version id alone
+//! have no model, so they're totaled under the version id instead. Replicate
+//! doesn't report which hardware a prediction ran on, so `PriceTable` maps
+//! models to hardware itself.
+
+use std::collections::{ BTreeMap, HashMap, HashSet, VecDeque };
+use std::sync::{ Arc, Mutex };
+use anyhow::Result;
+use serde::{ Deserialize, Serialize };
+use crate::hardware::Hardware;
+use crate::prediction::{ Prediction, PredictionMetrics };
+use crate::status::Status;
+use crate::training::Training;
+
+/// How many counted ids the tracker remembers. Seeing one again after this
+/// many newer ones would count it twice.
+const MAX_SEEN: usize = 10_000;
+
+/// Per-model totals.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub predictions: u64,
+    pub trainings: u64,
+    /// Sum of `predict_time`, which is what Replicate bills for.
+    pub compute_seconds: f64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl ModelUsage {
+    fn add(&mut self, metrics: Option<&PredictionMetrics>) {
+        let Some(metrics) = metrics else {
+            return;
+        };
+        self.compute_seconds += metrics.predict_time.unwrap_or_default();
+        self.input_tokens += metrics.input_token_count.unwrap_or_default().max(0) as u64;
+        self.output_tokens += metrics.output_token_count.unwrap_or_default().max(0) as u64;
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenPrice {
+    /// Price per input token.
+    #[serde(default)]
+    pub input: f64,
+    /// Price per output token.
+    #[serde(default)]
+    pub output: f64,
+}
+
+/// Prices used to estimate cost. Models priced per token use `tokens`;
+/// everything else is priced per second of the hardware it runs on.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PriceTable {
+    /// Price per second of compute, by hardware SKU.
+    #[serde(default)]
+    pub hardware: HashMap<Hardware, f64>,
+    /// The hardware each model runs on, by "owner/name" or version id.
+    #[serde(default)]
+    pub models: HashMap<String, Hardware>,
+    /// Hardware assumed for models missing from `models`.
+    #[serde(default)]
+    pub default_hardware: Option<Hardware>,
+    /// Per-token prices, by "owner/name" or version id.
+    #[serde(default)]
+    pub tokens: HashMap<String, TokenPrice>,
+}
+
+impl PriceTable {
+    pub fn hardware_for(&self, model: &str) -> Option<&Hardware> {
+        self.models.get(model).or(self.default_hardware.as_ref())
+    }
+
+    /// Estimated cost of `usage`, or `None` if the table has no price for the
+    /// model.
+    pub fn estimate(&self, model: &str, usage: &ModelUsage) -> Option<f64> {
+        if let Some(price) = self.tokens.get(model) {
+            return Some(
+                (usage.input_tokens as f64) * price.input +
+                    (usage.output_tokens as f64) * price.output
+            );
+        }
+
+        let per_second = self.hardware.get(self.hardware_for(model)?)?;
+        Some(usage.compute_seconds * per_second)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelUsageSummary {
+    pub model: String,
+    pub hardware: Option<Hardware>,
+    #[serde(flatten)]
+    pub usage: ModelUsage,
+    pub estimated_cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub models: Vec<ModelUsageSummary>,
+    pub total_compute_seconds: f64,
+    /// Sum of the models that could be priced.
+    pub total_estimated_cost: f64,
+}
+
+impl UsageSummary {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// One row per model, with a header row.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            concat!(
+                "model,hardware,predictions,trainings,compute_seconds,",
+                "input_tokens,output_tokens,estimated_cost\n"
+            )
+        );
+        for row in &self.models {
+            let fields = [
+                csv_field(&row.model),
+                csv_field(&row.hardware.as_ref().map(|h| h.to_string()).unwrap_or_default()),
+                row.usage.predictions.to_string(),
+                row.usage.trainings.to_string(),
+                row.usage.compute_seconds.to_string(),
+                row.usage.input_tokens.to_string(),
+                row.usage.output_tokens.to_string(),
+                row.estimated_cost.map(|cost| cost.to_string()).unwrap_or_default(),
+            ];
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Per-model counts, compute seconds and tokens for every prediction and
+/// training a client sees finish. Each is counted once however often it's
+/// polled. Read the totals with `usage`, or price them with `summary`.
+#[derive(Debug, Clone, Default)]
+pub struct UsageTracker {
+    inner: Arc<Mutex<UsageState>>,
+}
+
+#[derive(Debug, Default)]
+struct UsageState {
+    // Predictions and trainings already counted, so seeing one again while
+    // polling or listing doesn't count it twice. `seen_order` holds the same
+    // keys oldest first, to forget the oldest past `MAX_SEEN`.
+    seen: HashSet<String>,
+    seen_order: VecDeque<String>,
+    models: BTreeMap<String, ModelUsage>,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_prediction(&self, prediction: &Prediction) {
+        self.record(
+            format!("prediction/{}", prediction.id),
+            usage_key(&prediction.model, &prediction.version),
+            &prediction.status,
+            prediction.metrics.as_ref(),
+            |usage| {
+                usage.predictions += 1;
+            }
+        );
+    }
+
+    pub fn record_training(&self, training: &Training) {
+        self.record(
+            format!("training/{}", training.id),
+            usage_key(&training.model, &training.version),
+            &training.status,
+            training.metrics.as_ref(),
+            |usage| {
+                usage.trainings += 1;
+            }
+        );
+    }
+
+    fn record(
+        &self,
+        key: String,
+        model: &str,
+        status: &Status,
+        metrics: Option<&PredictionMetrics>,
+        count: impl FnOnce(&mut ModelUsage)
+    ) {
+        if !status.is_terminated() {
+            return;
+        }
+
+        let mut state = self.inner.lock().unwrap();
+        if !state.seen.insert(key.clone()) {
+            return;
+        }
+        state.seen_order.push_back(key);
+        if state.seen_order.len() > MAX_SEEN {
+            if let Some(oldest) = state.seen_order.pop_front() {
+                state.seen.remove(&oldest);
+            }
+        }
+        let usage = state.models.entry(model.to_string()).or_default();
+        count(usage);
+        usage.add(metrics);
+    }
+
+    /// Totals so far, by "owner/name", or by version id for predictions run
+    /// without a model.
+    pub fn usage(&self) -> BTreeMap<String, ModelUsage> {
+        self.inner.lock().unwrap().models.clone()
+    }
+
+    pub fn summary(&self, prices: &PriceTable) -> UsageSummary {
+        let mut summary = UsageSummary::default();
+
+        for (model, usage) in self.usage() {
+            let estimated_cost = prices.estimate(&model, &usage);
+            summary.total_compute_seconds += usage.compute_seconds;
+            summary.total_estimated_cost += estimated_cost.unwrap_or_default();
+            summary.models.push(ModelUsageSummary {
+                hardware: prices.hardware_for(&model).cloned(),
+                model,
+                usage,
+                estimated_cost,
+            });
+        }
+
+        summary
+    }
+
+    pub fn reset(&self) {
+        let mut state = self.inner.lock().unwrap();
+        state.seen.clear();
+        state.seen_order.clear();
+        state.models.clear();
+    }
+}
+
+fn usage_key<'a>(model: &'a str, version: &'a str) -> &'a str {
+    if model.is_empty() { version } else { model }
+}
@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod tests {
+    use super::super::hardware::Hardware;
+    use super::super::status::Status;
+    use super::super::test_support::prediction;
+    use super::super::training::Training;
+    use super::super::usage::{ PriceTable, TokenPrice, UsageTracker };
+
+    #[test]
+    fn test_counts_each_completed_prediction_once() {
+        let tracker = UsageTracker::new();
+        tracker.record_prediction(&prediction("a", Status::Processing));
+        tracker.record_prediction(&prediction("a", Status::Succeeded));
+        tracker.record_prediction(&prediction("a", Status::Succeeded));
+        tracker.record_prediction(&prediction("b", Status::Failed));
+
+        let usage = &tracker.usage()["replicate/hello-world"];
+        assert_eq!(usage.predictions, 2);
+        assert!((usage.compute_seconds - 2.0 * 0.012683).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_version_only_predictions_count_by_version() {
+        let tracker = UsageTracker::new();
+        let mut prediction = prediction("a", Status::Succeeded);
+        prediction.model = String::new();
+        tracker.record_prediction(&prediction);
+
+        let usage = tracker.usage();
+        assert!(!usage.contains_key(""));
+        assert_eq!(usage[&prediction.version].predictions, 1);
+    }
+
+    #[test]
+    fn test_forgets_oldest_counted_ids() {
+        let tracker = UsageTracker::new();
+        for i in 0..=10_000 {
+            tracker.record_prediction(&prediction(&i.to_string(), Status::Succeeded));
+        }
+        tracker.record_prediction(&prediction("10000", Status::Succeeded));
+        assert_eq!(tracker.usage()["replicate/hello-world"].predictions, 10_001);
+
+        // Only the oldest id has been forgotten, so it counts again.
+        tracker.record_prediction(&prediction("1", Status::Succeeded));
+        tracker.record_prediction(&prediction("0", Status::Succeeded));
+        assert_eq!(tracker.usage()["replicate/hello-world"].predictions, 10_002);
+    }
+
+    #[test]
+    fn test_trainings_without_metrics() {
+        let tracker = UsageTracker::new();
+        let mut training: Training = serde_json
+            ::from_str(include_str!("fixtures/training.json"))
+            .unwrap();
+        tracker.record_training(&training);
+        assert!(tracker.usage().is_empty());
+
+        training.status = Status::Succeeded;
+        tracker.record_training(&training);
+        let usage = &tracker.usage()["stability-ai/sdxl"];
+        assert_eq!(usage.trainings, 1);
+        assert_eq!(usage.compute_seconds, 0.0);
+    }
+
+    #[test]
+    fn test_price_table() {
+        let prices: PriceTable = serde_json
+            ::from_value(
+                serde_json::json!({
+                    "hardware": { "gpu-t4": 0.000225, "cpu": 0.0001 },
+                    "models": { "stability-ai/sdxl": "gpu-t4" },
+                    "default_hardware": "cpu",
+                    "tokens": { "meta/llama": { "input": 0.000001, "output": 0.000005 } }
+                })
+            )
+            .unwrap();
+
+        assert_eq!(prices.hardware_for("stability-ai/sdxl"), Some(&Hardware::GpuT4));
+        assert_eq!(prices.hardware_for("other/model"), Some(&Hardware::Cpu));
+        assert_eq!(prices.tokens["meta/llama"], TokenPrice { input: 0.000001, output: 0.000005 });
+    }
+
+    #[test]
+    fn test_summary_exports() {
+        let tracker = UsageTracker::new();
+        tracker.record_prediction(&prediction("a", Status::Succeeded));
+        let mut unpriced = prediction("b", Status::Succeeded);
+        unpriced.model = "acme/\"quoted\",model".to_string();
+        tracker.record_prediction(&unpriced);
+
+        let mut prices = PriceTable::default();
+        prices.hardware.insert(Hardware::Cpu, 0.0001);
+        prices.models.insert("replicate/hello-world".to_string(), Hardware::Cpu);
+
+        let summary = tracker.summary(&prices);
+        assert_eq!(summary.models.len(), 2);
+        assert!((summary.total_estimated_cost - 0.012683 * 0.0001).abs() < 1e-12);
+
+        let json: serde_json::Value = serde_json::from_str(&summary.to_json().unwrap()).unwrap();
+        assert_eq!(json["models"][1]["model"], "replicate/hello-world");
+        assert_eq!(json["models"][1]["predictions"], 1);
+
+        let csv = summary.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("model,hardware,predictions"));
+        assert!(lines[1].starts_with("\"acme/\"\"quoted\"\",model\",,1,0,"));
+        assert!(lines[1].ends_with(','));
+        assert!(lines[2].starts_with("replicate/hello-world,cpu,1,0,0.012683,0,0,"));
+    }
+}
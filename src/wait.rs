@@ -1,14 +1,119 @@
-use std::time::Duration;
+use std::collections::{ HashMap, VecDeque };
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
 use anyhow::{ Result, anyhow };
+use tokio::sync::oneshot;
 use tokio::time::sleep;
 use crate::client::Client;
 use crate::prediction::Prediction;
+use crate::status::Status;
+use crate::telemetry;
+use crate::training::Training;
+use crate::webhook::{ Webhook, WebhookEventType };
 
 const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3600); // 1 hour
+const DEFAULT_DELIVERY_TIMEOUT: Duration = Duration::from_secs(300);
+const MAX_UNCLAIMED_DELIVERIES: usize = 1024;
+
+/// Completes waits from `completed` webhook deliveries instead of polling.
+///
+/// The waiter owns a public URL (a tunnel or ingress that routes to your
+/// receiver) and a map from prediction id to the task waiting on it. Feed it
+/// deliveries with `deliver`, or attach it to a `WebhookReceiver` with
+/// `WebhookReceiver::with_waiter`. Each time the delivery timeout passes
+/// without a delivery, the wait polls the prediction once and, if it's still
+/// running, goes back to waiting for the delivery.
+#[derive(Clone)]
+pub struct WebhookWaiter {
+    inner: Arc<WaiterState>,
+}
+
+struct WaiterState {
+    url: String,
+    delivery_timeout: Duration,
+    pending: Mutex<HashMap<String, oneshot::Sender<Prediction>>>,
+    // Deliveries that arrived before anyone registered for them, which
+    // happens when a prediction finishes before `create_prediction` returns.
+    unclaimed: Mutex<VecDeque<Prediction>>,
+}
+
+impl WebhookWaiter {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self::with_delivery_timeout(url, DEFAULT_DELIVERY_TIMEOUT)
+    }
+
+    pub fn with_delivery_timeout(url: impl Into<String>, delivery_timeout: Duration) -> Self {
+        Self {
+            inner: Arc::new(WaiterState {
+                url: url.into(),
+                delivery_timeout,
+                pending: Mutex::new(HashMap::new()),
+                unclaimed: Mutex::new(VecDeque::new()),
+            }),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.inner.url
+    }
+
+    /// The webhook to attach to predictions that should be awaited here.
+    pub fn webhook(&self) -> Webhook {
+        Webhook {
+            url: self.inner.url.clone(),
+            events: vec![WebhookEventType::Completed],
+        }
+    }
+
+    /// Hands a delivered prediction to whoever is waiting on it. Deliveries
+    /// for predictions that haven't finished are ignored.
+    pub fn deliver(&self, prediction: Prediction) {
+        if !prediction.status.is_terminated() {
+            return;
+        }
+
+        // Lock order matches `register` so a delivery can't slip between the
+        // pending lookup and the unclaimed queue.
+        let mut unclaimed = self.inner.unclaimed.lock().unwrap();
+        let sender = self.inner.pending.lock().unwrap().remove(&prediction.id);
+        match sender {
+            Some(sender) => {
+                let _ = sender.send(prediction);
+            }
+            None => {
+                if unclaimed.len() >= MAX_UNCLAIMED_DELIVERIES {
+                    unclaimed.pop_front();
+                }
+                unclaimed.push_back(prediction);
+            }
+        }
+    }
+
+    pub(crate) fn register(&self, prediction_id: &str) -> oneshot::Receiver<Prediction> {
+        let (tx, rx) = oneshot::channel();
+
+        let mut unclaimed = self.inner.unclaimed.lock().unwrap();
+        if let Some(pos) = unclaimed.iter().position(|p| p.id == prediction_id) {
+            let _ = tx.send(unclaimed.remove(pos).unwrap());
+        } else {
+            self.inner.pending.lock().unwrap().insert(prediction_id.to_string(), tx);
+        }
+
+        rx
+    }
+
+    pub(crate) fn unregister(&self, prediction_id: &str) {
+        self.inner.pending.lock().unwrap().remove(prediction_id);
+    }
+
+    pub(crate) fn delivery_timeout(&self) -> Duration {
+        self.inner.delivery_timeout
+    }
+}
 
 impl Client {
-    pub async fn wait(&self, prediction: &Prediction) -> Result<()> {
+    pub async fn wait(&self, prediction: &Prediction) -> Result<Prediction> {
         self.wait_with_options(prediction, DEFAULT_POLL_INTERVAL, DEFAULT_TIMEOUT).await
     }
 
@@ -17,17 +122,56 @@ impl Client {
         prediction: &Prediction,
         poll_interval: Duration,
         timeout: Duration
-    ) -> Result<()> {
-        let start = std::time::Instant::now();
+    ) -> Result<Prediction> {
+        if prediction.status.is_terminated() {
+            return Ok(prediction.clone());
+        }
+
+        let start = Instant::now();
+        let span = telemetry::wait_span("prediction", &prediction.id);
+        let result = telemetry::instrument(
+            self.wait_for_prediction(prediction, poll_interval, timeout),
+            span
+        ).await;
+        telemetry::record_wait("prediction", outcome(&result, |p| p.status), start.elapsed());
+        if let Some(reporter) = &self.progress_reporter {
+            reporter.finish(&prediction.id);
+        }
+        result
+    }
+
+    async fn wait_for_prediction(
+        &self,
+        prediction: &Prediction,
+        poll_interval: Duration,
+        timeout: Duration
+    ) -> Result<Prediction> {
+        let start = Instant::now();
         let mut current_prediction = prediction.clone();
 
+        if let Some(waiter) = &self.webhook_waiter {
+            if prediction.webhook.as_deref() == Some(waiter.url()) {
+                match self.wait_for_delivery(waiter, prediction, timeout).await? {
+                    Some(prediction) => {
+                        return Ok(prediction);
+                    }
+                    None => {
+                        current_prediction = self.get_prediction(&prediction.id).await?;
+                    }
+                }
+            }
+        }
+
         loop {
             if start.elapsed() > timeout {
                 return Err(anyhow!("Timeout waiting for prediction to complete"));
             }
 
+            if let Some(reporter) = &self.progress_reporter {
+                reporter.record_prediction(&current_prediction);
+            }
             if current_prediction.status.is_terminated() {
-                return Ok(());
+                return Ok(current_prediction);
             }
 
             sleep(poll_interval).await;
@@ -35,7 +179,114 @@ impl Client {
         }
     }
 
-    // pub(crate) async fn get_prediction(&self, prediction_id: &str) -> Result<Prediction> {
-    //     self.fetch(reqwest::Method::GET, &format!("/predictions/{}", prediction_id), None).await
-    // }
+    /// Waits for `prediction`'s webhook delivery, polling it once every
+    /// delivery timeout in case the delivery was lost. Returns `None` if the
+    /// waiter dropped the registration, leaving the caller to poll.
+    async fn wait_for_delivery(
+        &self,
+        waiter: &WebhookWaiter,
+        prediction: &Prediction,
+        timeout: Duration
+    ) -> Result<Option<Prediction>> {
+        let start = Instant::now();
+        let mut delivery = waiter.register(&prediction.id);
+
+        loop {
+            let remaining = timeout.saturating_sub(start.elapsed());
+            if remaining.is_zero() {
+                waiter.unregister(&prediction.id);
+                return Err(anyhow!("Timeout waiting for prediction to complete"));
+            }
+
+            let delivery_timeout = waiter.delivery_timeout().min(remaining);
+            match tokio::time::timeout(delivery_timeout, &mut delivery).await {
+                Ok(Ok(prediction)) => {
+                    self.observe_prediction(&prediction).await;
+                    if let Some(reporter) = &self.progress_reporter {
+                        reporter.record_prediction(&prediction);
+                    }
+                    return Ok(Some(prediction));
+                }
+                Ok(Err(_)) => {
+                    return Ok(None);
+                }
+                Err(_) => {
+                    log::warn!(
+                        "No webhook delivery for prediction {} after {:?}, checking on it",
+                        prediction.id,
+                        delivery_timeout
+                    );
+                    match self.get_prediction(&prediction.id).await {
+                        Ok(current) => {
+                            if let Some(reporter) = &self.progress_reporter {
+                                reporter.record_prediction(&current);
+                            }
+                            if current.status.is_terminated() {
+                                waiter.unregister(&prediction.id);
+                                return Ok(Some(current));
+                            }
+                        }
+                        Err(e) => log::warn!("Failed to poll prediction {}: {}", prediction.id, e),
+                    }
+                }
+            }
+        }
+    }
+
+    pub async fn wait_training(&self, training: &Training) -> Result<Training> {
+        self.wait_training_with_options(training, DEFAULT_POLL_INTERVAL, DEFAULT_TIMEOUT).await
+    }
+
+    pub async fn wait_training_with_options(
+        &self,
+        training: &Training,
+        poll_interval: Duration,
+        timeout: Duration
+    ) -> Result<Training> {
+        let start = Instant::now();
+        let span = telemetry::wait_span("training", &training.id);
+        let result = telemetry::instrument(
+            self.wait_for_training(training, poll_interval, timeout),
+            span
+        ).await;
+        telemetry::record_wait("training", outcome(&result, |t| t.status), start.elapsed());
+        if let Some(reporter) = &self.progress_reporter {
+            reporter.finish(&training.id);
+        }
+        result
+    }
+
+    async fn wait_for_training(
+        &self,
+        training: &Training,
+        poll_interval: Duration,
+        timeout: Duration
+    ) -> Result<Training> {
+        let start = Instant::now();
+        let mut current_training = training.clone();
+
+        loop {
+            if start.elapsed() > timeout {
+                return Err(anyhow!("Timeout waiting for training to complete"));
+            }
+
+            if let Some(reporter) = &self.progress_reporter {
+                reporter.record_training(&current_training);
+            }
+            if current_training.status.is_terminated() {
+                return Ok(current_training);
+            }
+
+            sleep(poll_interval).await;
+            current_training = self.get_training(&current_training.id).await?;
+        }
+    }
+}
+
+/// The final status of a wait, or `error`.
+fn outcome<T>(result: &Result<T>, status: impl Fn(&T) -> Status) -> String {
+    match result {
+        Ok(value) => status(value).to_string(),
+        Err(_) => "error".to_string(),
+    }
 }
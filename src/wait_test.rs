@@ -0,0 +1,112 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use reqwest::StatusCode;
+    use super::super::client::Client;
+    use super::super::prediction::Prediction;
+    use super::super::status::Status;
+    use super::super::test_support::{
+        json_response,
+        prediction,
+        ScriptedTransport,
+        PREDICTION_ID,
+    };
+    use super::super::wait::WebhookWaiter;
+
+    const HOOK: &str = "https://example.com/hook";
+
+    /// A client whose every prediction fetch answers with `status`.
+    fn client(status: Status, waiter: &WebhookWaiter) -> (Client, ScriptedTransport) {
+        let api = ScriptedTransport::new(move |_| {
+            json_response(StatusCode::OK, &prediction(PREDICTION_ID, status))
+        });
+        (api.client().with_webhook_waiter(waiter.clone()), api)
+    }
+
+    fn started() -> Prediction {
+        let mut prediction = prediction(PREDICTION_ID, Status::Starting);
+        prediction.webhook = Some(HOOK.to_string());
+        prediction
+    }
+
+    #[tokio::test]
+    async fn test_delivery_completes_registered_wait() {
+        let waiter = WebhookWaiter::new("https://example.com/hook");
+        let delivery = waiter.register(PREDICTION_ID);
+
+        waiter.deliver(prediction(PREDICTION_ID, Status::Processing));
+        waiter.deliver(prediction(PREDICTION_ID, Status::Succeeded));
+
+        assert_eq!(delivery.await.unwrap().status, Status::Succeeded);
+    }
+
+    #[tokio::test]
+    async fn test_delivery_before_registration() {
+        let waiter = WebhookWaiter::new("https://example.com/hook");
+        waiter.deliver(prediction(PREDICTION_ID, Status::Failed));
+
+        let delivery = waiter.register(PREDICTION_ID);
+        assert_eq!(delivery.await.unwrap().status, Status::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_wait_polls_when_delivery_times_out() {
+        let waiter = WebhookWaiter::with_delivery_timeout(HOOK, Duration::from_millis(20));
+        let (client, api) = client(Status::Succeeded, &waiter);
+
+        let prediction = client
+            .wait_with_options(&started(), Duration::from_millis(1), Duration::from_secs(5)).await
+            .unwrap();
+        assert_eq!(prediction.status, Status::Succeeded);
+        assert_eq!(api.requests().len(), 1);
+
+        // The timed-out wait no longer holds a slot, so a late delivery is
+        // buffered for the next wait on that prediction.
+        waiter.deliver(prediction.clone());
+        let delivery = waiter.register(&prediction.id);
+        assert_eq!(delivery.await.unwrap().status, Status::Succeeded);
+    }
+
+    #[tokio::test]
+    async fn test_wait_keeps_waiting_for_late_delivery() {
+        let waiter = WebhookWaiter::with_delivery_timeout(HOOK, Duration::from_millis(20));
+        let (client, api) = client(Status::Processing, &waiter);
+
+        let late = waiter.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(70)).await;
+            let mut delivered = prediction(PREDICTION_ID, Status::Succeeded);
+            delivered.output = Some(serde_json::json!("from the webhook"));
+            late.deliver(delivered);
+        });
+
+        let prediction = client
+            .wait_with_options(&started(), Duration::from_millis(1), Duration::from_secs(5)).await
+            .unwrap();
+        assert_eq!(prediction.output, Some(serde_json::json!("from the webhook")));
+        // One poll per delivery timeout, not one per poll interval.
+        let polls = api.requests().len();
+        assert!((1..=4).contains(&polls), "{} polls", polls);
+    }
+
+    #[tokio::test]
+    async fn test_wait_claims_delivery_that_arrived_first() {
+        let waiter = WebhookWaiter::with_delivery_timeout(HOOK, Duration::from_secs(5));
+        let (client, api) = client(Status::Processing, &waiter);
+        waiter.deliver(prediction(PREDICTION_ID, Status::Failed));
+
+        let prediction = client
+            .wait_with_options(&started(), Duration::from_millis(1), Duration::from_secs(5)).await
+            .unwrap();
+        assert_eq!(prediction.status, Status::Failed);
+        assert!(api.requests().is_empty());
+    }
+
+    #[test]
+    fn test_webhook_filters_completed_events() {
+        let waiter = WebhookWaiter::new("https://example.com/hook");
+        let webhook = waiter.webhook();
+        assert_eq!(webhook.url, "https://example.com/hook");
+        assert_eq!(serde_json::to_value(webhook.events).unwrap(), serde_json::json!(["completed"]));
+    }
+}
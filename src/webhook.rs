@@ -1,33 +1,206 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{ DateTime, Utc };
+use hmac::{ Hmac, Mac };
+use reqwest::header::HeaderMap;
 use serde::{ Deserialize, Serialize };
+use sha2::Sha256;
+use thiserror::Error;
 use anyhow::Result;
 use crate::client::Client;
 use crate::paginate::Page;
 
+/// How far a webhook's timestamp may drift from the local clock before it is
+/// rejected as a possible replay.
+pub const DEFAULT_WEBHOOK_TOLERANCE: Duration = Duration::from_secs(5 * 60);
+
+const WEBHOOK_SECRET_PREFIX: &str = "whsec_";
+const WEBHOOK_SIGNATURE_VERSION: &str = "v1";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Webhook {
     pub url: String,
-    pub events: Vec<String>,
+    pub events: Vec<WebhookEventType>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebhookEvent {
     pub id: String,
-    pub created_at: String,
+    pub created_at: DateTime<Utc>,
     pub destination: String,
     pub event_type: String,
+    #[serde(default)]
     pub data: serde_json::Value,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum WebhookEventType {
+    #[serde(rename = "start")]
     Start,
+    #[serde(rename = "output")]
     Output,
+    #[serde(rename = "logs")]
     Logs,
+    #[serde(rename = "completed")]
     Completed,
-    // Add any other event types as needed
+    /// An event type this version of the client doesn't know about yet.
+    #[serde(untagged)]
+    Other(String),
+}
+
+impl WebhookEventType {
+    /// Every event type the API currently sends.
+    pub const ALL: [WebhookEventType; 4] = [
+        WebhookEventType::Start,
+        WebhookEventType::Output,
+        WebhookEventType::Logs,
+        WebhookEventType::Completed,
+    ];
+}
+
+impl std::fmt::Display for WebhookEventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookEventType::Start => write!(f, "start"),
+            WebhookEventType::Output => write!(f, "output"),
+            WebhookEventType::Logs => write!(f, "logs"),
+            WebhookEventType::Completed => write!(f, "completed"),
+            WebhookEventType::Other(event_type) => write!(f, "{}", event_type),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookSigningSecret {
+    pub key: String,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WebhookValidationError {
+    #[error("missing webhook header \"{0}\"")]
+    MissingHeader(&'static str),
+    #[error("invalid webhook timestamp")]
+    InvalidTimestamp,
+    #[error("webhook timestamp is outside the allowed tolerance")]
+    TimestampOutsideTolerance,
+    #[error("invalid webhook signing secret")]
+    InvalidSecret,
+    #[error("no matching webhook signature found")]
+    InvalidSignature,
+}
+
+/// Verifies that a webhook request was sent by Replicate.
+///
+/// `body` must be the raw request body, before any JSON parsing.
+pub fn validate_webhook(
+    headers: &HeaderMap,
+    body: &[u8],
+    secret: &WebhookSigningSecret
+) -> Result<(), WebhookValidationError> {
+    validate_webhook_with_tolerance(headers, body, secret, DEFAULT_WEBHOOK_TOLERANCE)
+}
+
+pub fn validate_webhook_with_tolerance(
+    headers: &HeaderMap,
+    body: &[u8],
+    secret: &WebhookSigningSecret,
+    tolerance: Duration
+) -> Result<(), WebhookValidationError> {
+    validate_webhook_at(headers, body, secret, tolerance, Utc::now())
+}
+
+pub(crate) fn validate_webhook_at(
+    headers: &HeaderMap,
+    body: &[u8],
+    secret: &WebhookSigningSecret,
+    tolerance: Duration,
+    now: DateTime<Utc>
+) -> Result<(), WebhookValidationError> {
+    let id = header(headers, "webhook-id")?;
+    let timestamp = header(headers, "webhook-timestamp")?;
+    let signatures = header(headers, "webhook-signature")?;
+
+    let sent_at = timestamp
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .ok_or(WebhookValidationError::InvalidTimestamp)?;
+    let drift = (now - sent_at).abs().to_std().unwrap_or(Duration::MAX);
+    if drift > tolerance {
+        return Err(WebhookValidationError::TimestampOutsideTolerance);
+    }
+
+    let mac = webhook_mac(id, timestamp, body, secret)?;
+
+    // The header holds space-separated "version,signature" pairs so the
+    // secret can be rotated without dropping deliveries.
+    let matched = signatures
+        .split(' ')
+        .filter_map(|entry| entry.split_once(','))
+        .filter(|(version, _)| *version == WEBHOOK_SIGNATURE_VERSION)
+        .filter_map(|(_, signature)| BASE64.decode(signature).ok())
+        .any(|signature| mac.clone().verify_slice(&signature).is_ok());
+
+    if matched {
+        Ok(())
+    } else {
+        Err(WebhookValidationError::InvalidSignature)
+    }
+}
+
+fn webhook_mac(
+    id: &str,
+    timestamp: &str,
+    body: &[u8],
+    secret: &WebhookSigningSecret
+) -> Result<Hmac<Sha256>, WebhookValidationError> {
+    let key = BASE64.decode(
+        secret.key.strip_prefix(WEBHOOK_SECRET_PREFIX).unwrap_or(&secret.key)
+    ).map_err(|_| WebhookValidationError::InvalidSecret)?;
+
+    let mut mac = Hmac::<Sha256>
+        ::new_from_slice(&key)
+        .map_err(|_| WebhookValidationError::InvalidSecret)?;
+    mac.update(id.as_bytes());
+    mac.update(b".");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    Ok(mac)
+}
+
+/// The `webhook-signature` header value Replicate would send for a delivery.
+#[cfg_attr(not(feature = "testing"), allow(dead_code))]
+pub(crate) fn sign_webhook(
+    id: &str,
+    timestamp: &str,
+    body: &[u8],
+    secret: &WebhookSigningSecret
+) -> Result<String, WebhookValidationError> {
+    let signature = webhook_mac(id, timestamp, body, secret)?.finalize().into_bytes();
+    Ok(format!("{},{}", WEBHOOK_SIGNATURE_VERSION, BASE64.encode(signature)))
+}
+
+fn header<'a>(
+    headers: &'a HeaderMap,
+    name: &'static str
+) -> Result<&'a str, WebhookValidationError> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(WebhookValidationError::MissingHeader(name))
 }
 
 impl Client {
+    /// Returns the secret used to sign webhooks sent to this account.
+    pub async fn get_default_webhook_secret(&self) -> Result<WebhookSigningSecret> {
+        self.fetch(reqwest::Method::GET, "/webhooks/default/secret", None).await
+    }
+
     pub async fn list_webhook_events(&self) -> Result<Page<WebhookEvent>> {
         self.fetch(reqwest::Method::GET, "/webhook-events", None).await
     }
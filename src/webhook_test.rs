@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod tests {
+    use super::super::webhook::{
+        validate_webhook_at,
+        Webhook,
+        WebhookEventType,
+        WebhookSigningSecret,
+        WebhookValidationError,
+        DEFAULT_WEBHOOK_TOLERANCE,
+    };
+    use chrono::{ DateTime, Utc };
+    use reqwest::header::HeaderMap;
+    use serde_json::json;
+
+    const BODY: &[u8] = br#"{"test": 2432232314}"#;
+    const TIMESTAMP: i64 = 1614265330;
+
+    fn secret() -> WebhookSigningSecret {
+        WebhookSigningSecret { key: "whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw".to_string() }
+    }
+
+    fn headers(signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("webhook-id", "msg_p5jXN8AQM9LWM0D4loKWxJek".parse().unwrap());
+        headers.insert("webhook-timestamp", TIMESTAMP.to_string().parse().unwrap());
+        headers.insert("webhook-signature", signature.parse().unwrap());
+        headers
+    }
+
+    fn validate(
+        headers: &HeaderMap,
+        body: &[u8],
+        offset: i64
+    ) -> Result<(), WebhookValidationError> {
+        let now: DateTime<Utc> = DateTime::from_timestamp(TIMESTAMP + offset, 0).unwrap();
+        validate_webhook_at(headers, body, &secret(), DEFAULT_WEBHOOK_TOLERANCE, now)
+    }
+
+    #[test]
+    fn test_valid_signature() {
+        let headers = headers("v1,g0hM9SsE+OTPJTGt/tmIKtSyZlE3uFJELVlNIOLJ1OE=");
+        let result = validate(&headers, BODY, 10);
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_one_of_multiple_signatures() {
+        let headers = headers(
+            &[
+                "v1,bm9wZQ==",
+                "v1a,g0hM9SsE+OTPJTGt/tmIKtSyZlE3uFJELVlNIOLJ1OE=",
+                "v1,g0hM9SsE+OTPJTGt/tmIKtSyZlE3uFJELVlNIOLJ1OE=",
+            ].join(" ")
+        );
+        let result = validate(&headers, BODY, 0);
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_tampered_body() {
+        let headers = headers("v1,g0hM9SsE+OTPJTGt/tmIKtSyZlE3uFJELVlNIOLJ1OE=");
+        let result = validate(&headers, br#"{"test": 1}"#, 0);
+        assert_eq!(result, Err(WebhookValidationError::InvalidSignature));
+    }
+
+    #[test]
+    fn test_replayed_timestamp() {
+        let headers = headers("v1,g0hM9SsE+OTPJTGt/tmIKtSyZlE3uFJELVlNIOLJ1OE=");
+        let result = validate(&headers, BODY, 3600);
+        assert_eq!(result, Err(WebhookValidationError::TimestampOutsideTolerance));
+    }
+
+    #[test]
+    fn test_missing_header() {
+        let mut headers = headers("v1,g0hM9SsE+OTPJTGt/tmIKtSyZlE3uFJELVlNIOLJ1OE=");
+        headers.remove("webhook-id");
+        let result = validate(&headers, BODY, 0);
+        assert_eq!(result, Err(WebhookValidationError::MissingHeader("webhook-id")));
+    }
+
+    #[test]
+    fn test_event_type_serialization() {
+        let webhook = Webhook {
+            url: "https://example.com/hook".to_string(),
+            events: vec![WebhookEventType::Start, WebhookEventType::Completed],
+        };
+        assert_eq!(
+            serde_json::to_value(webhook).unwrap(),
+            json!({ "url": "https://example.com/hook", "events": ["start", "completed"] })
+        );
+    }
+
+    #[test]
+    fn test_unknown_event_type() {
+        let events: Vec<WebhookEventType> = serde_json
+            ::from_value(json!(["output", "queued"]))
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![WebhookEventType::Output, WebhookEventType::Other("queued".to_string())]
+        );
+        assert_eq!(serde_json::to_value(&events).unwrap(), json!(["output", "queued"]));
+    }
+}